@@ -0,0 +1,41 @@
+#[derive(Debug, thiserror::Error)]
+pub enum ParseError {
+    #[error("Invalid GPX: {0}")]
+    InvalidGpx(String),
+    #[error("Invalid FIT: {0}")]
+    InvalidFit(String),
+    #[error("No track points found in file")]
+    EmptyFile,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ProcessError {
+    #[error("Insufficient data points (need at least 2, got {0})")]
+    InsufficientPoints(usize),
+    #[error("Only {valid} of {total} points have valid coordinates (need at least 2)")]
+    InvalidCoordinates { valid: usize, total: usize },
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum PrepareError {
+    #[error("No {0} data available in this activity")]
+    MissingData(&'static str),
+    #[error("Route ends {0:.1} km from its start; only loops can start at an offset")]
+    NotALoop(f64),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum RenderError {
+    #[error("SVG generation failed: {0}")]
+    SvgError(String),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum RasterError {
+    #[error("PNG rendering failed: {0}")]
+    RenderFailed(String),
+    #[error("Animation rendering failed: {0}")]
+    AnimationFailed(String),
+    #[error("Export cancelled")]
+    Cancelled,
+}
@@ -0,0 +1,44 @@
+//! Renders GPX/FIT activities as 3D route overlays, as static PNGs or animated APNGs.
+//!
+//! This is the pipeline behind the `rideviz-rs` server, with no HTTP dependencies. An
+//! activity goes through [`pipeline::parse`], [`pipeline::process`] (metrics and
+//! downsampling) and [`pipeline::prepare`] (projection for the chosen [`RenderOptions`]),
+//! then either [`pipeline::render`] plus [`pipeline::rasterize`] for a single frame or
+//! [`pipeline::animate`] for the whole animation, whose frames are drawn without SVG by
+//! [`pipeline::draw`]. [`pipeline::stats`] summarizes a processed activity (splits,
+//! climbs, zones, peaks) for stats panels.
+//!
+//! ```no_run
+//! use std::sync::atomic::AtomicBool;
+//!
+//! use rideviz_core::pipeline::{animate, parse, prepare, process};
+//! use rideviz_core::types::activity::FileFormat;
+//! use rideviz_core::types::viz::{OutputConfig, RenderOptions, TitleCards};
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let bytes = std::fs::read("ride.gpx")?;
+//! let activity = process::process(&parse::parse(&bytes, FileFormat::Gpx)?)?;
+//!
+//! let options = RenderOptions::route_3d_defaults();
+//! let data = prepare::prepare(&activity, &options)?;
+//! let output = OutputConfig {
+//!     width: options.width,
+//!     height: options.height,
+//!     background: None,
+//!     watermark: false,
+//! };
+//! let apng = animate::render_apng(&data, &options, &output, &[], &TitleCards::default(), &AtomicBool::new(false))?;
+//! std::fs::write("ride.png", apng)?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Text uses the fonts in `assets/fonts` (relative to the working directory, or compiled in
+//! with the `embed-fonts` feature) and falls back to system fonts. The `openapi` feature
+//! derives `utoipa::ToSchema` for the option and metrics types.
+//!
+//! [`RenderOptions`]: types::viz::RenderOptions
+
+pub mod error;
+pub mod pipeline;
+pub mod types;
@@ -0,0 +1,335 @@
+use std::{
+    f64::consts::PI,
+    io::Cursor,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use apng::{create_config, image_png, Encoder, Frame, PNGImage};
+use rayon::prelude::*;
+
+use crate::error::RasterError;
+use crate::pipeline::render::{self, RouteProjection};
+use crate::pipeline::{draw, rasterize};
+use crate::types::viz::{
+    AnimationEasing, AnimationMode, LoopMode, OutputConfig, RenderOptions, StatOverlayItem,
+    TitleCard, TitleCards, VizData,
+};
+
+/// Renders the full animation, with any title cards, as an APNG. Frames render in parallel
+/// on the current rayon pool; setting `cancel` stops the render with
+/// [`RasterError::Cancelled`].
+pub fn render_apng(
+    data: &VizData,
+    options: &RenderOptions,
+    output: &OutputConfig,
+    stats: &[StatOverlayItem],
+    cards: &TitleCards,
+    cancel: &AtomicBool,
+) -> Result<Vec<u8>, RasterError> {
+    let projection = render::project_route(data, options).map_err(|err| {
+        RasterError::AnimationFailed(format!("Failed to project route: {}", err))
+    })?;
+    render_projected_apng(data, &projection, options, output, stats, cards, cancel)
+}
+
+/// [`render_apng`] from a projection made earlier.
+#[tracing::instrument(skip_all, fields(frames = options.animation_frames))]
+pub fn render_projected_apng(
+    data: &VizData,
+    projection: &RouteProjection,
+    options: &RenderOptions,
+    output: &OutputConfig,
+    stats: &[StatOverlayItem],
+    cards: &TitleCards,
+    cancel: &AtomicBool,
+) -> Result<Vec<u8>, RasterError> {
+    let frame_count = options.animation_frames.max(8);
+    // Stats and the watermark are identical on every frame: rasterize them once and
+    // composite them over each frame instead of laying out their text again. Nothing
+    // else is: walls, ground, glow and the gradient's span all follow the reveal, and
+    // rotation or the follow camera move the whole route, so the rest is drawn per frame
+    // (directly with tiny-skia, which is cheap next to the SVG path it replaced).
+    let overlay = rasterize::rasterize_overlay(&render::render_svg_overlay(options, stats), output)?;
+    let mode = options.reveal_mode();
+    let timeline = (mode != AnimationMode::Distance).then(|| Timeline::new(data));
+    // Rayon workers don't inherit the caller's span; parent frame spans explicitly.
+    let parent = tracing::Span::current();
+    let frames: Vec<PNGImage> = (0..frame_count)
+        .into_par_iter()
+        .map(|idx| {
+            let _frame = tracing::info_span!(parent: &parent, "frame", idx).entered();
+            if cancel.load(Ordering::Relaxed) {
+                return Err(RasterError::Cancelled);
+            }
+            let linear_progress = if frame_count <= 1 {
+                1.0
+            } else {
+                idx as f64 / (frame_count - 1) as f64
+            };
+            let progress = route_progress(
+                eased_progress(linear_progress, options.animation_easing),
+                mode,
+                timeline.as_ref(),
+            );
+
+            let rotation_deg = if options.rotate {
+                linear_progress * render::ROTATION_SWEEP_DEG
+            } else {
+                0.0
+            };
+
+            let pixmap = rasterize::compose_frame(output, &overlay, |pixmap| {
+                draw::draw_frame(projection, options, progress, rotation_deg, pixmap).map_err(|err| {
+                    RasterError::AnimationFailed(format!(
+                        "Failed to render animation frame {}: {}",
+                        idx, err
+                    ))
+                })
+            })?;
+            Ok(png_image_from_pixmap(&pixmap))
+        })
+        .collect::<Result<Vec<_>, RasterError>>()?;
+    if cancel.load(Ordering::Relaxed) {
+        return Err(RasterError::Cancelled);
+    }
+
+    let intro = cards
+        .intro
+        .as_ref()
+        .map(|card| render_title_card(card, options, output, frame_count))
+        .transpose()?;
+    let outro = cards
+        .outro
+        .as_ref()
+        .map(|card| render_title_card(card, options, output, frame_count + 1))
+        .transpose()?;
+
+    // (frame, delay in ms) in playback order.
+    let delay_ms = (options.animation_duration_ms / frame_count.max(1)).max(16);
+    let last_idx = frame_count - 1;
+    let mut playlist: Vec<(&PNGImage, u32)> = Vec::new();
+    if let Some((image, duration_ms)) = &intro {
+        playlist.push((image, *duration_ms));
+    }
+    for frame_idx in frame_sequence(frame_count, options.loop_mode) {
+        let hold_ms = if frame_idx == last_idx { options.end_hold_ms } else { 0 };
+        playlist.push((&frames[frame_idx as usize], delay_ms + hold_ms));
+    }
+    if let Some((image, duration_ms)) = &outro {
+        playlist.push((image, *duration_ms));
+    }
+
+    let _encode = tracing::info_span!("encode_apng", frames = playlist.len()).entered();
+    let mut config = create_config(&frames, None)
+        .map_err(|err| RasterError::AnimationFailed(format!("Failed to build APNG config: {}", err)))?;
+    config.num_frames = playlist.len() as u32;
+
+    let mut output_bytes = Vec::new();
+    {
+        let mut cursor = Cursor::new(&mut output_bytes);
+        let mut encoder = Encoder::new(&mut cursor, config)
+            .map_err(|err| RasterError::AnimationFailed(format!("Failed to create APNG encoder: {}", err)))?;
+        for (position, (image, frame_delay_ms)) in playlist.into_iter().enumerate() {
+            let frame = Frame {
+                delay_num: Some(frame_delay_ms.min(u16::MAX as u32) as u16),
+                delay_den: Some(1000),
+                ..Default::default()
+            };
+            encoder.write_frame(image, frame).map_err(|err| {
+                RasterError::AnimationFailed(format!(
+                    "Failed to encode APNG frame {}: {}",
+                    position, err
+                ))
+            })?;
+        }
+        encoder.finish_encode().map_err(|err| {
+            RasterError::AnimationFailed(format!("Failed to finish APNG encoding: {}", err))
+        })?;
+    }
+
+    Ok(output_bytes)
+}
+
+fn render_title_card(
+    card: &TitleCard,
+    options: &RenderOptions,
+    output: &OutputConfig,
+    frame_idx: u32,
+) -> Result<(PNGImage, u32), RasterError> {
+    let svg = render::render_title_card_svg(card, options);
+    let png_bytes = rasterize::rasterize(&svg, output)?;
+    let image = png_image_from_bytes(&png_bytes, frame_idx)?;
+    Ok((image, card.duration_ms.max(16)))
+}
+
+/// Order in which rendered frames are emitted. Boomerang plays the reveal forward and
+/// then back, skipping both endpoints on the way back so no frame is shown twice in a row.
+fn frame_sequence(frame_count: u32, loop_mode: LoopMode) -> Vec<u32> {
+    let forward = 0..frame_count;
+    match loop_mode {
+        LoopMode::Forward => forward.collect(),
+        LoopMode::Boomerang => forward
+            .chain((1..frame_count.saturating_sub(1)).rev())
+            .collect(),
+    }
+}
+
+/// Straight (non-premultiplied) RGBA, as `Pixmap::encode_png` would have written it.
+fn png_image_from_pixmap(pixmap: &tiny_skia::Pixmap) -> PNGImage {
+    let data = pixmap
+        .pixels()
+        .iter()
+        .flat_map(|pixel| {
+            let color = pixel.demultiply();
+            [color.red(), color.green(), color.blue(), color.alpha()]
+        })
+        .collect();
+    PNGImage {
+        width: pixmap.width(),
+        height: pixmap.height(),
+        data,
+        color_type: image_png::ColorType::Rgba,
+        bit_depth: image_png::BitDepth::Eight,
+    }
+}
+
+fn png_image_from_bytes(png_bytes: &[u8], frame_idx: u32) -> Result<PNGImage, RasterError> {
+    let decoder = image_png::Decoder::new(Cursor::new(png_bytes));
+    let mut reader = decoder.read_info().map_err(|err| {
+        RasterError::AnimationFailed(format!(
+            "Failed to decode PNG metadata for frame {}: {}",
+            frame_idx, err
+        ))
+    })?;
+    let mut data = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut data).map_err(|err| {
+        RasterError::AnimationFailed(format!(
+            "Failed to decode PNG pixels for frame {}: {}",
+            frame_idx, err
+        ))
+    })?;
+    data.truncate(info.buffer_size());
+
+    Ok(PNGImage {
+        width: info.width,
+        height: info.height,
+        data,
+        color_type: info.color_type,
+        bit_depth: info.bit_depth,
+    })
+}
+
+fn eased_progress(t: f64, easing: AnimationEasing) -> f64 {
+    match easing {
+        AnimationEasing::EaseInOutSine => ease_in_out_sine(t),
+        AnimationEasing::Linear | AnimationEasing::RealTime => t.clamp(0.0, 1.0),
+        AnimationEasing::EaseOutCubic => ease_out_cubic(t),
+    }
+}
+
+/// Fraction of the route distance revealed at (eased) progress `t`.
+fn route_progress(t: f64, mode: AnimationMode, timeline: Option<&Timeline>) -> f64 {
+    match (mode, timeline) {
+        (AnimationMode::Time, Some(timeline)) => timeline.progress(t),
+        (AnimationMode::Uniform, Some(timeline)) => timeline.point_progress(t),
+        _ => t.clamp(0.0, 1.0),
+    }
+}
+
+fn ease_in_out_sine(t: f64) -> f64 {
+    let t = t.clamp(0.0, 1.0);
+    0.5 * (1.0 - (PI * t).cos())
+}
+
+fn ease_out_cubic(t: f64) -> f64 {
+    let t = t.clamp(0.0, 1.0);
+    1.0 - (1.0 - t).powi(3)
+}
+
+/// Distance covered along the route against elapsed time or point index, for the time
+/// and uniform reveal modes. Built once per export so each frame only needs a binary
+/// search over the points.
+struct Timeline {
+    /// Elapsed seconds at each point, never decreasing: a timestamp earlier than the one
+    /// before it (clock jumps, merged tracks), or a missing one, takes the previous value.
+    elapsed: Vec<f64>,
+    /// Route distance up to each point, in projected units.
+    cumulative: Vec<f64>,
+    total_seconds: f64,
+}
+
+impl Timeline {
+    fn new(data: &VizData) -> Self {
+        let points = &data.points;
+        let elapsed: Vec<f64> = points
+            .iter()
+            .scan(0.0_f64, |latest, p| {
+                *latest = p.elapsed_seconds.map_or(*latest, |seconds| latest.max(seconds));
+                Some(*latest)
+            })
+            .collect();
+        let total_seconds = elapsed.last().copied().unwrap_or(0.0);
+
+        let mut cumulative = Vec::with_capacity(points.len());
+        let mut traveled = 0.0;
+        cumulative.push(0.0);
+        for pair in points.windows(2) {
+            let dx = pair[1].x - pair[0].x;
+            let dy = pair[1].y - pair[0].y;
+            traveled += (dx * dx + dy * dy).sqrt();
+            cumulative.push(traveled);
+        }
+        Self {
+            elapsed,
+            cumulative,
+            total_seconds,
+        }
+    }
+
+    /// Maps a fraction of the activity's elapsed time to the fraction of route distance
+    /// covered at that moment. Falls back to linear playback when timestamps are missing.
+    fn progress(&self, t: f64) -> f64 {
+        let t = t.clamp(0.0, 1.0);
+        let elapsed = &self.elapsed;
+        let traveled = self.cumulative.last().copied().unwrap_or(0.0);
+        if elapsed.len() < 2 || self.total_seconds <= f64::EPSILON || traveled <= f64::EPSILON {
+            return t;
+        }
+
+        // `elapsed` never decreases, which the binary search relies on.
+        let target = t * self.total_seconds;
+        let idx = elapsed.partition_point(|&seconds| seconds < target);
+        if idx == 0 {
+            return 0.0;
+        }
+        if idx >= elapsed.len() {
+            return 1.0;
+        }
+        let before = elapsed[idx - 1];
+        let after = elapsed[idx];
+        let local_t = if after - before > f64::EPSILON {
+            ((target - before) / (after - before)).clamp(0.0, 1.0)
+        } else {
+            1.0
+        };
+        let cumulative = &self.cumulative;
+        let distance = cumulative[idx - 1] + (cumulative[idx] - cumulative[idx - 1]) * local_t;
+        (distance / traveled).clamp(0.0, 1.0)
+    }
+
+    /// Maps a fraction of the track's points to the fraction of route distance up to
+    /// that point, so every point takes the same share of the animation.
+    fn point_progress(&self, t: f64) -> f64 {
+        let t = t.clamp(0.0, 1.0);
+        let traveled = self.cumulative.last().copied().unwrap_or(0.0);
+        if self.cumulative.len() < 2 || traveled <= f64::EPSILON {
+            return t;
+        }
+        let position = t * (self.cumulative.len() - 1) as f64;
+        let idx = (position.floor() as usize).min(self.cumulative.len() - 2);
+        let local_t = position - idx as f64;
+        let cumulative = &self.cumulative;
+        let distance = cumulative[idx] + (cumulative[idx + 1] - cumulative[idx]) * local_t;
+        (distance / traveled).clamp(0.0, 1.0)
+    }
+}
@@ -0,0 +1,280 @@
+use tiny_skia::{
+    Color, FillRule, GradientStop, IntRect, LineCap, LineJoin, LinearGradient, Paint, Path, PathBuilder,
+    Pixmap, PixmapPaint, Point, Shader, SpreadMode, Stroke, Transform,
+};
+
+use crate::error::RenderError;
+use crate::pipeline::render::{
+    self, FrameGeometry, RouteProjection, ENDPOINT_OPACITY, ENDPOINT_RADIUS_RATIO, GLOW_BLUR_STD_DEV, GLOW_OPACITY,
+    GLOW_WIDTH_RATIO, GROUND_OPACITY, OUTLINE_OPACITY, OUTLINE_WIDTH_RATIO, WALL_FILL_OPACITY,
+};
+use crate::types::gradient::{Gradient, Rgb};
+use crate::types::viz::RenderOptions;
+
+const WHITE: Rgb = Rgb(255, 255, 255);
+
+/// Draws one frame of the route straight onto `pixmap` with tiny-skia, without building
+/// and parsing an SVG. Matches [`render::render_projected_svg_frame`] with no stats, except
+/// that the glow's Gaussian blur is approximated by repeated box blurs. Used for
+/// animation frames; static renders still go through SVG.
+#[tracing::instrument(skip(projection, options, pixmap))]
+pub fn draw_frame(
+    projection: &RouteProjection,
+    options: &RenderOptions,
+    progress: f64,
+    rotation_deg: f64,
+    pixmap: &mut Pixmap,
+) -> Result<(), RenderError> {
+    let geometry = render::frame_geometry(projection, options, progress, rotation_deg)?;
+    let transform = Transform::from_scale(
+        pixmap.width() as f32 / options.width as f32,
+        pixmap.height() as f32 / options.height as f32,
+    );
+
+    for wall in &geometry.walls {
+        if let Some(path) = polygon(&wall.corners) {
+            let paint = solid(wall.color, WALL_FILL_OPACITY);
+            pixmap.fill_path(&path, &paint, FillRule::Winding, transform, None);
+        }
+    }
+    if let Some(ground) = route_path(&geometry.ground, options.curve_tension) {
+        let paint = solid(WHITE, GROUND_OPACITY);
+        let stroke = round_stroke(render::ground_stroke_width(options));
+        pixmap.stroke_path(&ground, &paint, &stroke, transform, None);
+    }
+    let top = route_path(&geometry.top, options.curve_tension);
+    if let Some(top) = &top {
+        let paint = solid(WHITE, OUTLINE_OPACITY);
+        let stroke = round_stroke(options.stroke_width * OUTLINE_WIDTH_RATIO);
+        pixmap.stroke_path(top, &paint, &stroke, transform, None);
+    }
+    if options.glow && geometry.has_extent {
+        draw_glow(pixmap, &geometry, top.as_ref(), options, transform);
+    }
+    draw_route(pixmap, &geometry, top.as_ref(), options.stroke_width, options, transform);
+
+    if geometry.top.len() >= 2 {
+        let radius = options.stroke_width as f64 * ENDPOINT_RADIUS_RATIO;
+        let (start_color, end_color) = render::endpoint_colors(&options.gradient);
+        let ends = [geometry.top[0], geometry.top[geometry.top.len() - 1]];
+        for ((x, y), color) in ends.into_iter().zip([start_color, end_color]) {
+            if let Some(dot) = PathBuilder::from_circle(x as f32, y as f32, radius as f32) {
+                pixmap.fill_path(&dot, &solid(color, ENDPOINT_OPACITY), FillRule::Winding, transform, None);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn polygon(corners: &[(f64, f64)]) -> Option<Path> {
+    let (&(x, y), rest) = corners.split_first()?;
+    let mut builder = PathBuilder::new();
+    builder.move_to(x as f32, y as f32);
+    for &(x, y) in rest {
+        builder.line_to(x as f32, y as f32);
+    }
+    builder.close();
+    builder.finish()
+}
+
+/// The route line itself, in the gradient or, with `color_by`, one color per value bucket.
+fn draw_route(
+    pixmap: &mut Pixmap,
+    geometry: &FrameGeometry,
+    top: Option<&Path>,
+    width: f32,
+    options: &RenderOptions,
+    transform: Transform,
+) {
+    let stroke = round_stroke(width);
+    if options.color_by.is_some() {
+        for bucket in render::segment_buckets(&geometry.top, Some(&geometry.values)) {
+            let mut builder = PathBuilder::new();
+            for ((x1, y1), (x2, y2)) in bucket.segments {
+                builder.move_to(x1 as f32, y1 as f32);
+                builder.line_to(x2 as f32, y2 as f32);
+            }
+            if let Some(path) = builder.finish() {
+                let paint = solid(options.gradient.color(bucket.color_t), 1.0);
+                pixmap.stroke_path(&path, &paint, &stroke, transform, None);
+            }
+        }
+    } else if let Some(top) = top {
+        let paint = Paint {
+            shader: route_gradient(top, &options.gradient),
+            anti_alias: true,
+            ..Default::default()
+        };
+        pixmap.stroke_path(top, &paint, &stroke, transform, None);
+    }
+}
+
+/// The blurred, wider copy of the route under it. Drawn on a layer covering just the
+/// route and the blur's reach, since blurring the whole canvas would cost more than
+/// everything else in the frame.
+fn draw_glow(
+    pixmap: &mut Pixmap,
+    geometry: &FrameGeometry,
+    top: Option<&Path>,
+    options: &RenderOptions,
+    transform: Transform,
+) {
+    let width = options.stroke_width * GLOW_WIDTH_RATIO;
+    let scale = transform.sx.max(transform.sy);
+    let sigma = GLOW_BLUR_STD_DEV as f32 * scale;
+    let radius = (((4.0 * sigma * sigma + 1.0).sqrt() - 1.0) * 0.5).round() as usize;
+    let reach = width * scale * 0.5 + 3.0 * radius as f32;
+
+    let (mut min_x, mut min_y, mut max_x, mut max_y) = (f32::MAX, f32::MAX, f32::MIN, f32::MIN);
+    for &(x, y) in &geometry.top {
+        let mut point = Point::from_xy(x as f32, y as f32);
+        transform.map_point(&mut point);
+        min_x = min_x.min(point.x);
+        min_y = min_y.min(point.y);
+        max_x = max_x.max(point.x);
+        max_y = max_y.max(point.y);
+    }
+    let left = ((min_x - reach).floor() as i32).max(0);
+    let top_edge = ((min_y - reach).floor() as i32).max(0);
+    let right = ((max_x + reach).ceil() as i32).min(pixmap.width() as i32);
+    let bottom = ((max_y + reach).ceil() as i32).min(pixmap.height() as i32);
+    let Some(area) = IntRect::from_ltrb(left, top_edge, right, bottom) else {
+        return;
+    };
+    let Some(mut layer) = Pixmap::new(area.width(), area.height()) else {
+        return;
+    };
+
+    let local = transform.post_translate(-area.x() as f32, -area.y() as f32);
+    draw_route(&mut layer, geometry, top, width, options, local);
+    let sharp = layer.clone();
+    box_blur(&mut layer, radius);
+    // The SVG filter merges the blur twice under the sharp stroke.
+    let blurred = layer.clone();
+    layer.draw_pixmap(0, 0, blurred.as_ref(), &PixmapPaint::default(), Transform::identity(), None);
+    layer.draw_pixmap(0, 0, sharp.as_ref(), &PixmapPaint::default(), Transform::identity(), None);
+
+    let paint = PixmapPaint {
+        opacity: GLOW_OPACITY as f32,
+        ..Default::default()
+    };
+    pixmap.draw_pixmap(area.x(), area.y(), layer.as_ref(), &paint, Transform::identity(), None);
+}
+
+/// Three box blur passes each way, which is close to a Gaussian blur. Works on the
+/// premultiplied pixels directly; pixels outside the pixmap count as transparent.
+fn box_blur(pixmap: &mut Pixmap, radius: usize) {
+    if radius == 0 {
+        return;
+    }
+    let (width, height) = (pixmap.width() as usize, pixmap.height() as usize);
+    let data = pixmap.data_mut();
+    let mut line = Vec::new();
+    for _ in 0..3 {
+        for y in 0..height {
+            box_blur_line(data, y * width * 4, 4, width, radius, &mut line);
+        }
+        for x in 0..width {
+            box_blur_line(data, x * 4, width * 4, height, radius, &mut line);
+        }
+    }
+}
+
+/// Blurs `len` pixels starting at byte `start` and `stride` bytes apart, in place.
+fn box_blur_line(data: &mut [u8], start: usize, stride: usize, len: usize, radius: usize, line: &mut Vec<[u8; 4]>) {
+    line.clear();
+    line.extend((0..len).map(|i| {
+        let offset = start + i * stride;
+        [data[offset], data[offset + 1], data[offset + 2], data[offset + 3]]
+    }));
+    let window = (2 * radius + 1) as u32;
+    let mut sum = [0u32; 4];
+    for pixel in line.iter().take(radius + 1) {
+        for channel in 0..4 {
+            sum[channel] += pixel[channel] as u32;
+        }
+    }
+    for i in 0..len {
+        let offset = start + i * stride;
+        for channel in 0..4 {
+            data[offset + channel] = ((sum[channel] + window / 2) / window) as u8;
+        }
+        if let Some(entering) = line.get(i + radius + 1) {
+            for channel in 0..4 {
+                sum[channel] += entering[channel] as u32;
+            }
+        }
+        if i >= radius {
+            let leaving = line[i - radius];
+            for channel in 0..4 {
+                sum[channel] -= leaving[channel] as u32;
+            }
+        }
+    }
+}
+
+/// Same curve as the SVG path: cubic Beziers with tension, straight lines without.
+fn route_path(coords: &[(f64, f64)], tension: f32) -> Option<Path> {
+    let (&(x, y), _) = coords.split_first()?;
+    let mut builder = PathBuilder::new();
+    builder.move_to(x as f32, y as f32);
+    for i in 0..coords.len() - 1 {
+        let (x, y) = coords[i + 1];
+        if tension > 0.0 {
+            let ((x1, y1), (x2, y2)) = render::smooth_control_points(coords, i, tension);
+            builder.cubic_to(x1 as f32, y1 as f32, x2 as f32, y2 as f32, x as f32, y as f32);
+        } else {
+            builder.line_to(x as f32, y as f32);
+        }
+    }
+    builder.finish()
+}
+
+/// Left-to-right gradient across the path's bounding box, like the SVG's
+/// `objectBoundingBox` `routeGradient`.
+fn route_gradient(path: &Path, gradient: &Gradient) -> Shader<'static> {
+    let colors = gradient.stops();
+    let stops = colors
+        .iter()
+        .enumerate()
+        .map(|(i, &rgb)| {
+            let offset = if colors.len() == 1 {
+                0.0
+            } else {
+                i as f32 / (colors.len() - 1) as f32
+            };
+            GradientStop::new(offset, color(rgb, 1.0))
+        })
+        .collect();
+    let bounds = path.bounds();
+    LinearGradient::new(
+        Point::from_xy(bounds.left(), bounds.top()),
+        Point::from_xy(bounds.right(), bounds.top()),
+        stops,
+        SpreadMode::Pad,
+        Transform::identity(),
+    )
+    .unwrap_or(Shader::SolidColor(Color::WHITE))
+}
+
+fn round_stroke(width: f32) -> Stroke {
+    Stroke {
+        width,
+        line_cap: LineCap::Round,
+        line_join: LineJoin::Round,
+        ..Default::default()
+    }
+}
+
+fn solid(rgb: Rgb, opacity: f64) -> Paint<'static> {
+    let mut paint = Paint::default();
+    paint.set_color(color(rgb, opacity));
+    paint.anti_alias = true;
+    paint
+}
+
+fn color(Rgb(r, g, b): Rgb, opacity: f64) -> Color {
+    let mut color = Color::from_rgba8(r, g, b, 255);
+    color.set_alpha(opacity as f32);
+    color
+}
@@ -0,0 +1,110 @@
+use std::fmt::Write;
+
+use chrono::SecondsFormat;
+
+use crate::pipeline::process::haversine_distance;
+use crate::types::activity::ProcessedActivity;
+
+const CSV_HEADER: &str = "time,lat,lon,elevation_m,speed_kmh,heart_rate_bpm,power_w,cadence_rpm";
+
+/// Serializes the processed (downsampled) track as GPX 1.1 with Garmin
+/// TrackPointExtension fields, so it round-trips through our own parser.
+pub fn to_gpx(processed: &ProcessedActivity, name: &str) -> String {
+    let mut gpx = String::with_capacity(processed.points.len() * 160 + 512);
+    gpx.push_str(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<gpx version="1.1" creator="rideviz.online" xmlns="http://www.topografix.com/GPX/1/1" xmlns:gpxtpx="http://www.garmin.com/xmlschemas/TrackPointExtension/v1">
+"#,
+    );
+    if let Some(start) = processed.start_time {
+        let _ = writeln!(
+            gpx,
+            "  <metadata><time>{}</time></metadata>",
+            start.to_rfc3339_opts(SecondsFormat::Secs, true)
+        );
+    }
+    let _ = writeln!(gpx, "  <trk>\n    <name>{}</name>\n    <trkseg>", escape_xml(name));
+
+    for point in &processed.points {
+        let _ = write!(gpx, r#"      <trkpt lat="{:.7}" lon="{:.7}">"#, point.lat, point.lon);
+        if let Some(elevation) = point.elevation {
+            let _ = write!(gpx, "<ele>{:.1}</ele>", elevation);
+        }
+        if let Some(time) = point.time {
+            let _ = write!(gpx, "<time>{}</time>", time.to_rfc3339_opts(SecondsFormat::Secs, true));
+        }
+        if point.heart_rate.is_some()
+            || point.cadence.is_some()
+            || point.temperature.is_some()
+            || point.power.is_some()
+        {
+            gpx.push_str("<extensions>");
+            if let Some(power) = point.power {
+                let _ = write!(gpx, "<power>{}</power>", power);
+            }
+            gpx.push_str("<gpxtpx:TrackPointExtension>");
+            if let Some(temperature) = point.temperature {
+                let _ = write!(gpx, "<gpxtpx:atemp>{:.1}</gpxtpx:atemp>", temperature);
+            }
+            if let Some(heart_rate) = point.heart_rate {
+                let _ = write!(gpx, "<gpxtpx:hr>{}</gpxtpx:hr>", heart_rate);
+            }
+            if let Some(cadence) = point.cadence {
+                let _ = write!(gpx, "<gpxtpx:cad>{}</gpxtpx:cad>", cadence);
+            }
+            gpx.push_str("</gpxtpx:TrackPointExtension></extensions>");
+        }
+        gpx.push_str("</trkpt>\n");
+    }
+
+    gpx.push_str("    </trkseg>\n  </trk>\n</gpx>\n");
+    gpx
+}
+
+/// One row per processed point. Speed is derived from the distance and time to the
+/// previous point; empty cells mean the value wasn't recorded.
+pub fn to_csv(processed: &ProcessedActivity) -> String {
+    let mut csv = String::with_capacity(processed.points.len() * 96 + CSV_HEADER.len() + 1);
+    csv.push_str(CSV_HEADER);
+    csv.push('\n');
+
+    for (idx, point) in processed.points.iter().enumerate() {
+        let speed_kmh = idx
+            .checked_sub(1)
+            .map(|prev_idx| &processed.points[prev_idx])
+            .and_then(|prev| {
+                let seconds = (point.time? - prev.time?).num_milliseconds() as f64 / 1000.0;
+                (seconds > 0.0).then(|| {
+                    haversine_distance(prev.lat, prev.lon, point.lat, point.lon) / (seconds / 3600.0)
+                })
+            });
+
+        let _ = writeln!(
+            csv,
+            "{},{:.7},{:.7},{},{},{},{},{}",
+            point
+                .time
+                .map(|time| time.to_rfc3339_opts(SecondsFormat::Secs, true))
+                .unwrap_or_default(),
+            point.lat,
+            point.lon,
+            optional_cell(point.elevation.map(|v| format!("{:.1}", v))),
+            optional_cell(speed_kmh.map(|v| format!("{:.2}", v))),
+            optional_cell(point.heart_rate),
+            optional_cell(point.power),
+            optional_cell(point.cadence),
+        );
+    }
+    csv
+}
+
+fn optional_cell<T: ToString>(value: Option<T>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_default()
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -1,6 +1,9 @@
+pub mod draw;
+pub mod export;
 pub mod parse;
 pub mod prepare;
 pub mod process;
 pub mod rasterize;
 pub mod render;
+pub mod stats;
 pub mod animate;
\ No newline at end of file
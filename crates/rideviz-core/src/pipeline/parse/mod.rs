@@ -8,6 +8,9 @@ pub trait Parser {
     fn parse(&self, bytes: &[u8]) -> Result<ParsedActivity, ParseError>;
 }
 
+/// Reads the raw track points from a GPX or FIT file. Both parsers work on the borrowed
+/// bytes without copying them, so `bytes` can be a memory-mapped upload.
+#[tracing::instrument(skip(bytes), fields(bytes = bytes.len()))]
 pub fn parse(bytes: &[u8], format: FileFormat) -> Result<ParsedActivity, ParseError> {
     match format {
         FileFormat::Gpx => gpx::GpxParser.parse(bytes),
@@ -1,14 +1,22 @@
+use std::borrow::Cow;
+
+use chrono::Duration;
+
 use crate::error::PrepareError;
 use crate::types::activity::{ProcessedActivity, TrackPoint};
-use crate::types::viz::{ColorByMetric, RenderOptions, RoutePoint, VizData};
+use crate::types::viz::{AnimationMode, ColorByMetric, RenderOptions, RoutePoint, VizData};
+
+/// A loop may end this far from where it started and still be started at an offset.
+const MAX_LOOP_GAP_KM: f64 = 1.0;
 
+/// Puts the track in render order, projects and normalizes it and attaches the
+/// `color_by` values. Fails if the activity lacks coordinates or the data the options
+/// need. Without elevation the route is still prepared and renders flat.
+#[tracing::instrument(skip_all)]
 pub fn prepare(processed: &ProcessedActivity, options: &RenderOptions) -> Result<VizData, PrepareError> {
     if !processed.available_data.has_coordinates {
         return Err(PrepareError::MissingData("coordinates"));
     }
-    if !processed.available_data.has_elevation {
-        return Err(PrepareError::MissingData("elevation"));
-    }
     if let Some(metric) = options.color_by {
         match metric {
             ColorByMetric::Elevation if !processed.available_data.has_elevation => {
@@ -26,9 +34,12 @@ pub fn prepare(processed: &ProcessedActivity, options: &RenderOptions) -> Result
             _ => {}
         }
     }
+    if options.reveal_mode() == AnimationMode::Time && !has_speed_samples(&processed.points) {
+        return Err(PrepareError::MissingData("timestamp"));
+    }
 
-    let projected: Vec<(f64, f64)> = processed
-        .points
+    let track = reorder(&processed.points, options.reverse, options.start_offset_km)?;
+    let projected: Vec<(f64, f64)> = track
         .iter()
         .map(|p| mercator_project(p.lat, p.lon))
         .collect();
@@ -40,7 +51,8 @@ pub fn prepare(processed: &ProcessedActivity, options: &RenderOptions) -> Result
     let normalized = normalize_route_points(&projected);
     let values = options
         .color_by
-        .map(|metric| compute_route_metric_values(&processed.points, metric));
+        .map(|metric| compute_route_metric_values(&track, metric));
+    let elapsed = compute_elapsed_seconds(&track);
 
     let points = normalized
         .into_iter()
@@ -53,13 +65,99 @@ pub fn prepare(processed: &ProcessedActivity, options: &RenderOptions) -> Result
                 .and_then(|metric_values| metric_values.get(idx))
                 .copied()
                 .flatten(),
-            elevation: processed.points.get(idx).and_then(|p| p.elevation),
+            elevation: track.get(idx).and_then(|p| p.elevation),
+            elapsed_seconds: elapsed.get(idx).copied().flatten(),
         })
         .collect();
 
     Ok(VizData { points })
 }
 
+/// The track started `start_offset_km` into a loop and/or run backwards. Readings stay
+/// with their points, so grades flip sign on the way back; timestamps are rebuilt from
+/// the original gaps between points so speeds and real-time playback stay right.
+/// Borrows when the order is unchanged.
+fn reorder(
+    points: &[TrackPoint],
+    reverse: bool,
+    start_offset_km: Option<f64>,
+) -> Result<Cow<'_, [TrackPoint]>, PrepareError> {
+    let n = points.len();
+    if n < 2 || (!reverse && start_offset_km.is_none()) {
+        return Ok(Cow::Borrowed(points));
+    }
+    let Some(offset_km) = start_offset_km else {
+        let order: Vec<usize> = (0..n).rev().collect();
+        return Ok(Cow::Owned(retime(points, &order, 0.0)));
+    };
+
+    let gap_km = haversine_distance(points[n - 1].lat, points[n - 1].lon, points[0].lat, points[0].lon);
+    if gap_km > MAX_LOOP_GAP_KM {
+        return Err(PrepareError::NotALoop(gap_km));
+    }
+    let mut cumulative = Vec::with_capacity(n);
+    let mut traveled = 0.0;
+    cumulative.push(0.0);
+    for pair in points.windows(2) {
+        traveled += haversine_distance(pair[0].lat, pair[0].lon, pair[1].lat, pair[1].lon);
+        cumulative.push(traveled);
+    }
+    let loop_km = traveled + gap_km;
+    let target_km = if loop_km > f64::EPSILON {
+        offset_km.rem_euclid(loop_km)
+    } else {
+        0.0
+    };
+    // First point at or past the target; within the closing gap, the start itself.
+    let start = cumulative.partition_point(|km| *km < target_km) % n;
+    let order: Vec<usize> = if reverse {
+        (0..n).map(|i| (start + n - i) % n).collect()
+    } else {
+        (0..n).map(|i| (start + i) % n).collect()
+    };
+    Ok(Cow::Owned(retime(points, &order, gap_km)))
+}
+
+/// `points` in `order`, each step between neighbours taking as long as it originally did.
+/// The step across a loop's closing gap (last point to first, `gap_km` long) takes as
+/// long as it would at the ride's average pace. Points without a timestamp keep none.
+fn retime(points: &[TrackPoint], order: &[usize], gap_km: f64) -> Vec<TrackPoint> {
+    let step_ms = |from: &TrackPoint, to: &TrackPoint| match (from.time, to.time) {
+        (Some(from), Some(to)) => (to - from).num_milliseconds().max(0),
+        _ => 0,
+    };
+    let total_ms: i64 = points.windows(2).map(|pair| step_ms(&pair[0], &pair[1])).sum();
+    let total_km: f64 = points
+        .windows(2)
+        .map(|pair| haversine_distance(pair[0].lat, pair[0].lon, pair[1].lat, pair[1].lon))
+        .sum();
+    let gap_ms = if total_km > f64::EPSILON {
+        (gap_km / total_km * total_ms as f64).round() as i64
+    } else {
+        0
+    };
+
+    let Some(start) = points.iter().find_map(|point| point.time) else {
+        return order.iter().map(|&idx| points[idx].clone()).collect();
+    };
+    let mut elapsed_ms = 0;
+    let mut retimed = Vec::with_capacity(order.len());
+    for (position, &idx) in order.iter().enumerate() {
+        if position > 0 {
+            let (a, b) = (order[position - 1].min(idx), order[position - 1].max(idx));
+            elapsed_ms += if b - a == 1 {
+                step_ms(&points[a], &points[b])
+            } else {
+                gap_ms
+            };
+        }
+        let mut point = points[idx].clone();
+        point.time = point.time.map(|_| start + Duration::milliseconds(elapsed_ms));
+        retimed.push(point);
+    }
+    retimed
+}
+
 fn mercator_project(lat: f64, lon: f64) -> (f64, f64) {
     let x = lon;
     let y = (lat.to_radians().tan() + (1.0 / lat.to_radians().cos())).ln();
@@ -186,6 +284,25 @@ fn normalize_optional_values(values: &[Option<f64>]) -> Vec<Option<f64>> {
         .collect()
 }
 
+/// Seconds since the first timestamped point. Kept non-decreasing (points without a
+/// timestamp inherit the previous value) so playback never moves backwards.
+fn compute_elapsed_seconds(points: &[TrackPoint]) -> Vec<Option<f64>> {
+    let Some(start) = points.iter().find_map(|p| p.time) else {
+        return vec![None; points.len()];
+    };
+    let mut latest = 0.0_f64;
+    points
+        .iter()
+        .map(|point| {
+            if let Some(time) = point.time {
+                let seconds = (time - start).num_milliseconds() as f64 / 1000.0;
+                latest = latest.max(seconds);
+            }
+            Some(latest)
+        })
+        .collect()
+}
+
 fn has_speed_samples(points: &[TrackPoint]) -> bool {
     points.windows(2).any(|pair| {
         let a = &pair[0];
@@ -1,22 +1,72 @@
+use std::borrow::Cow;
+
 use crate::error::ProcessError;
 use crate::types::activity::{AvailableData, Metrics, ParsedActivity, ProcessedActivity, TrackPoint};
 
 const MAX_POINTS: usize = 1000;
 
+/// Drops points with invalid coordinates, computes metrics and downsamples the track to
+/// at most 1000 points.
+#[tracing::instrument(skip_all, fields(points = parsed.points.len()))]
 pub fn process(parsed: &ParsedActivity) -> Result<ProcessedActivity, ProcessError> {
     if parsed.points.len() < 2 {
         return Err(ProcessError::InsufficientPoints(parsed.points.len()));
     }
+    let valid = sanitize(&parsed.points);
+    if valid.len() < 2 {
+        return Err(ProcessError::InvalidCoordinates {
+            valid: valid.len(),
+            total: parsed.points.len(),
+        });
+    }
+    if valid.len() < parsed.points.len() {
+        tracing::warn!(
+            "Dropped {} of {} points with invalid coordinates",
+            parsed.points.len() - valid.len(),
+            parsed.points.len()
+        );
+    }
 
-    let metrics = compute_metrics(&parsed.points);
-    let available_data = detect_available_data(&parsed.points);
-    let points = downsample(&parsed.points);
+    let metrics = compute_metrics(&valid);
+    let available_data = detect_available_data(&valid);
+    let start_time = valid.iter().find_map(|p| p.time);
+    let points = downsample(&valid);
 
-    Ok(ProcessedActivity {
+    let mut processed = ProcessedActivity {
         points,
         metrics,
         available_data,
-    })
+        start_time,
+        digest: String::new(),
+    };
+    processed.digest = processed.compute_digest();
+    Ok(processed)
+}
+
+/// Keeps points whose latitude and longitude are finite and in range (corrupt files
+/// produce NaN or lat=181), and clears non-finite elevations and temperatures. Borrows
+/// when every point is already clean.
+fn sanitize(points: &[TrackPoint]) -> Cow<'_, [TrackPoint]> {
+    let has_valid_coordinates = |p: &TrackPoint| p.lat.abs() < 90.0 && p.lon.abs() <= 180.0;
+    let is_clean = |p: &TrackPoint| {
+        has_valid_coordinates(p)
+            && p.elevation.is_none_or(f64::is_finite)
+            && p.temperature.is_none_or(f32::is_finite)
+    };
+    if points.iter().all(is_clean) {
+        return Cow::Borrowed(points);
+    }
+    Cow::Owned(
+        points
+            .iter()
+            .filter(|p| has_valid_coordinates(p))
+            .map(|p| TrackPoint {
+                elevation: p.elevation.filter(|e| e.is_finite()),
+                temperature: p.temperature.filter(|t| t.is_finite()),
+                ..p.clone()
+            })
+            .collect(),
+    )
 }
 
 fn compute_metrics(points: &[TrackPoint]) -> Metrics {
@@ -29,6 +79,11 @@ fn compute_metrics(points: &[TrackPoint]) -> Metrics {
     let mut power_sum = 0u64;
     let mut power_count = 0;
     let mut max_power = 0u16;
+    let mut cadence_sum = 0u64;
+    let mut cadence_count = 0;
+    let mut max_cadence = 0u16;
+    let mut temperature_sum = 0.0f64;
+    let mut temperature_count = 0;
 
     for i in 1..points.len() {
         let prev = &points[i - 1];
@@ -58,6 +113,17 @@ fn compute_metrics(points: &[TrackPoint]) -> Metrics {
             power_count += 1;
             max_power = max_power.max(power);
         }
+
+        if let Some(cadence) = curr.cadence {
+            cadence_sum += cadence as u64;
+            cadence_count += 1;
+            max_cadence = max_cadence.max(cadence);
+        }
+
+        if let Some(temperature) = curr.temperature {
+            temperature_sum += temperature as f64;
+            temperature_count += 1;
+        }
     }
 
     let avg_speed_kmh = if duration_seconds > 0 {
@@ -83,6 +149,13 @@ fn compute_metrics(points: &[TrackPoint]) -> Metrics {
             None
         },
         max_power: if max_power > 0 { Some(max_power) } else { None },
+        avg_cadence: cadence_sum.checked_div(cadence_count).map(|avg| avg as u16),
+        max_cadence: if max_cadence > 0 { Some(max_cadence) } else { None },
+        avg_temperature: if temperature_count > 0 {
+            Some((temperature_sum / temperature_count as f64) as f32)
+        } else {
+            None
+        },
     }
 }
 
@@ -160,7 +233,8 @@ fn lttb_downsample(data: &[TrackPoint], threshold: usize) -> Vec<TrackPoint> {
     sampled
 }
 
-fn haversine_distance(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+/// Great-circle distance between two coordinates, in kilometres.
+pub fn haversine_distance(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
     const R: f64 = 6371.0; // Earth radius in km
 
     let d_lat = (lat2 - lat1).to_radians();
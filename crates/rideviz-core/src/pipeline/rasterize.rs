@@ -0,0 +1,152 @@
+use std::sync::OnceLock;
+
+use crate::error::RasterError;
+use crate::types::viz::OutputConfig;
+
+/// `assets/fonts` compiled in, loaded after the font files found on disk.
+#[cfg(feature = "embed-fonts")]
+#[derive(rust_embed::RustEmbed)]
+#[folder = "../../assets/fonts/"]
+#[allow_missing = true]
+struct EmbeddedFonts;
+
+/// Loaded on first use and shared by every render thread; scanning system fonts is the
+/// slowest part of a cold render.
+static FONT_DB: OnceLock<usvg::fontdb::Database> = OnceLock::new();
+
+/// Renders SVG to a PNG at the output size, adding the watermark if enabled.
+#[tracing::instrument(skip_all, fields(width = config.width, height = config.height))]
+pub fn rasterize(svg: &str, config: &OutputConfig) -> Result<Vec<u8>, RasterError> {
+    rasterize_with_fontdb(svg, config, font_db())
+}
+
+/// Number of font faces available to the rasterizer. Loads them if nothing has yet.
+pub fn loaded_font_count() -> usize {
+    font_db().len()
+}
+
+fn font_db() -> &'static usvg::fontdb::Database {
+    FONT_DB.get_or_init(load_font_db)
+}
+
+fn load_font_db() -> usvg::fontdb::Database {
+    let mut fontdb = usvg::fontdb::Database::new();
+    // Prefer explicitly known font files so text rendering is reliable in containers.
+    for path in [
+        "/app/assets/fonts/Geist-Regular.otf",
+        "./assets/fonts/Geist-Regular.otf",
+        "/app/assets/fonts/GeistPixel-Square.ttf",
+        "./assets/fonts/GeistPixel-Square.ttf",
+        "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf",
+        "/usr/share/fonts/truetype/dejavu/DejaVuSansMono.ttf",
+        "/usr/share/fonts/dejavu/DejaVuSans.ttf",
+        "C:\\Windows\\Fonts\\arial.ttf",
+    ] {
+        let _ = fontdb.load_font_file(path);
+    }
+    #[cfg(feature = "embed-fonts")]
+    for path in EmbeddedFonts::iter() {
+        if let Some(font) = EmbeddedFonts::get(&path) {
+            fontdb.load_font_data(font.data.into_owned());
+        }
+    }
+    fontdb.load_system_fonts();
+    fontdb
+}
+
+fn rasterize_with_fontdb(
+    svg: &str,
+    config: &OutputConfig,
+    fontdb: &usvg::fontdb::Database,
+) -> Result<Vec<u8>, RasterError> {
+    let svg = if config.watermark {
+        inject_watermark(svg, config.width, config.height)
+    } else {
+        svg.to_string()
+    };
+    let mut pixmap = background_pixmap(config)?;
+    render_onto(&svg, &mut pixmap, fontdb)?;
+    pixmap
+        .encode_png()
+        .map_err(|e| RasterError::RenderFailed(format!("Failed to encode PNG: {}", e)))
+}
+
+/// Rasterizes a layer that stays the same across animation frames onto a transparent
+/// pixmap, with the watermark if enabled. Pass it to [`compose_frame`] to draw it
+/// over each frame.
+pub fn rasterize_overlay(svg: &str, config: &OutputConfig) -> Result<tiny_skia::Pixmap, RasterError> {
+    let svg = if config.watermark {
+        inject_watermark(svg, config.width, config.height)
+    } else {
+        svg.to_string()
+    };
+    let mut pixmap = tiny_skia::Pixmap::new(config.width, config.height)
+        .ok_or_else(|| RasterError::RenderFailed("Failed to create pixmap".to_string()))?;
+    render_onto(&svg, &mut pixmap, font_db())?;
+    Ok(pixmap)
+}
+
+/// Builds one animation frame: the background, whatever `draw` puts over it, then
+/// `overlay` on top.
+pub fn compose_frame(
+    config: &OutputConfig,
+    overlay: &tiny_skia::Pixmap,
+    draw: impl FnOnce(&mut tiny_skia::Pixmap) -> Result<(), RasterError>,
+) -> Result<tiny_skia::Pixmap, RasterError> {
+    let mut pixmap = background_pixmap(config)?;
+    draw(&mut pixmap)?;
+    pixmap.draw_pixmap(
+        0,
+        0,
+        overlay.as_ref(),
+        &tiny_skia::PixmapPaint::default(),
+        tiny_skia::Transform::identity(),
+        None,
+    );
+    Ok(pixmap)
+}
+
+fn background_pixmap(config: &OutputConfig) -> Result<tiny_skia::Pixmap, RasterError> {
+    let mut pixmap = tiny_skia::Pixmap::new(config.width, config.height)
+        .ok_or_else(|| RasterError::RenderFailed("Failed to create pixmap".to_string()))?;
+    if let Some((r, g, b, a)) = config.background {
+        pixmap.fill(tiny_skia::Color::from_rgba8(r, g, b, a));
+    }
+    Ok(pixmap)
+}
+
+/// Draws `svg` over what's already on `pixmap`, scaled to fill it.
+fn render_onto(
+    svg: &str,
+    pixmap: &mut tiny_skia::Pixmap,
+    fontdb: &usvg::fontdb::Database,
+) -> Result<(), RasterError> {
+    let options = usvg::Options::default();
+    let tree = usvg::Tree::from_str(svg, &options, fontdb)
+        .map_err(|e| RasterError::RenderFailed(format!("Failed to parse SVG: {}", e)))?;
+
+    let transform = tiny_skia::Transform::from_scale(
+        pixmap.width() as f32 / tree.size().width(),
+        pixmap.height() as f32 / tree.size().height(),
+    );
+
+    resvg::render(&tree, transform, &mut pixmap.as_mut());
+    Ok(())
+}
+
+fn inject_watermark(
+    svg: &str,
+    width: u32,
+    height: u32,
+) -> String {
+    let font_size = ((height as f32 * 0.020) as u32).max(13);
+    let padding = 16u32;
+    let text_x = width / 2;
+    let text_y = height.saturating_sub(padding);
+
+    let nodes = format!(
+        "<text x=\"{text_x}\" y=\"{text_y}\" font-family=\"Geist Pixel, DejaVu Sans Mono, DejaVu Sans, sans-serif\" font-size=\"{font_size}\" fill=\"rgb(0,0,0)\" text-anchor=\"middle\">created with rideviz.online</text>"
+    );
+
+    svg.replacen("</svg>", &format!("{nodes}</svg>"), 1)
+}
@@ -0,0 +1,967 @@
+use std::borrow::Cow;
+use std::fmt::{Display, Write};
+
+use crate::error::RenderError;
+use crate::types::gradient::{Gradient, Rgb};
+use crate::types::viz::{CameraMode, RenderOptions, RoutePoint, StatOverlayItem, TitleCard, VizData};
+
+const ELEVATION_GAMMA: f64 = 0.82;
+const EXTRUSION_RATIO: f64 = 0.24;
+const ELEVATION_RANGE_DIVISOR: f64 = 600.0;
+const ELEVATION_SCALE_MIN: f64 = 0.7;
+const ELEVATION_SCALE_MAX: f64 = 1.4;
+const ISOMETRIC_ANGLE_DEG: f64 = 30.0;
+pub(crate) const WALL_FILL_OPACITY: f64 = 0.24;
+pub(crate) const GROUND_OPACITY: f64 = 0.14;
+pub(crate) const OUTLINE_OPACITY: f64 = 0.55;
+pub(crate) const OUTLINE_WIDTH_RATIO: f32 = 1.5;
+pub(crate) const GLOW_OPACITY: f64 = 0.6;
+pub(crate) const GLOW_WIDTH_RATIO: f32 = 2.4;
+pub(crate) const GLOW_BLUR_STD_DEV: f64 = 6.0;
+pub(crate) const ENDPOINT_RADIUS_RATIO: f64 = 2.2;
+pub(crate) const ENDPOINT_OPACITY: f64 = 0.95;
+const COLOR_BUCKETS: usize = 48;
+/// Rough SVG bytes per wall, route path point and stat line, for sizing the output up front.
+const WALL_BYTES: usize = 150;
+const PATH_POINT_BYTES: usize = 50;
+const STAT_LINE_BYTES: usize = 400;
+const LEGACY_WIDE_WIDTH: f64 = 1920.0;
+const LEGACY_WIDE_HEIGHT: f64 = 1080.0;
+const FOLLOW_CAMERA_ZOOM: f64 = 2.6;
+/// Total turntable sweep over one animation when `rotate` is enabled.
+pub const ROTATION_SWEEP_DEG: f64 = 360.0;
+
+#[derive(Clone, Copy)]
+struct ProjectedPoint {
+    ground: (f64, f64),
+    top: (f64, f64),
+    value: Option<f64>,
+}
+
+/// The part of drawing a route that is the same for every frame: the simplified points,
+/// projected to the isometric view and fitted to the canvas. Make it once with
+/// [`project_route`] and draw any number of frames from it, with options that agree on
+/// everything in [`projection_key`].
+#[derive(Clone)]
+pub struct RouteProjection {
+    points: Vec<RoutePoint>,
+    min_elev: f64,
+    elev_range: f64,
+    extrusion_height: f64,
+    projection_width: f64,
+    projection_height: f64,
+    /// Top-left corner of the area the route is fitted into.
+    origin: (f64, f64),
+    view_width: f64,
+    view_height: f64,
+    /// Turntable animations fit the circle the route sweeps, the same at every angle.
+    rotating_bounds: Option<ContentBounds>,
+    /// At the resting angle; turned frames are projected again.
+    fitted: Vec<ProjectedPoint>,
+}
+
+impl RouteProjection {
+    fn fitted_at(&self, rotation_deg: f64) -> Result<Cow<'_, [ProjectedPoint]>, RenderError> {
+        if rotation_deg == 0.0 {
+            return Ok(Cow::Borrowed(&self.fitted));
+        }
+        self.fit(rotation_deg).map(Cow::Owned)
+    }
+
+    fn fit(&self, rotation_deg: f64) -> Result<Vec<ProjectedPoint>, RenderError> {
+        let points: Vec<&RoutePoint> = self.points.iter().collect();
+        let projected = project_to_isometric(
+            &points,
+            self.projection_width,
+            self.projection_height,
+            self.min_elev,
+            self.elev_range,
+            self.extrusion_height,
+            ISOMETRIC_ANGLE_DEG + rotation_deg,
+        );
+        let bounds = self
+            .rotating_bounds
+            .unwrap_or_else(|| ContentBounds::of(&projected));
+        fit_to_viewport(&projected, bounds, self.origin, self.view_width, self.view_height)
+    }
+}
+
+/// Simplifies, projects and fits the route for `options`.
+#[tracing::instrument(skip_all)]
+pub fn project_route(data: &VizData, options: &RenderOptions) -> Result<RouteProjection, RenderError> {
+    let width = options.width as f64;
+    let height = options.height as f64;
+    let padding = options.padding as f64;
+    let safe_top = options.safe_area_top as f64;
+    let view_width = width - 2.0 * padding;
+    let view_height = height - 2.0 * padding - safe_top - options.safe_area_bottom as f64;
+    if view_width <= 0.0 || view_height <= 0.0 {
+        return Err(RenderError::SvgError("Invalid viewport size".to_string()));
+    }
+
+    let filtered_points = filter_route_points(&data.points, options.simplify)?;
+    let (min_elev, max_elev) = route_elevation_bounds(&filtered_points);
+    let elev_range = (max_elev - min_elev).max(f64::EPSILON);
+    let elevation_scale =
+        ((max_elev - min_elev) / ELEVATION_RANGE_DIVISOR).clamp(ELEVATION_SCALE_MIN, ELEVATION_SCALE_MAX);
+    // Keep a fixed legacy-wide camera basis (pre multi-format behavior) for all outputs.
+    let projection_width = (LEGACY_WIDE_WIDTH - 2.0 * padding).max(1.0);
+    let projection_height = (LEGACY_WIDE_HEIGHT - 2.0 * padding).max(1.0);
+    let extrusion_height = projection_height * EXTRUSION_RATIO * elevation_scale;
+
+    let rotating_bounds = options.rotate.then(|| {
+        // Fit the circle swept by the ground plane so the scale stays fixed while rotating.
+        let radius = filtered_points
+            .iter()
+            .map(|point| {
+                let x = (point.x - 0.5) * projection_width;
+                let y = (0.5 - point.y) * projection_height;
+                (x * x + y * y).sqrt()
+            })
+            .fold(0.0_f64, f64::max);
+        ContentBounds {
+            min_x: -radius,
+            max_x: radius,
+            min_y: -radius - extrusion_height,
+            max_y: radius,
+        }
+    });
+    let mut projection = RouteProjection {
+        points: filtered_points.into_iter().cloned().collect(),
+        min_elev,
+        elev_range,
+        extrusion_height,
+        projection_width,
+        projection_height,
+        origin: (padding, padding + safe_top),
+        view_width,
+        view_height,
+        rotating_bounds,
+        fitted: Vec::new(),
+    };
+    projection.fitted = projection.fit(0.0)?;
+    Ok(projection)
+}
+
+/// The options a [`RouteProjection`] of a given activity depends on, for caching it.
+/// Includes `color_by`, which picks the values `prepare` attaches to the points, and
+/// `reverse` and `start_offset_km`, which set their order.
+pub fn projection_key(options: &RenderOptions) -> String {
+    format!(
+        "{}x{}:{}:{}-{}:{}:{}:{:?}:{}:{:?}",
+        options.width,
+        options.height,
+        options.padding,
+        options.safe_area_top,
+        options.safe_area_bottom,
+        options.simplify,
+        options.rotate,
+        options.color_by,
+        options.reverse,
+        options.start_offset_km
+    )
+}
+
+/// Draws one frame as SVG with the route revealed up to `progress` (0.0-1.0), turned by
+/// `rotation_deg`, with `stats` as an overlay.
+pub fn render_svg_frame(
+    data: &VizData,
+    options: &RenderOptions,
+    progress: f64,
+    rotation_deg: f64,
+    stats: &[StatOverlayItem],
+) -> Result<String, RenderError> {
+    render_projected_svg_frame(&project_route(data, options)?, options, progress, rotation_deg, stats)
+}
+
+/// [`render_svg_frame`] from a projection made earlier.
+#[tracing::instrument(skip(projection, options, stats))]
+pub fn render_projected_svg_frame(
+    projection: &RouteProjection,
+    options: &RenderOptions,
+    progress: f64,
+    rotation_deg: f64,
+    stats: &[StatOverlayItem],
+) -> Result<String, RenderError> {
+    render_route_3d(projection, options, progress.clamp(0.0, 1.0), rotation_deg, stats)
+}
+
+/// Draws just the stats overlay on a transparent canvas. It is the same on every
+/// animation frame, so animations rasterize it once and composite it over each frame
+/// drawn without stats.
+pub fn render_svg_overlay(options: &RenderOptions, stats: &[StatOverlayItem]) -> String {
+    let mut svg = String::with_capacity(256 + stats.len() * STAT_LINE_BYTES);
+    write_svg_open(&mut svg, options.width, options.height);
+    svg.push_str("\n  ");
+    write_stats_overlay(&mut svg, stats, options);
+    svg.push_str("\n</svg>");
+    svg
+}
+
+fn render_route_3d(
+    projection: &RouteProjection,
+    options: &RenderOptions,
+    progress: f64,
+    rotation_deg: f64,
+    stats: &[StatOverlayItem],
+) -> Result<String, RenderError> {
+    let FrameGeometry {
+        walls,
+        ground: ground_coords,
+        top: top_coords,
+        values: top_values,
+        has_extent,
+    } = frame_geometry(projection, options, progress, rotation_deg)?;
+    let glow = options.glow && has_extent;
+    let tension = options.curve_tension;
+
+    // Sized for the walls plus the four route paths (ground, outline, glow, top), so the
+    // buffer grows at most once or twice.
+    let mut svg = String::with_capacity(
+        2048 + walls.len() * WALL_BYTES + top_coords.len() * 4 * PATH_POINT_BYTES + stats.len() * STAT_LINE_BYTES,
+    );
+    write_svg_open(&mut svg, options.width, options.height);
+    svg.push_str("\n  <defs>\n    ");
+    write_linear_gradient(&mut svg, "routeGradient", &options.gradient);
+    svg.push_str("\n    ");
+    if glow {
+        write_glow_filter(&mut svg);
+    }
+    svg.push_str("\n  </defs>\n  ");
+    write_wall_polygons(&mut svg, &walls);
+    svg.push_str("\n  ");
+
+    svg.push_str(r#"<path d=""#);
+    write_route_path(&mut svg, &ground_coords, tension);
+    let _ = write!(
+        svg,
+        r##"" fill="none" stroke="#FFFFFF" stroke-opacity="{}" stroke-width="{:.1}" stroke-linecap="round" stroke-linejoin="round"/>"##,
+        GROUND_OPACITY,
+        ground_stroke_width(options)
+    );
+    svg.push_str("\n  ");
+    svg.push_str(r#"<path d=""#);
+    write_route_path(&mut svg, &top_coords, tension);
+    let _ = write!(
+        svg,
+        r##"" fill="none" stroke="#FFFFFF" stroke-opacity="{}" stroke-width="{:.1}" stroke-linecap="round" stroke-linejoin="round"/>"##,
+        OUTLINE_OPACITY,
+        options.stroke_width * OUTLINE_WIDTH_RATIO
+    );
+    svg.push_str("\n  ");
+
+    if glow {
+        let glow_width = options.stroke_width * GLOW_WIDTH_RATIO;
+        if options.color_by.is_some() {
+            let _ = write!(svg, r#"<g filter="url(#glow)" opacity="{}">"#, GLOW_OPACITY);
+            write_segment_paths(&mut svg, &top_coords, Some(&top_values), glow_width, &options.gradient);
+            svg.push_str("</g>");
+        } else {
+            svg.push_str(r#"<path d=""#);
+            write_route_path(&mut svg, &top_coords, tension);
+            let _ = write!(
+                svg,
+                r#"" fill="none" stroke="url(#routeGradient)" stroke-width="{:.1}" stroke-linecap="round" stroke-linejoin="round" filter="url(#glow)" opacity="{}"/>"#,
+                glow_width,
+                GLOW_OPACITY
+            );
+        }
+    }
+    svg.push_str("\n  ");
+
+    if options.color_by.is_some() {
+        write_segment_paths(
+            &mut svg,
+            &top_coords,
+            Some(&top_values),
+            options.stroke_width,
+            &options.gradient,
+        );
+    } else {
+        svg.push_str(r#"<path d=""#);
+        write_route_path(&mut svg, &top_coords, tension);
+        let _ = write!(
+            svg,
+            r#"" fill="none" stroke="url(#routeGradient)" stroke-width="{:.1}" stroke-linecap="round" stroke-linejoin="round"/>"#,
+            options.stroke_width
+        );
+    }
+    svg.push_str("\n  ");
+    write_3d_endpoint_dots(&mut svg, &top_coords, options);
+    svg.push_str("\n  ");
+    write_stats_overlay(&mut svg, stats, options);
+    svg.push_str("\n</svg>");
+    Ok(svg)
+}
+
+fn write_svg_open(out: &mut String, width: impl Display, height: impl Display) {
+    let _ = write!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" viewBox="0 0 {} {}">"#,
+        width, height, width, height
+    );
+}
+
+/// One frame of the route after projection, reveal, camera and smoothing, in canvas
+/// coordinates. Both the SVG renderer and the direct drawing path in
+/// [`draw`](super::draw) start from this.
+pub(crate) struct FrameGeometry {
+    /// Back to front.
+    pub walls: Vec<Wall>,
+    pub ground: Vec<(f64, f64)>,
+    pub top: Vec<(f64, f64)>,
+    pub values: Vec<Option<f64>>,
+    /// False while the revealed route is still too short to see, when glow is skipped.
+    pub has_extent: bool,
+}
+
+/// Quad between two consecutive ground and top points.
+pub(crate) struct Wall {
+    pub corners: [(f64, f64); 4],
+    pub color: Rgb,
+}
+
+pub(crate) fn frame_geometry(
+    projection: &RouteProjection,
+    options: &RenderOptions,
+    progress: f64,
+    rotation_deg: f64,
+) -> Result<FrameGeometry, RenderError> {
+    let fitted = projection.fitted_at(rotation_deg)?;
+    let revealed = reveal_projected_points(&fitted, progress.clamp(0.0, 1.0));
+    let revealed = match options.camera {
+        CameraMode::Overview => revealed,
+        CameraMode::Follow => follow_camera(&revealed, safe_center(options)),
+    };
+    let smoothed = subdivide_projected_catmull(&revealed, options.curve_tension, options.wall_subdivisions);
+
+    let has_extent = smoothed.len() >= 2 && {
+        let first = smoothed.first().unwrap().top;
+        smoothed.iter().any(|p| {
+            let dx = p.top.0 - first.0;
+            let dy = p.top.1 - first.1;
+            dx * dx + dy * dy > 1.0
+        })
+    };
+    let walls = wall_quads(&smoothed, &options.gradient);
+    let (ground, top, values) = split_projected_points(&smoothed);
+    Ok(FrameGeometry {
+        walls,
+        ground,
+        top,
+        values,
+        has_extent,
+    })
+}
+
+/// Renders an intro/outro card: a gradient heading, optional subheading and a
+/// centered two-column list of stats.
+pub fn render_title_card_svg(card: &TitleCard, options: &RenderOptions) -> String {
+    let width = options.width as f64;
+    let height = options.height as f64;
+    let center_x = width * 0.5;
+    let heading_size = (height * 0.07).clamp(28.0, 120.0);
+    let subheading_size = heading_size * 0.42;
+    let item_size = (height * 0.036).clamp(16.0, 56.0);
+    let line_gap = item_size * 1.6;
+    let column_gap = item_size * 0.6;
+
+    let subheading_block = if card.subheading.is_some() {
+        subheading_size * 1.8
+    } else {
+        0.0
+    };
+    let items_block = if card.items.is_empty() {
+        0.0
+    } else {
+        heading_size * 0.6 + card.items.len() as f64 * line_gap
+    };
+    let block_height = heading_size + subheading_block + items_block;
+    let mut y = safe_center(options).1 - block_height * 0.5 + heading_size;
+
+    let mut svg = String::with_capacity(1024 + card.items.len() * STAT_LINE_BYTES);
+    write_svg_open(&mut svg, width, height);
+    svg.push_str("\n  <defs>\n    ");
+    write_linear_gradient(&mut svg, "titleGradient", &options.gradient);
+    svg.push_str("\n  </defs>\n  ");
+    let _ = write!(
+        svg,
+        r#"<text x="{:.2}" y="{:.2}" font-family="Geist Sans, Geist, DejaVu Sans, sans-serif" font-size="{:.2}" font-weight="700" fill="url(#titleGradient)" text-anchor="middle">{}</text>"#,
+        center_x,
+        y,
+        heading_size,
+        escape_xml(&card.heading)
+    );
+    if let Some(subheading) = &card.subheading {
+        y += subheading_size * 1.8;
+        let _ = write!(
+            svg,
+            r#"<text x="{:.2}" y="{:.2}" font-family="Geist Sans, Geist, DejaVu Sans, sans-serif" font-size="{:.2}" font-weight="600" letter-spacing="0.4" fill="{}" fill-opacity="0.78" text-anchor="middle">{}</text>"#,
+            center_x,
+            y,
+            subheading_size,
+            options.gradient.interpolate(0.5),
+            escape_xml(subheading)
+        );
+    }
+    if !card.items.is_empty() {
+        y += heading_size * 0.6;
+    }
+    for stat in &card.items {
+        y += line_gap;
+        let color = options.gradient.interpolate(stat.color_t);
+        let _ = write!(
+            svg,
+            r#"<text x="{:.2}" y="{:.2}" font-family="Geist Sans, Geist, DejaVu Sans, sans-serif" font-size="{:.2}" font-weight="600" letter-spacing="0.2" fill="{}" fill-opacity="0.78" text-anchor="end">{}</text>
+<text x="{:.2}" y="{:.2}" font-family="Geist Sans, Geist, DejaVu Sans, sans-serif" font-size="{:.2}" font-weight="700" fill="{}">{}</text>"#,
+            center_x - column_gap,
+            y,
+            item_size * 0.68,
+            color,
+            stat.label,
+            center_x + column_gap,
+            y,
+            item_size,
+            color,
+            stat.value
+        );
+    }
+    svg.push_str("\n</svg>");
+    svg
+}
+
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+fn filter_route_points(points: &[RoutePoint], simplify: usize) -> Result<Vec<&RoutePoint>, RenderError> {
+    let stride = simplify.max(1);
+    let filtered: Vec<&RoutePoint> = points
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| i % stride == 0 || *i == points.len() - 1)
+        .map(|(_, point)| point)
+        .collect();
+    if filtered.len() < 2 {
+        return Err(RenderError::SvgError(
+            "Not enough route points for 3D route".to_string(),
+        ));
+    }
+    Ok(filtered)
+}
+
+/// Lowest and highest elevation on the route; `(0, 0)` without any, which draws the
+/// route flat on the ground.
+fn route_elevation_bounds(points: &[&RoutePoint]) -> (f64, f64) {
+    let valid: Vec<f64> = points.iter().filter_map(|point| point.elevation).collect();
+    if valid.is_empty() {
+        return (0.0, 0.0);
+    }
+    let min_elev = valid.iter().copied().fold(f64::INFINITY, f64::min);
+    let max_elev = valid.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    (min_elev, max_elev)
+}
+
+fn project_to_isometric(
+    points: &[&RoutePoint],
+    projection_width: f64,
+    projection_height: f64,
+    min_elev: f64,
+    elev_range: f64,
+    extrusion_height: f64,
+    angle_deg: f64,
+) -> Vec<ProjectedPoint> {
+    let angle = angle_deg.to_radians();
+    let sin_angle = angle.sin();
+    let cos_angle = angle.cos();
+
+    points
+        .iter()
+        .map(|point| {
+            // Rotate around the center of the normalized route box.
+            let x = (point.x - 0.5) * projection_width;
+            let y = (0.5 - point.y) * projection_height;
+            let ground_x = x * cos_angle + y * sin_angle;
+            let ground_y = -x * sin_angle + y * cos_angle;
+            let norm_elev = point
+                .elevation
+                .map(|elevation| (elevation - min_elev) / elev_range)
+                .unwrap_or(0.0)
+                .powf(ELEVATION_GAMMA);
+            let top_y = ground_y - norm_elev * extrusion_height;
+            ProjectedPoint {
+                ground: (ground_x, ground_y),
+                top: (ground_x, top_y),
+                value: point.value,
+            }
+        })
+        .collect()
+}
+
+#[derive(Clone, Copy)]
+struct ContentBounds {
+    min_x: f64,
+    max_x: f64,
+    min_y: f64,
+    max_y: f64,
+}
+
+impl ContentBounds {
+    fn of(points: &[ProjectedPoint]) -> Self {
+        let mut bounds = Self {
+            min_x: f64::INFINITY,
+            max_x: f64::NEG_INFINITY,
+            min_y: f64::INFINITY,
+            max_y: f64::NEG_INFINITY,
+        };
+        for point in points {
+            bounds.min_x = bounds.min_x.min(point.ground.0).min(point.top.0);
+            bounds.max_x = bounds.max_x.max(point.ground.0).max(point.top.0);
+            bounds.min_y = bounds.min_y.min(point.ground.1).min(point.top.1);
+            bounds.max_y = bounds.max_y.max(point.ground.1).max(point.top.1);
+        }
+        bounds
+    }
+}
+
+fn fit_to_viewport(
+    points: &[ProjectedPoint],
+    bounds: ContentBounds,
+    origin: (f64, f64),
+    view_width: f64,
+    view_height: f64,
+) -> Result<Vec<ProjectedPoint>, RenderError> {
+    if points.is_empty() {
+        return Err(RenderError::SvgError("No projected points".to_string()));
+    }
+
+    let ContentBounds {
+        min_x,
+        max_x,
+        min_y,
+        max_y,
+    } = bounds;
+    let content_width = (max_x - min_x).max(f64::EPSILON);
+    let content_height = (max_y - min_y).max(f64::EPSILON);
+    let scale = (view_width / content_width).min(view_height / content_height);
+    let offset_x = origin.0 + (view_width - content_width * scale) * 0.5;
+    let offset_y = origin.1 + (view_height - content_height * scale) * 0.5;
+
+    Ok(points
+        .iter()
+        .map(|point| ProjectedPoint {
+            ground: (
+                offset_x + (point.ground.0 - min_x) * scale,
+                offset_y + (point.ground.1 - min_y) * scale,
+            ),
+            top: (
+                offset_x + (point.top.0 - min_x) * scale,
+                offset_y + (point.top.1 - min_y) * scale,
+            ),
+            value: point.value,
+        })
+        .collect())
+}
+
+/// Middle of the canvas between the safe areas.
+fn safe_center(options: &RenderOptions) -> (f64, f64) {
+    let top = options.safe_area_top as f64;
+    let bottom = options.height as f64 - options.safe_area_bottom as f64;
+    (options.width as f64 * 0.5, (top + bottom) * 0.5)
+}
+
+/// Zooms into the fitted scene and puts the reveal tip at `center`. Applied to the
+/// coordinates rather than as an SVG transform so stroke widths stay constant.
+fn follow_camera(points: &[ProjectedPoint], center: (f64, f64)) -> Vec<ProjectedPoint> {
+    let Some(tip) = points.last() else {
+        return Vec::new();
+    };
+    let focus = tip.top;
+    let zoom = |p: (f64, f64)| {
+        (
+            center.0 + (p.0 - focus.0) * FOLLOW_CAMERA_ZOOM,
+            center.1 + (p.1 - focus.1) * FOLLOW_CAMERA_ZOOM,
+        )
+    };
+    points
+        .iter()
+        .map(|point| ProjectedPoint {
+            ground: zoom(point.ground),
+            top: zoom(point.top),
+            value: point.value,
+        })
+        .collect()
+}
+
+fn wall_quads(points: &[ProjectedPoint], gradient: &Gradient) -> Vec<Wall> {
+    let mut walls: Vec<(f64, Wall)> = Vec::new();
+    for i in 0..points.len().saturating_sub(1) {
+        let current = points[i];
+        let next = points[i + 1];
+        let t = current
+            .value
+            .unwrap_or_else(|| i as f64 / (points.len().saturating_sub(1).max(1)) as f64);
+        let wall = Wall {
+            corners: [current.ground, current.top, next.top, next.ground],
+            color: gradient.color(remap_color_contrast(t)),
+        };
+        walls.push(((current.ground.1 + next.ground.1) * 0.5, wall));
+    }
+    walls.sort_by(|a, b| a.0.total_cmp(&b.0));
+    walls.into_iter().map(|(_, wall)| wall).collect()
+}
+
+fn write_wall_polygons(out: &mut String, walls: &[Wall]) {
+    for wall in walls {
+        let [a, b, c, d] = wall.corners;
+        let _ = write!(
+            out,
+            r#"<polygon points="{:.2},{:.2} {:.2},{:.2} {:.2},{:.2} {:.2},{:.2}" fill="{}" fill-opacity="{:.2}"/>"#,
+            a.0, a.1, b.0, b.1, c.0, c.1, d.0, d.1, wall.color, WALL_FILL_OPACITY
+        );
+    }
+}
+
+fn split_projected_points(
+    points: &[ProjectedPoint],
+) -> (Vec<(f64, f64)>, Vec<(f64, f64)>, Vec<Option<f64>>) {
+    let ground_coords = points.iter().map(|point| point.ground).collect();
+    let top_coords = points.iter().map(|point| point.top).collect();
+    let top_values = points.iter().map(|point| point.value).collect();
+    (ground_coords, top_coords, top_values)
+}
+
+fn write_stats_overlay(out: &mut String, stats: &[StatOverlayItem], options: &RenderOptions) {
+    if stats.is_empty() {
+        return;
+    }
+
+    let start_x = options.padding as f64 + 14.0;
+    let start_y = options.safe_area_top as f64 + options.padding as f64 + 28.0;
+    let font_size = ((options.height as f64) * 0.024).clamp(12.0, 34.0);
+    let line_gap = (font_size * 1.38).clamp(18.0, 52.0);
+    let label_dx = (font_size * 6.1).clamp(72.0, 280.0);
+
+    out.push_str(r#"<g id="statsOverlay">"#);
+    for (idx, stat) in stats.iter().enumerate() {
+        let y = start_y + idx as f64 * line_gap;
+        let color = options.gradient.interpolate(stat.color_t);
+        let _ = write!(
+            out,
+            r#"<text x="{:.2}" y="{:.2}" font-family="Geist Sans, Geist, DejaVu Sans, sans-serif" font-size="{:.2}" font-weight="600" letter-spacing="0.2" fill="{}" fill-opacity="0.78">{}</text>
+<text x="{:.2}" y="{:.2}" font-family="Geist Sans, Geist, DejaVu Sans, sans-serif" font-size="{:.2}" font-weight="700" fill="{}">{}</text>"#,
+            start_x,
+            y,
+            font_size * 0.68,
+            color,
+            stat.label,
+            start_x + label_dx,
+            y,
+            font_size,
+            color,
+            stat.value
+        );
+    }
+    out.push_str("</g>");
+}
+
+/// Path data for the route: Bezier curves with tension, straight lines without.
+fn write_route_path(out: &mut String, coords: &[(f64, f64)], curve_tension: f32) {
+    let Some(&(x, y)) = coords.first() else {
+        return;
+    };
+    let _ = write!(out, "M {:.2} {:.2}", x, y);
+    for i in 0..coords.len() - 1 {
+        let (x, y) = coords[i + 1];
+        if curve_tension > 0.0 {
+            let ((cp1x, cp1y), (cp2x, cp2y)) = smooth_control_points(coords, i, curve_tension);
+            let _ = write!(out, " C {:.2} {:.2} {:.2} {:.2} {:.2} {:.2}", cp1x, cp1y, cp2x, cp2y, x, y);
+        } else {
+            let _ = write!(out, " L {:.2} {:.2}", x, y);
+        }
+    }
+}
+
+fn write_3d_endpoint_dots(out: &mut String, top_coords: &[(f64, f64)], options: &RenderOptions) {
+    if top_coords.len() < 2 {
+        return;
+    }
+    let start = top_coords[0];
+    let end = top_coords[top_coords.len() - 1];
+    let radius = options.stroke_width as f64 * ENDPOINT_RADIUS_RATIO;
+    let (start_color, end_color) = endpoint_colors(&options.gradient);
+    let _ = write!(
+        out,
+        r#"<circle cx="{:.2}" cy="{:.2}" r="{:.2}" fill="{}" opacity="{:.2}"/>
+  <circle cx="{:.2}" cy="{:.2}" r="{:.2}" fill="{}" opacity="{:.2}"/>"#,
+        start.0, start.1, radius, start_color, ENDPOINT_OPACITY, end.0, end.1, radius, end_color, ENDPOINT_OPACITY
+    );
+}
+
+pub(crate) fn endpoint_colors(gradient: &Gradient) -> (Rgb, Rgb) {
+    (gradient.color(0.0), gradient.color(1.0))
+}
+
+pub(crate) fn ground_stroke_width(options: &RenderOptions) -> f32 {
+    (options.stroke_width * 0.9).max(1.0)
+}
+
+fn write_glow_filter(out: &mut String) {
+    let _ = write!(
+        out,
+        r#"<filter id="glow" x="-20%" y="-20%" width="140%" height="140%">
+      <feGaussianBlur stdDeviation="{}" result="blur"/>
+      <feMerge>
+        <feMergeNode in="blur"/>
+        <feMergeNode in="blur"/>
+        <feMergeNode in="SourceGraphic"/>
+      </feMerge>
+    </filter>"#,
+        GLOW_BLUR_STD_DEV
+    );
+}
+
+/// Bezier control points for the curve from `points[i]` to `points[i + 1]`.
+pub(crate) fn smooth_control_points(points: &[(f64, f64)], i: usize, tension: f32) -> ((f64, f64), (f64, f64)) {
+    let t = tension as f64;
+    let p0 = if i > 0 { points[i - 1] } else { points[i] };
+    let p1 = points[i];
+    let p2 = points[i + 1];
+    let p3 = if i + 2 < points.len() { points[i + 2] } else { points[i + 1] };
+    (
+        (p1.0 + (p2.0 - p0.0) * t, p1.1 + (p2.1 - p0.1) * t),
+        (p2.0 - (p3.0 - p1.0) * t, p2.1 - (p3.1 - p1.1) * t),
+    )
+}
+
+fn write_linear_gradient(out: &mut String, id: &str, gradient: &Gradient) {
+    let stops = &gradient.colors;
+    let _ = write!(out, r#"<linearGradient id="{}" x1="0%" y1="0%" x2="100%" y2="0%">"#, id);
+    out.push_str("\n      ");
+    for (i, color) in stops.iter().enumerate() {
+        let offset = if stops.len() == 1 {
+            0.0
+        } else {
+            i as f64 / (stops.len() - 1) as f64 * 100.0
+        };
+        let _ = write!(
+            out,
+            r#"<stop offset="{:.1}%" style="stop-color:{};stop-opacity:1" />"#,
+            offset, color
+        );
+    }
+    out.push_str("\n    </linearGradient>");
+}
+
+fn write_segment_paths(
+    out: &mut String,
+    coords: &[(f64, f64)],
+    values: Option<&[Option<f64>]>,
+    stroke_width: f32,
+    gradient: &Gradient,
+) {
+    for bucket in segment_buckets(coords, values) {
+        out.push_str(r#"<path d=""#);
+        for (i, ((x1, y1), (x2, y2))) in bucket.segments.iter().enumerate() {
+            if i > 0 {
+                out.push(' ');
+            }
+            let _ = write!(out, "M {:.2} {:.2} L {:.2} {:.2}", x1, y1, x2, y2);
+        }
+        let _ = write!(
+            out,
+            r#"" fill="none" stroke="{}" stroke-width="{:.1}" stroke-linecap="round" stroke-linejoin="round"/>"#,
+            gradient.color(bucket.color_t),
+            stroke_width
+        );
+    }
+}
+
+/// A line between two projected points.
+pub(crate) type Segment = ((f64, f64), (f64, f64));
+
+/// Route segments drawn in one color.
+pub(crate) struct SegmentBucket {
+    /// Position on the gradient.
+    pub color_t: f64,
+    pub segments: Vec<Segment>,
+}
+
+/// Route segments grouped into `COLOR_BUCKETS` colors, one entry per non-empty bucket,
+/// so per-value coloring costs one path per color rather than one per segment.
+pub(crate) fn segment_buckets(coords: &[(f64, f64)], values: Option<&[Option<f64>]>) -> Vec<SegmentBucket> {
+    if coords.len() < 2 {
+        return Vec::new();
+    }
+    let mut buckets = vec![Vec::new(); COLOR_BUCKETS];
+    for i in 0..coords.len() - 1 {
+        let fallback_t = i as f64 / (coords.len() - 1) as f64;
+        let color_t = values
+            .and_then(|all_values| all_values.get(i))
+            .copied()
+            .flatten()
+            .map(remap_color_contrast)
+            .unwrap_or(fallback_t);
+        let bucket_idx = ((color_t * (COLOR_BUCKETS - 1) as f64).round() as usize).min(COLOR_BUCKETS - 1);
+        buckets[bucket_idx].push((coords[i], coords[i + 1]));
+    }
+    buckets
+        .into_iter()
+        .enumerate()
+        .filter(|(_, segments)| !segments.is_empty())
+        .map(|(bucket_idx, segments)| SegmentBucket {
+            color_t: bucket_idx as f64 / (COLOR_BUCKETS - 1).max(1) as f64,
+            segments,
+        })
+        .collect()
+}
+
+fn remap_color_contrast(value: f64) -> f64 {
+    let v = value.clamp(0.0, 1.0);
+    ((v - 0.5) * 1.55 + 0.5).clamp(0.0, 1.0)
+}
+
+fn reveal_projected_points(points: &[ProjectedPoint], progress: f64) -> Vec<ProjectedPoint> {
+    if points.len() <= 1 {
+        return points.to_vec();
+    }
+    let progress = progress.clamp(0.0, 1.0);
+    if progress >= 1.0 {
+        return points.to_vec();
+    }
+
+    let segment_lengths: Vec<f64> = points
+        .windows(2)
+        .map(|pair| distance_2d(pair[0].top, pair[1].top))
+        .collect();
+    let total_length: f64 = segment_lengths.iter().sum();
+    if total_length <= f64::EPSILON {
+        return vec![points[0]];
+    }
+
+    let target_length = total_length * progress;
+    let mut traveled = 0.0;
+    let mut out = vec![points[0]];
+    for (idx, segment_length) in segment_lengths.iter().copied().enumerate() {
+        if segment_length <= f64::EPSILON {
+            continue;
+        }
+        let next_traveled = traveled + segment_length;
+        if next_traveled < target_length {
+            out.push(points[idx + 1]);
+            traveled = next_traveled;
+            continue;
+        }
+        let local_t = ((target_length - traveled) / segment_length).clamp(0.0, 1.0);
+        out.push(ProjectedPoint {
+            ground: lerp_point(points[idx].ground, points[idx + 1].ground, local_t),
+            top: lerp_point(points[idx].top, points[idx + 1].top, local_t),
+            value: lerp_optional(points[idx].value, points[idx + 1].value, local_t),
+        });
+        return out;
+    }
+    points.to_vec()
+}
+
+fn distance_2d(a: (f64, f64), b: (f64, f64)) -> f64 {
+    let dx = b.0 - a.0;
+    let dy = b.1 - a.1;
+    (dx * dx + dy * dy).sqrt()
+}
+
+fn lerp_point(a: (f64, f64), b: (f64, f64), t: f64) -> (f64, f64) {
+    (a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t)
+}
+
+fn lerp_optional(a: Option<f64>, b: Option<f64>, t: f64) -> Option<f64> {
+    match (a, b) {
+        (Some(x), Some(y)) => Some(x + (y - x) * t),
+        (Some(x), None) => Some(x),
+        (None, Some(y)) => Some(y),
+        (None, None) => None,
+    }
+}
+
+fn subdivide_projected_catmull(
+    points: &[ProjectedPoint],
+    tension: f32,
+    subdivisions: usize,
+) -> Vec<ProjectedPoint> {
+    if points.len() < 3 || subdivisions < 2 {
+        return points.to_vec();
+    }
+    let curvature = (tension.clamp(0.0, 0.5) as f64 * 2.0).clamp(0.0, 1.0);
+    let mut out = Vec::with_capacity((points.len() - 1) * subdivisions + 1);
+
+    for i in 0..points.len() - 1 {
+        let p0 = if i > 0 { points[i - 1] } else { points[i] };
+        let p1 = points[i];
+        let p2 = points[i + 1];
+        let p3 = if i + 2 < points.len() { points[i + 2] } else { points[i + 1] };
+        for step in 0..subdivisions {
+            if i > 0 && step == 0 {
+                continue;
+            }
+            let t = step as f64 / subdivisions as f64;
+            out.push(ProjectedPoint {
+                ground: catmull_rom_point(p0.ground, p1.ground, p2.ground, p3.ground, t, curvature),
+                top: catmull_rom_point(p0.top, p1.top, p2.top, p3.top, t, curvature),
+                value: catmull_rom_optional(p0.value, p1.value, p2.value, p3.value, t, curvature),
+            });
+        }
+    }
+    out.push(*points.last().unwrap_or(&points[0]));
+    out
+}
+
+fn catmull_rom_point(
+    p0: (f64, f64),
+    p1: (f64, f64),
+    p2: (f64, f64),
+    p3: (f64, f64),
+    t: f64,
+    curvature: f64,
+) -> (f64, f64) {
+    (
+        catmull_rom_scalar(p0.0, p1.0, p2.0, p3.0, t, curvature),
+        catmull_rom_scalar(p0.1, p1.1, p2.1, p3.1, t, curvature),
+    )
+}
+
+fn catmull_rom_scalar(p0: f64, p1: f64, p2: f64, p3: f64, t: f64, curvature: f64) -> f64 {
+    let t2 = t * t;
+    let t3 = t2 * t;
+    let m1 = (p2 - p0) * 0.5 * curvature;
+    let m2 = (p3 - p1) * 0.5 * curvature;
+    (2.0 * t3 - 3.0 * t2 + 1.0) * p1
+        + (t3 - 2.0 * t2 + t) * m1
+        + (-2.0 * t3 + 3.0 * t2) * p2
+        + (t3 - t2) * m2
+}
+
+fn catmull_rom_optional(
+    p0: Option<f64>,
+    p1: Option<f64>,
+    p2: Option<f64>,
+    p3: Option<f64>,
+    t: f64,
+    curvature: f64,
+) -> Option<f64> {
+    match (p0, p1, p2, p3) {
+        (Some(v0), Some(v1), Some(v2), Some(v3)) => {
+            Some(catmull_rom_scalar(v0, v1, v2, v3, t, curvature))
+        }
+        _ => lerp_optional(p1, p2, t),
+    }
+}
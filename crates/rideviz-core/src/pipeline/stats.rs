@@ -0,0 +1,325 @@
+use crate::pipeline::process::haversine_distance;
+use crate::types::activity::{ProcessedActivity, TrackPoint};
+use crate::types::stats::{ActivityStats, Climb, Peak, Split, Zone};
+
+/// Segments slower than this count as stopped.
+const MOVING_SPEED_KMH: f64 = 2.0;
+const SPLIT_KM: f64 = 1.0;
+/// A climb ends once the road drops this far below its highest point.
+const CLIMB_END_DROP_M: f64 = 10.0;
+const MIN_CLIMB_GAIN_M: f64 = 30.0;
+const MIN_CLIMB_GRADE: f64 = 0.03;
+/// Lower bounds of zones 2-5 as shares of the max heart rate.
+const HEART_RATE_ZONES: [f64; 4] = [0.6, 0.7, 0.8, 0.9];
+/// Lower bounds of Coggan zones 2-7 as shares of FTP.
+const POWER_ZONES: [f64; 6] = [0.55, 0.75, 0.9, 1.05, 1.2, 1.5];
+const PEAK_DURATIONS: [u64; 4] = [60, 300, 1200, 3600];
+
+/// The step between two consecutive points. Like the metrics, sensor values are taken
+/// from the later point.
+struct Segment<'a> {
+    from: &'a TrackPoint,
+    to: &'a TrackPoint,
+    distance_km: f64,
+    seconds: Option<f64>,
+}
+
+impl Segment<'_> {
+    /// The segment's duration if it was spent moving.
+    fn moving_seconds(&self) -> Option<f64> {
+        let seconds = self.seconds.filter(|seconds| *seconds > 0.0)?;
+        (self.speed_kmh(seconds) >= MOVING_SPEED_KMH).then_some(seconds)
+    }
+
+    fn speed_kmh(&self, seconds: f64) -> f64 {
+        self.distance_km / (seconds / 3600.0)
+    }
+
+    fn elevation_change(&self) -> Option<f64> {
+        Some(self.to.elevation? - self.from.elevation?)
+    }
+}
+
+/// Splits, climbs, zones, peak efforts and moving time for a stats panel. Heart rate
+/// zones are relative to `max_heart_rate`, or the activity's own maximum; power zones
+/// need an `ftp`.
+#[tracing::instrument(skip_all, fields(points = activity.points.len()))]
+pub fn summarize(activity: &ProcessedActivity, max_heart_rate: Option<u16>, ftp: Option<u16>) -> ActivityStats {
+    let points = &activity.points;
+    let segments: Vec<Segment> = points
+        .windows(2)
+        .map(|pair| Segment {
+            from: &pair[0],
+            to: &pair[1],
+            distance_km: haversine_distance(pair[0].lat, pair[0].lon, pair[1].lat, pair[1].lon),
+            seconds: match (pair[0].time, pair[1].time) {
+                (Some(from), Some(to)) => Some((to - from).num_milliseconds() as f64 / 1000.0),
+                _ => None,
+            },
+        })
+        .collect();
+
+    let elapsed_seconds = match (
+        points.iter().find_map(|point| point.time),
+        points.iter().rev().find_map(|point| point.time),
+    ) {
+        (Some(first), Some(last)) => (last - first).num_seconds().max(0) as u64,
+        _ => 0,
+    };
+    let mut moving_seconds = 0.0;
+    let mut moving_km = 0.0;
+    let mut max_speed_kmh: Option<f64> = None;
+    for segment in &segments {
+        if let Some(seconds) = segment.moving_seconds() {
+            moving_seconds += seconds;
+            moving_km += segment.distance_km;
+            let speed = segment.speed_kmh(seconds);
+            max_speed_kmh = Some(max_speed_kmh.map_or(speed, |max| max.max(speed)));
+        }
+    }
+
+    let elevations = || points.iter().filter_map(|point| point.elevation);
+    let min_elevation_m = elevations().reduce(f64::min);
+    let max_elevation_m = elevations().reduce(f64::max);
+    let elevation_loss_m = segments
+        .iter()
+        .filter_map(Segment::elevation_change)
+        .filter(|change| *change < 0.0)
+        .map(f64::abs)
+        .sum();
+
+    let heart_rate_zones = match max_heart_rate.or(activity.metrics.max_heart_rate) {
+        Some(max) if activity.available_data.has_heart_rate => {
+            zones(&segments, max, &HEART_RATE_ZONES, |point| point.heart_rate)
+        }
+        _ => Vec::new(),
+    };
+    let power_zones = match ftp {
+        Some(ftp) if activity.available_data.has_power => zones(&segments, ftp, &POWER_ZONES, |point| point.power),
+        _ => Vec::new(),
+    };
+
+    ActivityStats {
+        elapsed_seconds,
+        moving_seconds: moving_seconds.round() as u64,
+        avg_moving_speed_kmh: if moving_seconds > 0.0 {
+            moving_km / (moving_seconds / 3600.0)
+        } else {
+            0.0
+        },
+        max_speed_kmh,
+        min_elevation_m,
+        max_elevation_m,
+        elevation_loss_m,
+        splits: splits(&segments),
+        climbs: climbs(&segments),
+        heart_rate_zones,
+        power_zones,
+        peaks: peaks(&segments, elapsed_seconds),
+    }
+}
+
+#[derive(Default)]
+struct SplitTotals {
+    distance_km: f64,
+    seconds: Option<f64>,
+    elevation_gain_m: f64,
+    heart_rate: (u64, u64),
+    power: (u64, u64),
+}
+
+impl SplitTotals {
+    fn add(&mut self, segment: &Segment) {
+        self.distance_km += segment.distance_km;
+        if let Some(seconds) = segment.seconds.filter(|seconds| *seconds > 0.0) {
+            *self.seconds.get_or_insert(0.0) += seconds;
+        }
+        self.elevation_gain_m += segment.elevation_change().unwrap_or(0.0).max(0.0);
+        if let Some(heart_rate) = segment.to.heart_rate {
+            self.heart_rate.0 += heart_rate as u64;
+            self.heart_rate.1 += 1;
+        }
+        if let Some(power) = segment.to.power {
+            self.power.0 += power as u64;
+            self.power.1 += 1;
+        }
+    }
+
+    fn finish(self, index: usize) -> Split {
+        let average = |(sum, count): (u64, u64)| (count > 0).then(|| (sum / count) as u16);
+        Split {
+            index: index as u32 + 1,
+            distance_km: self.distance_km,
+            duration_seconds: self.seconds.map(|seconds| seconds.round() as u64),
+            elevation_gain_m: self.elevation_gain_m,
+            avg_heart_rate: average(self.heart_rate),
+            avg_power: average(self.power),
+        }
+    }
+}
+
+/// Closes a split at the first point past each kilometre, so split lengths vary with
+/// the point spacing.
+fn splits(segments: &[Segment]) -> Vec<Split> {
+    let mut splits = Vec::new();
+    let mut current = SplitTotals::default();
+    for segment in segments {
+        current.add(segment);
+        if current.distance_km >= SPLIT_KM {
+            let index = splits.len();
+            splits.push(std::mem::take(&mut current).finish(index));
+        }
+    }
+    if current.distance_km > 0.0 {
+        let index = splits.len();
+        splits.push(current.finish(index));
+    }
+    splits
+}
+
+/// Climbs run from a low point to the top before the road drops `CLIMB_END_DROP_M`,
+/// kept when they gain enough at a steady enough grade.
+fn climbs(segments: &[Segment]) -> Vec<Climb> {
+    // (distance into the activity, elevation) of every point with an elevation.
+    let mut profile = Vec::new();
+    let mut distance_km = 0.0;
+    if let Some(first) = segments.first() {
+        profile.extend(first.from.elevation.map(|elevation| (0.0, elevation)));
+    }
+    for segment in segments {
+        distance_km += segment.distance_km;
+        profile.extend(segment.to.elevation.map(|elevation| (distance_km, elevation)));
+    }
+
+    let mut climbs = Vec::new();
+    let mut push = |(start_km, start_m): (f64, f64), (top_km, top_m): (f64, f64)| {
+        let gain = top_m - start_m;
+        let length_km = top_km - start_km;
+        if gain >= MIN_CLIMB_GAIN_M && length_km > 0.0 && gain / (length_km * 1000.0) >= MIN_CLIMB_GRADE {
+            climbs.push(Climb {
+                start_km,
+                distance_km: length_km,
+                elevation_gain_m: gain,
+                avg_grade_percent: gain / (length_km * 10.0),
+                top_elevation_m: top_m,
+            });
+        }
+    };
+    let Some(&first) = profile.first() else {
+        return climbs;
+    };
+    let (mut start, mut top) = (first, first);
+    for &point in &profile[1..] {
+        if point.1 >= top.1 {
+            top = point;
+        } else if top.1 - point.1 >= CLIMB_END_DROP_M {
+            push(start, top);
+            (start, top) = (point, point);
+        } else if point.1 < start.1 {
+            (start, top) = (point, point);
+        }
+    }
+    push(start, top);
+    climbs
+}
+
+/// Moving time per zone; `shares` are the lower bounds of zones 2 and up.
+fn zones(
+    segments: &[Segment],
+    basis: u16,
+    shares: &[f64],
+    value: impl Fn(&TrackPoint) -> Option<u16>,
+) -> Vec<Zone> {
+    let bounds: Vec<u16> = shares
+        .iter()
+        .map(|share| (basis as f64 * share).round() as u16)
+        .collect();
+    let mut seconds = vec![0.0; bounds.len() + 1];
+    for segment in segments {
+        if let (Some(moving), Some(value)) = (segment.moving_seconds(), value(segment.to)) {
+            let zone = bounds.iter().filter(|bound| value >= **bound).count();
+            seconds[zone] += moving;
+        }
+    }
+    seconds
+        .into_iter()
+        .enumerate()
+        .map(|(index, seconds)| Zone {
+            zone: index as u8 + 1,
+            min: if index == 0 { 0 } else { bounds[index - 1] },
+            max: bounds.get(index).copied(),
+            seconds: seconds.round() as u64,
+        })
+        .collect()
+}
+
+/// Running totals at one timestamped point, so any window's average is a difference.
+#[derive(Clone, Copy, Default)]
+struct Totals {
+    time: f64,
+    distance_km: f64,
+    /// Watt-seconds, and the seconds they cover.
+    energy: f64,
+    power_seconds: f64,
+    /// Heartbeats, and the seconds they cover.
+    beats: f64,
+    heart_rate_seconds: f64,
+}
+
+/// Best average power, heart rate and speed over each of `PEAK_DURATIONS` that fits
+/// in the activity. A sensor's average needs readings for at least half the window.
+fn peaks(segments: &[Segment], elapsed_seconds: u64) -> Vec<Peak> {
+    let mut totals = vec![Totals::default()];
+    for segment in segments {
+        let Some(seconds) = segment.seconds.filter(|seconds| *seconds > 0.0) else {
+            continue;
+        };
+        let mut next = totals[totals.len() - 1];
+        next.time += seconds;
+        next.distance_km += segment.distance_km;
+        if let Some(power) = segment.to.power {
+            next.energy += power as f64 * seconds;
+            next.power_seconds += seconds;
+        }
+        if let Some(heart_rate) = segment.to.heart_rate {
+            next.beats += heart_rate as f64 * seconds;
+            next.heart_rate_seconds += seconds;
+        }
+        totals.push(next);
+    }
+
+    let max = |best: &mut Option<f64>, value: f64| *best = Some(best.map_or(value, |best| best.max(value)));
+    PEAK_DURATIONS
+        .into_iter()
+        .filter(|duration| *duration <= elapsed_seconds)
+        .map(|duration| {
+            let (mut power, mut heart_rate, mut speed) = (None, None, None);
+            let mut end = 0;
+            for (start, from) in totals.iter().enumerate() {
+                while end < totals.len() && totals[end].time - from.time < duration as f64 {
+                    end += 1;
+                }
+                let Some(to) = totals.get(end).filter(|_| end > start) else {
+                    break;
+                };
+                let window = to.time - from.time;
+                max(&mut speed, (to.distance_km - from.distance_km) / (window / 3600.0));
+                if to.power_seconds - from.power_seconds >= window / 2.0 {
+                    max(&mut power, (to.energy - from.energy) / (to.power_seconds - from.power_seconds));
+                }
+                if to.heart_rate_seconds - from.heart_rate_seconds >= window / 2.0 {
+                    max(
+                        &mut heart_rate,
+                        (to.beats - from.beats) / (to.heart_rate_seconds - from.heart_rate_seconds),
+                    );
+                }
+            }
+            Peak {
+                duration_seconds: duration,
+                power_w: power.map(|watts| watts.round() as u16),
+                heart_rate: heart_rate.map(|bpm| bpm.round() as u16),
+                speed_kmh: speed,
+            }
+        })
+        .collect()
+}
@@ -1,5 +1,6 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TrackPoint {
@@ -36,6 +37,7 @@ pub struct ParsedActivity {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct Metrics {
     pub distance_km: f64,
     pub elevation_gain_m: f64,
@@ -45,9 +47,14 @@ pub struct Metrics {
     pub max_heart_rate: Option<u16>,
     pub avg_power: Option<u16>,
     pub max_power: Option<u16>,
+    pub avg_cadence: Option<u16>,
+    pub max_cadence: Option<u16>,
+    /// Degrees Celsius.
+    pub avg_temperature: Option<f32>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct AvailableData {
     pub has_coordinates: bool,
     pub has_elevation: bool,
@@ -55,9 +62,25 @@ pub struct AvailableData {
     pub has_power: bool,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProcessedActivity {
     pub points: Vec<TrackPoint>,
     pub metrics: Metrics,
     pub available_data: AvailableData,
+    pub start_time: Option<DateTime<Utc>>,
+    /// [`ProcessedActivity::compute_digest`], filled in by `process` so renders can
+    /// identify the track without hashing it again. Empty in records saved before it
+    /// existed.
+    #[serde(default)]
+    pub digest: String,
+}
+
+impl ProcessedActivity {
+    /// SHA-256 of the track and its metrics, hex encoded. Equal for two uploads of the
+    /// same file.
+    pub fn compute_digest(&self) -> String {
+        let track = (&self.points, &self.metrics, &self.available_data, &self.start_time);
+        let json = serde_json::to_vec(&track).expect("activity data serializes to JSON");
+        format!("{:x}", Sha256::digest(&json))
+    }
 }
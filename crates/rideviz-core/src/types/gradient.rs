@@ -0,0 +1,123 @@
+use std::fmt;
+
+/// Entries in a gradient's lookup table; enough that neighbouring entries differ by
+/// at most a step or two per channel.
+const LUT_SIZE: usize = 256;
+
+/// An opaque color, written as `#RRGGBB`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+impl fmt::Display for Rgb {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{:02X}{:02X}{:02X}", self.0, self.1, self.2)
+    }
+}
+
+/// A named run of color stops. The stops are parsed and the colors in between computed
+/// once, into a lookup table, since renders ask for them on every frame.
+#[derive(Clone)]
+pub struct Gradient {
+    pub name: &'static str,
+    pub colors: Vec<&'static str>,
+    stops: Vec<Rgb>,
+    lut: Box<[Rgb; LUT_SIZE]>,
+}
+
+impl fmt::Debug for Gradient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Gradient")
+            .field("name", &self.name)
+            .field("colors", &self.colors)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for Gradient {
+    fn default() -> Self {
+        Self::new("fire", vec!["#FF3366", "#FF6600", "#FF9933"])
+    }
+}
+
+impl Gradient {
+    fn new(name: &'static str, colors: Vec<&'static str>) -> Self {
+        let stops: Vec<Rgb> = colors
+            .iter()
+            .map(|hex| parse_hex_color(hex).unwrap_or(Rgb(255, 255, 255)))
+            .collect();
+        let mut lut = Box::new([Rgb(255, 255, 255); LUT_SIZE]);
+        for (i, entry) in lut.iter_mut().enumerate() {
+            *entry = blend(&stops, i as f64 / (LUT_SIZE - 1) as f64);
+        }
+        Self {
+            name,
+            colors,
+            stops,
+            lut,
+        }
+    }
+
+    pub fn get(name: &str) -> Option<Self> {
+        match name {
+            "fire" => Some(Self::new("fire", vec!["#FF3366", "#FF6600", "#FF9933"])),
+            "ocean" => Some(Self::new("ocean", vec!["#0055FF", "#0099DD", "#00D1FF"])),
+            "sunset" => Some(Self::new("sunset", vec!["#FF2D55", "#FF7E5F", "#FEB47B"])),
+            "forest" => Some(Self::new("forest", vec!["#1D976C", "#4CD964", "#93F9B9"])),
+            "violet" => Some(Self::new("violet", vec!["#FF0080", "#8E2DE2", "#4A00E0"])),
+            "rideviz" => Some(Self::new("rideviz", vec!["#00C2FF", "#00EABD", "#00FF94"])),
+            "white" => Some(Self::new("white", vec!["#FFFFFF", "#FFFFFF", "#FFFFFF"])),
+            "black" => Some(Self::new("black", vec!["#000000", "#000000", "#000000"])),
+            _ => None,
+        }
+    }
+
+    /// `colors`, parsed.
+    pub fn stops(&self) -> &[Rgb] {
+        &self.stops
+    }
+
+    /// The color at `t` along the gradient (clamped to 0..=1), from the lookup table.
+    pub fn color(&self, t: f64) -> Rgb {
+        let idx = (t.clamp(0.0, 1.0) * (LUT_SIZE - 1) as f64).round() as usize;
+        self.lut[idx]
+    }
+
+    pub fn interpolate(&self, t: f64) -> String {
+        self.color(t).to_string()
+    }
+}
+
+fn blend(stops: &[Rgb], t: f64) -> Rgb {
+    match stops {
+        [] => Rgb(255, 255, 255),
+        [only] => *only,
+        _ => {
+            let segments = (stops.len() - 1) as f64;
+            let scaled = t * segments;
+            let idx = (scaled.floor() as usize).min(stops.len() - 2);
+            let local_t = scaled - idx as f64;
+            let (start, end) = (stops[idx], stops[idx + 1]);
+            Rgb(
+                lerp_u8(start.0, end.0, local_t),
+                lerp_u8(start.1, end.1, local_t),
+                lerp_u8(start.2, end.2, local_t),
+            )
+        }
+    }
+}
+
+fn parse_hex_color(hex: &str) -> Option<Rgb> {
+    let value = hex.trim_start_matches('#');
+    if value.len() != 6 {
+        return None;
+    }
+    let r = u8::from_str_radix(&value[0..2], 16).ok()?;
+    let g = u8::from_str_radix(&value[2..4], 16).ok()?;
+    let b = u8::from_str_radix(&value[4..6], 16).ok()?;
+    Some(Rgb(r, g, b))
+}
+
+fn lerp_u8(start: u8, end: u8, t: f64) -> u8 {
+    let value = start as f64 + (end as f64 - start as f64) * t;
+    value.round().clamp(0.0, 255.0) as u8
+}
@@ -1,3 +1,4 @@
 pub mod activity;
 pub mod gradient;
+pub mod stats;
 pub mod viz;
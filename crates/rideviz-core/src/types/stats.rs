@@ -0,0 +1,74 @@
+use serde::{Deserialize, Serialize};
+
+/// Extended statistics for an activity, from [`crate::pipeline::stats::summarize`].
+/// Computed from the processed (downsampled) track, so short peaks and pauses are
+/// smoothed out on long activities.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ActivityStats {
+    /// Seconds from the first to the last timestamp.
+    pub elapsed_seconds: u64,
+    /// Seconds spent moving; stops are left out.
+    pub moving_seconds: u64,
+    /// Average speed over the moving time.
+    pub avg_moving_speed_kmh: f64,
+    pub max_speed_kmh: Option<f64>,
+    pub min_elevation_m: Option<f64>,
+    pub max_elevation_m: Option<f64>,
+    pub elevation_loss_m: f64,
+    /// One per full kilometre, then the remainder.
+    pub splits: Vec<Split>,
+    /// Sustained climbs, in the order they were ridden.
+    pub climbs: Vec<Climb>,
+    /// Moving time per heart rate zone (5 zones by share of the max heart rate). Empty
+    /// without heart rate data.
+    pub heart_rate_zones: Vec<Zone>,
+    /// Moving time per power zone (7 zones by share of FTP). Empty unless an FTP is given.
+    pub power_zones: Vec<Zone>,
+    /// Best averages over fixed durations, for the durations the activity lasts.
+    pub peaks: Vec<Peak>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Split {
+    /// 1-based.
+    pub index: u32,
+    pub distance_km: f64,
+    pub duration_seconds: Option<u64>,
+    pub elevation_gain_m: f64,
+    pub avg_heart_rate: Option<u16>,
+    pub avg_power: Option<u16>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Climb {
+    /// Distance into the activity where the climb starts.
+    pub start_km: f64,
+    pub distance_km: f64,
+    pub elevation_gain_m: f64,
+    pub avg_grade_percent: f64,
+    pub top_elevation_m: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Zone {
+    /// 1-based.
+    pub zone: u8,
+    /// Lower bound in bpm or watts.
+    pub min: u16,
+    /// Upper bound (exclusive); `None` for the top zone.
+    pub max: Option<u16>,
+    pub seconds: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Peak {
+    pub duration_seconds: u64,
+    pub power_w: Option<u16>,
+    pub heart_rate: Option<u16>,
+    pub speed_kmh: Option<f64>,
+}
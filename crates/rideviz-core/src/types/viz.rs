@@ -0,0 +1,202 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorByMetric {
+    Elevation,
+    Speed,
+    #[serde(rename = "heartrate")]
+    HeartRate,
+    Power,
+}
+
+impl ColorByMetric {
+    /// Case-insensitive; also accepts `heart_rate`.
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "elevation" => Some(Self::Elevation),
+            "speed" => Some(Self::Speed),
+            "heartrate" | "heart_rate" => Some(Self::HeartRate),
+            "power" => Some(Self::Power),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum AnimationEasing {
+    #[default]
+    EaseInOutSine,
+    Linear,
+    EaseOutCubic,
+    /// Reveal follows the activity clock, so stops and climbs play back at their real pace.
+    RealTime,
+}
+
+/// What the animation's progress advances along.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum AnimationMode {
+    /// Equal route length per step, ignoring timestamps.
+    #[default]
+    Distance,
+    /// The activity clock, so stops hold the reveal in place. Needs timestamps.
+    Time,
+    /// Equal share per track point, so detailed (twisty) sections play slower.
+    Uniform,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum LoopMode {
+    #[default]
+    Forward,
+    Boomerang,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum CameraMode {
+    /// Whole route fitted to the canvas for every frame.
+    #[default]
+    Overview,
+    /// Zoomed-in view that pans along with the reveal tip.
+    Follow,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    #[default]
+    Apng,
+}
+
+#[derive(Debug, Clone)]
+pub struct VizData {
+    pub points: Vec<RoutePoint>,
+}
+
+#[derive(Debug, Clone)]
+pub struct StatOverlayItem {
+    pub label: String,
+    pub value: String,
+    pub color_t: f64,
+}
+
+/// Full-canvas card shown before or after the route animation.
+#[derive(Debug, Clone)]
+pub struct TitleCard {
+    pub heading: String,
+    pub subheading: Option<String>,
+    pub items: Vec<StatOverlayItem>,
+    pub duration_ms: u32,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TitleCards {
+    pub intro: Option<TitleCard>,
+    pub outro: Option<TitleCard>,
+}
+
+#[derive(Debug, Clone)]
+pub struct RoutePoint {
+    pub x: f64,
+    pub y: f64,
+    pub value: Option<f64>,
+    pub elevation: Option<f64>,
+    pub elapsed_seconds: Option<f64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct RenderOptions {
+    pub width: u32,
+    pub height: u32,
+    pub padding: u32,
+    pub stroke_width: f32,
+    pub gradient: crate::types::gradient::Gradient,
+    pub color_by: Option<ColorByMetric>,
+    pub smoothing: usize,
+    pub glow: bool,
+    pub animation_frames: u32,
+    pub animation_duration_ms: u32,
+    pub animation_easing: AnimationEasing,
+    pub animation_mode: AnimationMode,
+    pub loop_mode: LoopMode,
+    pub camera: CameraMode,
+    /// Turntable the scene around its center over the course of the animation.
+    pub rotate: bool,
+    /// Extra time the completed route stays on screen before the animation ends or reverses.
+    pub end_hold_ms: u32,
+    /// Catmull-Rom curve tension for route smoothing.
+    /// 0.0 = straight lines, 0.5 = very rounded. Good range: 0.2–0.4.
+    pub curve_tension: f32,
+    /// Catmull-Rom samples per segment for walls and route lines. Below 2 disables subdivision.
+    pub wall_subdivisions: usize,
+    /// Rows at the top and bottom, in pixels, kept clear of the route, stats and title
+    /// cards because platform UI (captions, buttons) is drawn over them. The padding
+    /// applies inside them.
+    pub safe_area_top: u32,
+    pub safe_area_bottom: u32,
+    /// Play the route from its finish to its start.
+    pub reverse: bool,
+    /// Start a loop this far into the ride (negative: before the finish), e.g. at a
+    /// landmark. Wraps around the loop.
+    pub start_offset_km: Option<f64>,
+    /// Keep every Nth point before rendering. Higher = fewer points = smoother but less detailed.
+    /// 1 = no simplification, 5 = keep every 5th point. Good range: 3–10.
+    pub simplify: usize,
+}
+
+impl RenderOptions {
+    /// 1920x1080, `fire` gradient, 100 frames over 4.6 s.
+    pub fn route_3d_defaults() -> Self {
+        Self {
+            width: 1920,
+            height: 1080,
+            padding: 40,
+            stroke_width: 3.0,
+            gradient: crate::types::gradient::Gradient::default(),
+            color_by: None,
+            smoothing: 30,
+            glow: true,
+            animation_frames: 100,
+            animation_duration_ms: 4600,
+            animation_easing: AnimationEasing::EaseInOutSine,
+            animation_mode: AnimationMode::Distance,
+            loop_mode: LoopMode::Forward,
+            camera: CameraMode::Overview,
+            rotate: false,
+            end_hold_ms: 0,
+            curve_tension: 0.3,
+            wall_subdivisions: 4,
+            simplify: 5,
+            safe_area_top: 0,
+            safe_area_bottom: 0,
+            reverse: false,
+            start_offset_km: None,
+        }
+    }
+
+    /// `animation_mode`, except that the `real_time` easing always plays by time.
+    pub fn reveal_mode(&self) -> AnimationMode {
+        match self.animation_easing {
+            AnimationEasing::RealTime => AnimationMode::Time,
+            _ => self.animation_mode,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct OutputConfig {
+    pub width: u32,
+    pub height: u32,
+    pub background: Option<(u8, u8, u8, u8)>,
+    pub watermark: bool,
+}
@@ -0,0 +1,190 @@
+//! Golden-image regression tests: renders a synthetic route with fixed options and
+//! compares the pixels to PNGs checked in under `tests/golden`, so refactors of the
+//! render, draw and rasterize stages can't silently change what users get.
+//!
+//! After an intended change to the output, regenerate the goldens in the toolchain image
+//! the Dockerfile builds with, from the repository root:
+//!
+//! ```text
+//! docker run --rm -v "$PWD":/src -w /src rust:1.85-slim \
+//!     env UPDATE_GOLDENS=1 cargo test -p rideviz-core --test golden
+//! ```
+//!
+//! and look at the new images before committing them. The checked-in images come from
+//! resvg/usvg 0.40.0 and tiny-skia 0.11.4 on x86_64; a render with other versions may
+//! drift past the tolerances below. Fixtures have no text, so the results don't depend on
+//! which fonts are installed.
+
+use std::f64::consts::PI;
+use std::fmt::Write;
+use std::path::PathBuf;
+
+use rideviz_core::pipeline::{draw, parse, prepare, process, rasterize, render};
+use rideviz_core::types::activity::{FileFormat, ProcessedActivity};
+use rideviz_core::types::gradient::Gradient;
+use rideviz_core::types::viz::{CameraMode, ColorByMetric, OutputConfig, RenderOptions};
+use tiny_skia::Pixmap;
+
+const SIZE: u32 = 320;
+
+/// A pixel counts as changed when a channel moves by more than this.
+const CHANNEL_TOLERANCE: u8 = 32;
+/// Share of changed pixels allowed, for anti-aliasing differences between platforms
+/// and resvg/tiny-skia patch releases.
+const MAX_CHANGED_RATIO: f64 = 0.005;
+/// Mean absolute difference per channel allowed over the whole image.
+const MAX_MEAN_DIFF: f64 = 1.0;
+
+#[test]
+fn static_default() {
+    let options = options();
+    check_static("static_default", &options);
+}
+
+#[test]
+fn static_color_by_elevation() {
+    let mut options = options();
+    options.color_by = Some(ColorByMetric::Elevation);
+    options.gradient = Gradient::get("ocean").unwrap();
+    check_static("static_color_by_elevation", &options);
+}
+
+#[test]
+fn static_straight_without_glow() {
+    let mut options = options();
+    options.glow = false;
+    options.curve_tension = 0.0;
+    options.wall_subdivisions = 1;
+    options.gradient = Gradient::get("violet").unwrap();
+    check_static("static_straight_without_glow", &options);
+}
+
+#[test]
+fn frame_halfway_rotated() {
+    let mut options = options();
+    options.rotate = true;
+    check_frame("frame_halfway_rotated", &options, 0.5, 30.0);
+}
+
+#[test]
+fn frame_follow_camera() {
+    let mut options = options();
+    options.camera = CameraMode::Follow;
+    options.color_by = Some(ColorByMetric::Elevation);
+    check_frame("frame_follow_camera", &options, 0.7, 0.0);
+}
+
+fn options() -> RenderOptions {
+    RenderOptions {
+        width: SIZE,
+        height: SIZE,
+        padding: 20,
+        ..RenderOptions::route_3d_defaults()
+    }
+}
+
+fn output() -> OutputConfig {
+    OutputConfig {
+        width: SIZE,
+        height: SIZE,
+        background: Some((0, 0, 0, 255)),
+        watermark: false,
+    }
+}
+
+/// A lopsided loop with two climbs, one point every 10 s.
+fn activity() -> ProcessedActivity {
+    let mut gpx = String::from(
+        r#"<?xml version="1.0" encoding="UTF-8"?><gpx version="1.1" creator="golden"><trk><trkseg>"#,
+    );
+    for i in 0..240 {
+        let t = i as f64 / 240.0 * 2.0 * PI;
+        let lat = 46.5 + 0.02 * t.sin() + 0.006 * (3.0 * t).sin();
+        let lon = 7.5 + 0.03 * t.cos();
+        let ele = 600.0 + 180.0 * (2.0 * t).sin().max(0.0) + 40.0 * (5.0 * t).cos();
+        let _ = write!(
+            gpx,
+            r#"<trkpt lat="{:.6}" lon="{:.6}"><ele>{:.1}</ele><time>2024-05-01T08:{:02}:{:02}Z</time></trkpt>"#,
+            lat,
+            lon,
+            ele,
+            i * 10 / 60,
+            i * 10 % 60
+        );
+    }
+    gpx.push_str("</trkseg></trk></gpx>");
+    let parsed = parse::parse(gpx.as_bytes(), FileFormat::Gpx).expect("fixture parses");
+    process::process(&parsed).expect("fixture processes")
+}
+
+/// The static path: SVG, then resvg.
+fn check_static(name: &str, options: &RenderOptions) {
+    let data = prepare::prepare(&activity(), options).expect("prepare");
+    let svg = render::render_svg_frame(&data, options, 1.0, 0.0, &[]).expect("render");
+    let png = rasterize::rasterize(&svg, &output()).expect("rasterize");
+    let pixmap = Pixmap::decode_png(&png).expect("decode render");
+    compare(name, &pixmap);
+}
+
+/// The animation path: one frame drawn directly with tiny-skia.
+fn check_frame(name: &str, options: &RenderOptions, progress: f64, rotation_deg: f64) {
+    let data = prepare::prepare(&activity(), options).expect("prepare");
+    let projection = render::project_route(&data, options).expect("project");
+    let output = output();
+    let overlay = Pixmap::new(SIZE, SIZE).unwrap();
+    let pixmap = rasterize::compose_frame(&output, &overlay, |pixmap| {
+        draw::draw_frame(&projection, options, progress, rotation_deg, pixmap)
+            .map_err(|err| rideviz_core::error::RasterError::RenderFailed(err.to_string()))
+    })
+    .expect("draw");
+    compare(name, &pixmap);
+}
+
+fn compare(name: &str, actual: &Pixmap) {
+    let golden_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(format!("{}.png", name));
+    if std::env::var_os("UPDATE_GOLDENS").is_some() {
+        actual.save_png(&golden_path).expect("write golden");
+        return;
+    }
+    let expected = Pixmap::load_png(&golden_path).unwrap_or_else(|err| {
+        panic!(
+            "missing golden {} ({}); run with UPDATE_GOLDENS=1 to create it",
+            golden_path.display(),
+            err
+        )
+    });
+    assert_eq!(
+        (actual.width(), actual.height()),
+        (expected.width(), expected.height()),
+        "{}: size changed",
+        name
+    );
+
+    let mut changed = 0;
+    let mut total_diff = 0u64;
+    for (a, e) in actual.data().chunks_exact(4).zip(expected.data().chunks_exact(4)) {
+        let diffs = a.iter().zip(e).map(|(a, e)| a.abs_diff(*e));
+        let max = diffs.clone().max().unwrap_or(0);
+        total_diff += diffs.map(u64::from).sum::<u64>();
+        if max > CHANNEL_TOLERANCE {
+            changed += 1;
+        }
+    }
+    let pixels = (actual.width() * actual.height()) as f64;
+    let changed_ratio = changed as f64 / pixels;
+    let mean_diff = total_diff as f64 / (pixels * 4.0);
+    if changed_ratio > MAX_CHANGED_RATIO || mean_diff > MAX_MEAN_DIFF {
+        let actual_path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(format!("{}.actual.png", name));
+        let _ = actual.save_png(&actual_path);
+        panic!(
+            "{} differs from its golden: {:.2}% of pixels changed, mean difference {:.2}; \
+             output saved to {}",
+            name,
+            changed_ratio * 100.0,
+            mean_diff,
+            actual_path.display()
+        );
+    }
+}
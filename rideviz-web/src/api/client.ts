@@ -19,7 +19,7 @@ export async function uploadFile(file: File): Promise<UploadResponse> {
   const formData = new FormData();
   formData.append('file', file);
 
-  const response = await fetch(buildUrl('/api/upload'), {
+  const response = await fetch(buildUrl('/api/v1/upload'), {
     method: 'POST',
     body: formData,
   });
@@ -35,7 +35,7 @@ export async function getVisualization(
   payload: VisualizeRequest,
   signal?: AbortSignal,
 ): Promise<Blob> {
-  const response = await fetch(buildUrl('/api/visualize'), {
+  const response = await fetch(buildUrl('/api/v1/visualize'), {
     method: 'POST',
     headers: {
       'Content-Type': 'application/json',
@@ -5,6 +5,22 @@ export interface UploadResponse {
   available_data: AvailableData;
 }
 
+export interface UploadUrlRequest {
+  url: string;
+  filename?: string;
+}
+
+export interface ChunkedUploadStart {
+  filename: string;
+  size: number;
+}
+
+export interface ChunkedUploadStatus {
+  upload_id: string;
+  offset: number;
+  size: number;
+}
+
 export interface AvailableData {
   has_coordinates: boolean;
   has_elevation: boolean;
@@ -34,6 +50,9 @@ export type ExportPreset =
 export type ColorByMetric = 'elevation' | 'speed' | 'heartrate' | 'power';
 export type BackgroundColor = 'transparent' | 'white' | 'black';
 export type OutputFormat = 'apng';
+export type AnimationEasing = 'ease_in_out_sine' | 'linear' | 'ease_out_cubic' | 'real_time';
+export type LoopMode = 'forward' | 'boomerang';
+export type CameraMode = 'overview' | 'follow';
 export type StatKey =
   | 'distance'
   | 'duration'
@@ -68,7 +87,16 @@ export interface VisualizeRequest {
   fps?: number;
   animation_frames?: number;
   animation_duration_ms?: number;
+  easing?: AnimationEasing;
+  loop_mode?: LoopMode;
+  camera?: CameraMode;
+  rotate?: boolean;
+  end_hold_seconds?: number;
+  intro_title?: string;
+  intro_seconds?: number;
+  outro_seconds?: number;
   watermark?: boolean;
   format?: OutputFormat;
+  export_id?: string;
   stats?: StatKey[];
 }
@@ -0,0 +1,132 @@
+use std::path::{Path, PathBuf};
+
+use axum::{
+    http::{header, HeaderValue, Response, StatusCode},
+    middleware::map_response,
+    Router,
+};
+use tower::ServiceBuilder;
+use tower_http::services::{ServeDir, ServeFile};
+
+use crate::state::AppState;
+
+/// Where the frontend build is served from without `embed-assets` or `WEB_DIR`.
+const DEFAULT_WEB_DIR: &str = "assets/web";
+
+/// Where the frontend build puts its bundles. Their file names carry a content hash, so
+/// a name never points at different bytes and browsers can keep them for good.
+const HASHED_ASSETS: &str = "/assets";
+
+/// Frontend build output (`rideviz-web/dist`, copied to `assets/web`). Optional so the
+/// server still builds without a frontend; requests then get 404.
+#[cfg(feature = "embed-assets")]
+#[derive(rust_embed::RustEmbed)]
+#[folder = "assets/web/"]
+#[allow_missing = true]
+struct WebAssets;
+
+/// Serves the frontend for every path the API doesn't handle. `web_dir` takes precedence
+/// so frontend changes can be tried without rebuilding; otherwise the embedded copy is
+/// used when built with `embed-assets`, else `assets/web` relative to the working
+/// directory.
+pub fn serve_web(router: Router<AppState>, web_dir: Option<&Path>) -> Router<AppState> {
+    #[cfg(feature = "embed-assets")]
+    if web_dir.is_none() {
+        tracing::info!("Serving embedded web assets");
+        let serve = axum::routing::get(embedded::serve);
+        return router
+            .route(&format!("{}/*path", HASHED_ASSETS), serve.clone().layer(map_response(immutable)))
+            .fallback(serve.layer(map_response(revalidate)));
+    }
+
+    let dir = web_dir.map_or_else(|| PathBuf::from(DEFAULT_WEB_DIR), Path::to_path_buf);
+    tracing::info!("Serving web assets from {}", dir.display());
+    let index = ServeFile::new(dir.join("index.html"));
+    // No index fallback for bundles: a missing one is a 404, not an HTML page the
+    // browser would try to run as a script.
+    let hashed = ServeDir::new(dir.join(HASHED_ASSETS.trim_start_matches('/')));
+    router
+        .nest_service(
+            HASHED_ASSETS,
+            ServiceBuilder::new().layer(map_response(immutable)).service(hashed),
+        )
+        .fallback_service(
+            ServiceBuilder::new()
+                .layer(map_response(revalidate))
+                .service(ServeDir::new(dir).not_found_service(index)),
+        )
+}
+
+/// For content-hashed bundles. Errors aren't cached, so a bundle requested while a
+/// deploy is rolling out can still be fetched once it exists.
+async fn immutable<B>(mut response: Response<B>) -> Response<B> {
+    if response.status().is_success() || response.status() == StatusCode::NOT_MODIFIED {
+        response.headers_mut().insert(
+            header::CACHE_CONTROL,
+            HeaderValue::from_static("public, max-age=31536000, immutable"),
+        );
+    }
+    response
+}
+
+/// For `index.html` and other unhashed files, which must be revalidated so a deploy
+/// shows up on the next load.
+async fn revalidate<B>(mut response: Response<B>) -> Response<B> {
+    response
+        .headers_mut()
+        .insert(header::CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+    response
+}
+
+#[cfg(feature = "embed-assets")]
+mod embedded {
+    use axum::{
+        http::{header, HeaderMap, StatusCode, Uri},
+        response::{IntoResponse, Response},
+    };
+
+    use super::{WebAssets, HASHED_ASSETS};
+
+    /// Like `ServeDir` with an `index.html` fallback: unknown paths get the app shell so
+    /// client-side routes work on reload. Missing bundles are a plain 404.
+    pub async fn serve(uri: Uri, headers: HeaderMap) -> Response {
+        let path = uri.path().trim_start_matches('/');
+        let path = if path.is_empty() || path.ends_with('/') {
+            format!("{}index.html", path)
+        } else {
+            path.to_string()
+        };
+        let hashed = uri.path().starts_with(&format!("{}/", HASHED_ASSETS));
+        let file = WebAssets::get(&path).or_else(|| {
+            if hashed {
+                None
+            } else {
+                WebAssets::get("index.html")
+            }
+        });
+        let Some(file) = file else {
+            return StatusCode::NOT_FOUND.into_response();
+        };
+
+        let etag = format!("\"{}\"", hex(&file.metadata.sha256_hash()[..16]));
+        let not_modified = headers
+            .get(header::IF_NONE_MATCH)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.split(',').any(|tag| tag.trim() == etag));
+        if not_modified {
+            return (StatusCode::NOT_MODIFIED, [(header::ETAG, etag)]).into_response();
+        }
+        (
+            [
+                (header::CONTENT_TYPE, file.metadata.mimetype().to_string()),
+                (header::ETAG, etag),
+            ],
+            file.data,
+        )
+            .into_response()
+    }
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+}
@@ -0,0 +1,194 @@
+use std::collections::VecDeque;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// How many records the admin endpoint can search; older ones are only in the file.
+const RECENT_LIMIT: usize = 10_000;
+
+/// One `/visualize` export, as written to the audit log.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ExportRecord {
+    pub at: DateTime<Utc>,
+    /// `X-Request-Id` sent by the client or proxy, else generated; echoed in the response.
+    pub request_id: String,
+    /// `key:<name>` for API-key callers, otherwise the client IP.
+    pub subject: String,
+    pub file_id: String,
+    pub animated: bool,
+    /// The request options after defaults were applied.
+    #[schema(value_type = Object)]
+    pub parameters: serde_json::Value,
+    pub duration_ms: u64,
+    pub bytes: usize,
+    pub outcome: ExportOutcome,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportOutcome {
+    Rendered,
+    /// Served from the render cache.
+    Cached,
+    Failed,
+    /// The client went away or cancelled the export before it finished.
+    Cancelled,
+}
+
+/// Append-only record of exports for abuse investigation and billing reconciliation.
+/// Every record goes to a JSON-lines file when a path is configured; the most recent
+/// ones are also kept in memory for the admin API.
+pub struct ExportLog {
+    recent: Mutex<VecDeque<ExportRecord>>,
+    file: Option<(PathBuf, Mutex<File>)>,
+}
+
+/// Filters for [`ExportLog::query`]; unset fields match everything.
+#[derive(Default)]
+pub struct ExportFilter {
+    pub subject: Option<String>,
+    pub file_id: Option<String>,
+    pub outcome: Option<ExportOutcome>,
+    pub since: Option<DateTime<Utc>>,
+    pub limit: usize,
+}
+
+impl ExportLog {
+    /// Opens (or creates) the log at `path`, loading its tail so the admin API keeps
+    /// working across restarts. In-memory only without a path.
+    pub fn open(path: Option<&Path>) -> std::io::Result<Self> {
+        let Some(path) = path else {
+            return Ok(Self {
+                recent: Mutex::new(VecDeque::new()),
+                file: None,
+            });
+        };
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut recent = VecDeque::new();
+        if let Ok(existing) = File::open(path) {
+            for line in BufReader::new(existing).lines() {
+                // A torn last line from a crash shouldn't stop the service from starting.
+                if let Ok(record) = serde_json::from_str(&line?) {
+                    push_bounded(&mut recent, record);
+                }
+            }
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        tracing::info!(
+            "Export audit log at {} ({} recent records)",
+            path.display(),
+            recent.len()
+        );
+        Ok(Self {
+            recent: Mutex::new(recent),
+            file: Some((path.to_path_buf(), Mutex::new(file))),
+        })
+    }
+
+    /// Starts timing an export. The record is written when the returned guard is finished,
+    /// or as cancelled if it's dropped first.
+    pub fn begin(
+        self: &Arc<Self>,
+        request_id: String,
+        subject: String,
+        file_id: String,
+        animated: bool,
+        parameters: serde_json::Value,
+    ) -> PendingExport {
+        PendingExport {
+            log: Arc::clone(self),
+            started: Instant::now(),
+            record: Some(ExportRecord {
+                at: Utc::now(),
+                request_id,
+                subject,
+                file_id,
+                animated,
+                parameters,
+                duration_ms: 0,
+                bytes: 0,
+                outcome: ExportOutcome::Cancelled,
+                error: None,
+            }),
+        }
+    }
+
+    /// Matching records, newest first.
+    pub fn query(&self, filter: &ExportFilter) -> Vec<ExportRecord> {
+        self.recent
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .iter()
+            .rev()
+            .filter(|record| filter.subject.as_ref().is_none_or(|subject| &record.subject == subject))
+            .filter(|record| filter.file_id.as_ref().is_none_or(|file_id| &record.file_id == file_id))
+            .filter(|record| filter.outcome.is_none_or(|outcome| record.outcome == outcome))
+            .filter(|record| filter.since.is_none_or(|since| record.at >= since))
+            .take(filter.limit)
+            .cloned()
+            .collect()
+    }
+
+    fn append(&self, record: ExportRecord) {
+        if let Some((path, file)) = &self.file {
+            let result = serde_json::to_vec(&record)
+                .map_err(std::io::Error::other)
+                .and_then(|mut line| {
+                    line.push(b'\n');
+                    // One write per record, so a crash can tear at most the last line.
+                    let mut file = file.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+                    file.write_all(&line)
+                });
+            if let Err(err) = result {
+                tracing::error!("Failed to append to export audit log {}: {}", path.display(), err);
+            }
+        }
+        let mut recent = self.recent.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        push_bounded(&mut recent, record);
+    }
+}
+
+fn push_bounded(recent: &mut VecDeque<ExportRecord>, record: ExportRecord) {
+    if recent.len() == RECENT_LIMIT {
+        recent.pop_front();
+    }
+    recent.push_back(record);
+}
+
+/// An export in progress; see [`ExportLog::begin`].
+pub struct PendingExport {
+    log: Arc<ExportLog>,
+    started: Instant,
+    record: Option<ExportRecord>,
+}
+
+impl PendingExport {
+    pub fn finish(mut self, outcome: ExportOutcome, bytes: usize, error: Option<String>) {
+        if let Some(mut record) = self.record.take() {
+            record.outcome = outcome;
+            record.bytes = bytes;
+            record.error = error;
+            record.duration_ms = self.started.elapsed().as_millis() as u64;
+            self.log.append(record);
+        }
+    }
+}
+
+impl Drop for PendingExport {
+    fn drop(&mut self) {
+        if let Some(mut record) = self.record.take() {
+            record.duration_ms = self.started.elapsed().as_millis() as u64;
+            self.log.append(record);
+        }
+    }
+}
@@ -0,0 +1,143 @@
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::Arc;
+
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Request, State},
+    http::{header, request::Parts},
+    middleware::Next,
+    response::Response,
+};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::config::ApiKeyConfig;
+use crate::error::AppError;
+use crate::state::AppState;
+
+/// Header clients can use instead of `Authorization: Bearer <key>`.
+const API_KEY_HEADER: &str = "x-api-key";
+
+/// Configured API keys, looked up by digest so the comparison doesn't depend on how much
+/// of a guessed key is right. Empty means API keys are not required.
+pub struct ApiKeys {
+    keys: HashMap<[u8; 32], Arc<ApiKey>>,
+}
+
+pub struct ApiKey {
+    /// Namespace for this key's uploads, also used in logs.
+    pub name: String,
+    pub rate_limit_per_minute: Option<u32>,
+    pub rate_limit_burst: Option<u32>,
+    pub export_quota_daily: Option<u32>,
+    pub export_quota_monthly: Option<u32>,
+    pub max_dimension: Option<u32>,
+    pub max_megapixels: Option<f64>,
+}
+
+impl ApiKeys {
+    pub fn new(configs: &[ApiKeyConfig]) -> Self {
+        let keys = configs
+            .iter()
+            .map(|config| {
+                let key = ApiKey {
+                    name: config.name.clone(),
+                    rate_limit_per_minute: config.rate_limit_per_minute,
+                    rate_limit_burst: config.rate_limit_burst,
+                    export_quota_daily: config.export_quota_daily,
+                    export_quota_monthly: config.export_quota_monthly,
+                    max_dimension: config.max_dimension,
+                    max_megapixels: config.max_megapixels,
+                };
+                (digest(&config.key), Arc::new(key))
+            })
+            .collect();
+        Self { keys }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.keys.is_empty()
+    }
+
+    fn lookup(&self, presented: &str) -> Option<Arc<ApiKey>> {
+        self.keys.get(&digest(presented)).cloned()
+    }
+}
+
+fn digest(key: &str) -> [u8; 32] {
+    Sha256::digest(key.trim().as_bytes()).into()
+}
+
+/// Who is making the request. Without API keys configured every request is anonymous
+/// and sees everything; with them, each key only sees ids it created.
+#[derive(Clone, Default)]
+pub struct Caller(Option<Arc<ApiKey>>);
+
+impl Caller {
+    pub fn api_key(&self) -> Option<&ApiKey> {
+        self.0.as_deref()
+    }
+
+    /// A fresh id for an upload, prefixed with the caller's namespace.
+    pub fn new_id(&self) -> String {
+        self.scoped(&Uuid::new_v4().to_string())
+    }
+
+    /// Qualifies a client-chosen id (e.g. an `export_id`) so keys can't collide or
+    /// reach each other's.
+    pub fn scoped(&self, id: &str) -> String {
+        match self.api_key() {
+            Some(key) => format!("{}.{}", key.name, id),
+            None => id.to_string(),
+        }
+    }
+
+    pub fn owns(&self, id: &str) -> bool {
+        match self.api_key() {
+            Some(key) => id
+                .strip_prefix(key.name.as_str())
+                .is_some_and(|rest| rest.starts_with('.')),
+            None => true,
+        }
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for Caller {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(parts.extensions.get::<Caller>().cloned().unwrap_or_default())
+    }
+}
+
+/// Rejects requests without a valid key when API keys are configured, and records the
+/// key for handlers and the rate limiter.
+pub async fn require_api_key(
+    State(state): State<AppState>,
+    mut request: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    let keys = state.api_keys();
+    if !keys.is_enabled() {
+        return Ok(next.run(request).await);
+    }
+    let headers = request.headers();
+    let presented = headers
+        .get(API_KEY_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .or_else(|| {
+            headers
+                .get(header::AUTHORIZATION)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.strip_prefix("Bearer "))
+        })
+        .ok_or(AppError::Unauthorized)?;
+    let key = keys.lookup(presented).ok_or_else(|| {
+        tracing::warn!("Rejected unknown API key on {}", request.uri().path());
+        AppError::Unauthorized
+    })?;
+    request.extensions_mut().insert(Caller(Some(key)));
+    Ok(next.run(request).await)
+}
@@ -0,0 +1,192 @@
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use axum::body::Bytes;
+use lru::LruCache;
+use rideviz_core::error::RenderError;
+use rideviz_core::pipeline::render::{self, RouteProjection};
+use rideviz_core::types::viz::{RenderOptions, VizData};
+
+/// Projections kept by [`ProjectionCache`]. Each is at most a few hundred KB.
+const PROJECTION_CACHE_ENTRIES: NonZeroUsize = match NonZeroUsize::new(128) {
+    Some(entries) => entries,
+    None => unreachable!(),
+};
+
+/// A finished render, keyed by `<file_id>:<etag>`.
+#[derive(Clone)]
+pub struct CachedRender {
+    pub content_type: &'static str,
+    pub bytes: Bytes,
+}
+
+/// LRU cache of final render output, capped by total image bytes. Entries older than
+/// `ttl` are treated as misses and dropped.
+pub struct RenderCache {
+    max_bytes: usize,
+    ttl: Duration,
+    inner: Mutex<Inner>,
+}
+
+struct Inner {
+    entries: LruCache<String, Entry>,
+    total_bytes: usize,
+}
+
+struct Entry {
+    render: CachedRender,
+    inserted_at: Instant,
+}
+
+impl RenderCache {
+    pub fn new(max_bytes: usize, ttl: Duration) -> Self {
+        Self {
+            max_bytes,
+            ttl,
+            inner: Mutex::new(Inner {
+                entries: LruCache::unbounded(),
+                total_bytes: 0,
+            }),
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<CachedRender> {
+        let mut inner = self.lock();
+        let expired = inner.entries.get(key)?.inserted_at.elapsed() >= self.ttl;
+        if expired {
+            inner.remove(key);
+            return None;
+        }
+        inner.entries.get(key).map(|entry| entry.render.clone())
+    }
+
+    pub fn insert(&self, key: String, render: CachedRender) {
+        let size = render.bytes.len();
+        if size > self.max_bytes {
+            return;
+        }
+        let mut inner = self.lock();
+        inner.remove(&key);
+        while inner.total_bytes + size > self.max_bytes {
+            let Some((_, evicted)) = inner.entries.pop_lru() else {
+                break;
+            };
+            inner.total_bytes -= evicted.render.bytes.len();
+        }
+        inner.total_bytes += size;
+        inner.entries.put(
+            key,
+            Entry {
+                render,
+                inserted_at: Instant::now(),
+            },
+        );
+    }
+
+    /// Drops every render of `file_id`, e.g. when the activity is deleted.
+    pub fn remove_file(&self, file_id: &str) {
+        let prefix = format!("{}:", file_id);
+        self.remove_where(|key, _| key.starts_with(&prefix));
+    }
+
+    pub fn evict_expired(&self) {
+        self.remove_where(|_, entry| entry.inserted_at.elapsed() >= self.ttl);
+    }
+
+    /// Drops everything and returns how many entries were held.
+    pub fn clear(&self) -> usize {
+        let mut inner = self.lock();
+        let count = inner.entries.len();
+        inner.entries.clear();
+        inner.total_bytes = 0;
+        count
+    }
+
+    pub fn max_bytes(&self) -> usize {
+        self.max_bytes
+    }
+
+    /// Number of entries and bytes held.
+    pub fn usage(&self) -> (usize, usize) {
+        let inner = self.lock();
+        (inner.entries.len(), inner.total_bytes)
+    }
+
+    fn remove_where(&self, predicate: impl Fn(&str, &Entry) -> bool) {
+        let mut inner = self.lock();
+        let doomed: Vec<String> = inner
+            .entries
+            .iter()
+            .filter(|(key, entry)| predicate(key, entry))
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in doomed {
+            inner.remove(&key);
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Inner> {
+        self.inner.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Inner {
+    fn remove(&mut self, key: &str) {
+        if let Some(entry) = self.entries.pop(key) {
+            self.total_bytes -= entry.render.bytes.len();
+        }
+    }
+}
+
+/// Route projections keyed by `<file_id>:<projection key>`, so renders of a file that only
+/// differ in colors, background or stats (previews especially) skip projecting it again.
+pub struct ProjectionCache {
+    entries: Mutex<LruCache<String, Arc<RouteProjection>>>,
+}
+
+impl ProjectionCache {
+    pub fn new() -> Self {
+        Self {
+            entries: Mutex::new(LruCache::new(PROJECTION_CACHE_ENTRIES)),
+        }
+    }
+
+    /// The cached projection of `file_id` for `options`, projecting `data` on a miss.
+    pub fn get_or_project(
+        &self,
+        file_id: &str,
+        data: &VizData,
+        options: &RenderOptions,
+    ) -> Result<Arc<RouteProjection>, RenderError> {
+        let key = format!("{}:{}", file_id, render::projection_key(options));
+        if let Some(projection) = self.lock().get(&key) {
+            return Ok(Arc::clone(projection));
+        }
+        let projection = Arc::new(render::project_route(data, options)?);
+        self.lock().put(key, Arc::clone(&projection));
+        Ok(projection)
+    }
+
+    /// Drops every projection of `file_id`, e.g. when the activity is deleted.
+    pub fn remove_file(&self, file_id: &str) {
+        let prefix = format!("{}:", file_id);
+        let mut entries = self.lock();
+        let doomed: Vec<String> = entries
+            .iter()
+            .filter(|(key, _)| key.starts_with(&prefix))
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in doomed {
+            entries.pop(&key);
+        }
+    }
+
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, LruCache<String, Arc<RouteProjection>>> {
+        self.entries.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
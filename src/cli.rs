@@ -0,0 +1,238 @@
+use std::path::PathBuf;
+
+use clap::{Args, Parser, Subcommand};
+use rideviz_core::types::viz::RenderOptions;
+use serde_json::{json, Map, Value};
+
+use crate::config::{LogFormat, Overrides};
+
+/// Backend for 3D animated route overlays from GPX/FIT activities.
+///
+/// Without a subcommand this starts the server. Settings come from the config file, then
+/// environment variables, then these flags, each overriding the previous.
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    /// TOML config file (see rideviz.example.toml).
+    #[arg(long, env = "RIDEVIZ_CONFIG", value_name = "PATH")]
+    pub config: Option<PathBuf>,
+    /// Port to listen on. Overrides PORT.
+    #[arg(long)]
+    pub port: Option<u16>,
+    /// Persist uploads under this directory. Overrides DATA_DIR.
+    #[arg(long, value_name = "PATH")]
+    pub data_dir: Option<PathBuf>,
+    /// Log output format. Overrides LOG_FORMAT.
+    #[arg(long, value_enum)]
+    pub log_format: Option<LogFormat>,
+    /// Validate the configuration, check fonts, directories and TLS files, print a report
+    /// and exit without starting the server. Exits with 2 on an invalid config and 1 on a
+    /// failed check.
+    #[arg(long, visible_alias = "check")]
+    pub check_config: bool,
+}
+
+impl Cli {
+    pub fn overrides(&self) -> Overrides {
+        Overrides {
+            port: self.port,
+            data_dir: self.data_dir.clone(),
+            log_format: self.log_format,
+        }
+    }
+}
+
+/// Offline rendering: runs the pipeline on a local file without starting the server.
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Render a static PNG.
+    Render(Box<RenderArgs>),
+    /// Render an animated PNG (APNG).
+    Animate(Box<AnimateArgs>),
+}
+
+/// Options shared by `render` and `animate`; same meaning and defaults as the
+/// `/api/v1/visualize` body.
+#[derive(Debug, Args)]
+pub struct RenderArgs {
+    /// GPX or FIT file to render.
+    pub input: PathBuf,
+    /// Where to write the image. Defaults to the input path with a `.png` extension.
+    #[arg(short, long, value_name = "PATH")]
+    pub output: Option<PathBuf>,
+    /// fire, ocean, sunset, forest, violet, rideviz, white or black.
+    #[arg(long)]
+    gradient: Option<String>,
+    /// Output width in pixels; needs --height.
+    #[arg(long, requires = "height")]
+    width: Option<u32>,
+    /// Output height in pixels; needs --width.
+    #[arg(long, requires = "width")]
+    height: Option<u32>,
+    /// elevation, speed, heartrate or power.
+    #[arg(long)]
+    color_by: Option<String>,
+    /// transparent, white or black.
+    #[arg(long)]
+    background: Option<String>,
+    /// Route line width in pixels (default 3).
+    #[arg(long)]
+    stroke_width: Option<f32>,
+    /// Space around the route in pixels (default 40).
+    #[arg(long)]
+    padding: Option<u32>,
+    /// Keep platform UI clear: instagram_story, instagram_reels, tiktok or youtube_shorts.
+    #[arg(long)]
+    safe_area: Option<String>,
+    /// Pixels at the top kept clear of the route and overlays (overrides --safe-area).
+    #[arg(long)]
+    safe_area_top: Option<u32>,
+    /// Pixels at the bottom kept clear of the route and overlays (overrides --safe-area).
+    #[arg(long)]
+    safe_area_bottom: Option<u32>,
+    /// Play the route from its finish to its start.
+    #[arg(long)]
+    reverse: bool,
+    /// For loops: start this many km into the ride (negative counts back from the finish).
+    #[arg(long, allow_hyphen_values = true)]
+    start_offset_km: Option<f64>,
+    /// Route smoothing, 0-100.
+    #[arg(long)]
+    smoothing: Option<usize>,
+    /// Drop the glow around the route.
+    #[arg(long)]
+    no_glow: bool,
+    /// Leave out the watermark.
+    #[arg(long)]
+    no_watermark: bool,
+    /// Comma-separated stat overlay, e.g. distance,elevation_gain.
+    #[arg(long, value_delimiter = ',')]
+    stats: Vec<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct AnimateArgs {
+    #[command(flatten)]
+    pub render: RenderArgs,
+    /// Number of frames (8-180; capped lower for large outputs).
+    #[arg(long)]
+    frames: Option<u32>,
+    /// Animation length in milliseconds (500-8000).
+    #[arg(long)]
+    duration_ms: Option<u32>,
+    /// Animation length in seconds (3-60); frame count follows from --fps.
+    #[arg(long, conflicts_with_all = ["frames", "duration_ms"])]
+    duration: Option<f32>,
+    /// Frames per second with --duration (15-60, default 30).
+    #[arg(long, requires = "duration")]
+    fps: Option<u32>,
+    /// ease_in_out_sine, linear, ease_out_cubic or real_time.
+    #[arg(long)]
+    easing: Option<String>,
+    /// distance, time or uniform: what the reveal advances along.
+    #[arg(long)]
+    animation_mode: Option<String>,
+    /// forward or boomerang.
+    #[arg(long)]
+    loop_mode: Option<String>,
+    /// overview or follow.
+    #[arg(long)]
+    camera: Option<String>,
+    /// Turntable the route over the animation.
+    #[arg(long)]
+    rotate: bool,
+    /// Seconds to hold the finished route.
+    #[arg(long, value_name = "SECONDS")]
+    end_hold: Option<f32>,
+    /// Heading of the intro card (default RIDE).
+    #[arg(long)]
+    intro_title: Option<String>,
+    /// Show an intro card with the title and date for this long.
+    #[arg(long, value_name = "SECONDS")]
+    intro_seconds: Option<f32>,
+    /// UTC offset for the intro card's date, e.g. +09:00 (default: from the longitude).
+    #[arg(long, allow_hyphen_values = true)]
+    timezone: Option<String>,
+    /// Show an outro card with the stats for this long.
+    #[arg(long, value_name = "SECONDS")]
+    outro_seconds: Option<f32>,
+}
+
+impl Command {
+    pub fn render_args(&self) -> &RenderArgs {
+        match self {
+            Command::Render(args) => args,
+            Command::Animate(args) => &args.render,
+        }
+    }
+
+    /// The equivalent `/visualize` request body, so options are validated and defaulted
+    /// exactly as they are over HTTP.
+    pub fn request(&self, file_id: &str) -> Value {
+        let mut body = self.render_args().request(file_id);
+        if let Command::Animate(args) = self {
+            args.extend_request(&mut body);
+        }
+        Value::Object(body)
+    }
+}
+
+impl RenderArgs {
+    fn request(&self, file_id: &str) -> Map<String, Value> {
+        let mut body = Map::new();
+        body.insert("file_id".to_string(), json!(file_id));
+        insert(&mut body, "gradient", &self.gradient);
+        insert(&mut body, "width", &self.width);
+        insert(&mut body, "height", &self.height);
+        insert(&mut body, "color_by", &self.color_by);
+        insert(&mut body, "background", &self.background);
+        insert(&mut body, "stroke_width", &self.stroke_width);
+        insert(&mut body, "padding", &self.padding);
+        insert(&mut body, "safe_area", &self.safe_area);
+        insert(&mut body, "safe_area_top", &self.safe_area_top);
+        insert(&mut body, "safe_area_bottom", &self.safe_area_bottom);
+        body.insert("reverse".to_string(), json!(self.reverse));
+        insert(&mut body, "start_offset_km", &self.start_offset_km);
+        insert(&mut body, "smoothing", &self.smoothing);
+        body.insert("glow".to_string(), json!(!self.no_glow));
+        body.insert("watermark".to_string(), json!(!self.no_watermark));
+        if !self.stats.is_empty() {
+            body.insert("stats".to_string(), json!(self.stats));
+        }
+        body
+    }
+}
+
+impl AnimateArgs {
+    fn extend_request(&self, body: &mut Map<String, Value>) {
+        insert(body, "animation_frames", &self.frames);
+        insert(body, "animation_duration_ms", &self.duration_ms);
+        insert(body, "duration_seconds", &self.duration);
+        insert(body, "fps", &self.fps);
+        insert(body, "easing", &self.easing);
+        insert(body, "animation_mode", &self.animation_mode);
+        insert(body, "loop_mode", &self.loop_mode);
+        insert(body, "camera", &self.camera);
+        body.insert("rotate".to_string(), json!(self.rotate));
+        insert(body, "end_hold_seconds", &self.end_hold);
+        insert(body, "intro_title", &self.intro_title);
+        insert(body, "intro_seconds", &self.intro_seconds);
+        insert(body, "timezone", &self.timezone);
+        insert(body, "outro_seconds", &self.outro_seconds);
+        // A request is animated once any timing option is present; use the default length.
+        if self.frames.is_none() && self.duration_ms.is_none() && self.duration.is_none() {
+            body.insert(
+                "animation_frames".to_string(),
+                json!(RenderOptions::route_3d_defaults().animation_frames),
+            );
+        }
+    }
+}
+
+fn insert<T: serde::Serialize>(body: &mut Map<String, Value>, key: &str, value: &Option<T>) {
+    if let Some(value) = value {
+        body.insert(key.to_string(), json!(value));
+    }
+}
@@ -1,33 +1,623 @@
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use std::time::Duration;
 
+use reqwest::Url;
+use serde::Deserialize;
+
+use crate::error::ConfigError;
+
 #[derive(Debug, Clone)]
 pub struct Config {
     pub port: u16,
     pub max_file_size: usize,
     pub cache_ttl: Duration,
+    /// Most activities kept in memory; the oldest are evicted on upload. 0 means no cap.
+    pub max_cached_activities: usize,
+    /// Most unfinished chunked uploads one client may have open at once. 0 means no cap.
+    pub max_pending_uploads: usize,
+    /// Memory budget for finished renders kept for repeat requests; 0 disables the cache.
+    pub render_cache_bytes: usize,
+    pub render_cache_ttl: Duration,
+    /// Storage budget for share links across all users; 0 disables sharing.
+    pub share_max_bytes: usize,
+    /// Longest a share link stays valid; also the default when the client doesn't ask.
+    pub share_max_ttl: Duration,
+    /// Threads dedicated to animation rendering; 0 means one per CPU.
+    pub render_threads: usize,
+    /// Static renders (and previews) allowed to run at once.
+    pub max_static_renders: usize,
+    /// How long a static render waits for a free slot before the request gets a 503.
+    pub static_render_queue_timeout: Duration,
+    /// Cap on Tokio's blocking pool, which runs parsing, static renders and file I/O.
+    pub blocking_threads: usize,
+    /// Allowed output sizes: each side within `min_dimension..=max_dimension` pixels and
+    /// at most `max_megapixels` in total. API keys can raise the maximums.
+    pub min_dimension: u32,
+    pub max_dimension: u32,
+    pub max_megapixels: f64,
+    /// Budget for parsing an upload (including fetching it by URL).
+    pub upload_timeout: Duration,
+    /// Budget for a static render or preview. Animated exports are cancellable instead.
+    pub render_timeout: Duration,
+    /// Render a tiny synthetic route before accepting requests, so the first real render
+    /// doesn't pay for loading fonts.
+    pub warm_up: bool,
+    /// Scratch space for uploads being received or parsed. Stale files are swept at
+    /// startup and by the eviction loop.
+    pub work_dir: PathBuf,
+    /// Persist uploads under this directory so they survive restarts. In-memory only when unset.
+    pub data_dir: Option<PathBuf>,
+    /// Store uploads in an S3-compatible bucket instead, so any replica can serve them.
+    pub s3: Option<S3Config>,
+    /// Keep uploads in Redis so replicas share them; entries expire after `cache_ttl`.
+    pub redis_url: Option<String>,
+    /// Serve the frontend from this directory instead of the embedded or default copy.
+    pub web_dir: Option<PathBuf>,
+    /// Where clients reach this server (e.g. behind a proxy), for absolute links in share
+    /// previews. Taken from the request's Host header when unset.
+    pub public_url: Option<String>,
+    /// OTLP/HTTP collector base URL; traces are exported when set.
+    pub otlp_endpoint: Option<String>,
+    /// Animated exports allowed per client (or API key) per UTC day / month; 0 is unlimited.
+    pub export_quota_daily: u32,
+    pub export_quota_monthly: u32,
+    /// JSON-lines file every export is appended to; defaults to `audit/exports.jsonl` under
+    /// `data_dir`. Without either, the audit log is kept in memory only.
+    pub audit_log: Option<PathBuf>,
+    /// Sustained uploads/renders allowed per client per minute; 0 disables limiting.
+    pub rate_limit_per_minute: u32,
+    pub rate_limit_burst: u32,
+    /// Take the client IP from `X-Forwarded-For`. Only enable behind a proxy that sets it.
+    /// Clients can prepend anything to that header, so the address is read
+    /// `trusted_proxy_hops` entries from the right: the one your outermost proxy appended.
+    pub trust_proxy: bool,
+    pub trusted_proxy_hops: usize,
+    /// Serve HTTPS directly when both are set (PEM files); plain HTTP otherwise.
+    pub tls: Option<TlsConfig>,
+    pub log_format: LogFormat,
+    /// Bearer token for `/api/admin`; the admin endpoints don't exist when unset.
+    pub admin_token: Option<String>,
+    /// RideWithGPS app key; enables importing trips and routes from users' accounts.
+    pub ridewithgps_api_key: Option<String>,
+    /// Bot token from @BotFather; enables the Telegram webhook.
+    pub telegram_bot_token: Option<String>,
+    /// Telegram echoes this in `X-Telegram-Bot-Api-Secret-Token` so forged updates are
+    /// rejected. Required with a bot token.
+    pub telegram_webhook_secret: Option<String>,
+    /// Cloud drives users can import GPX/FIT files from with their own OAuth token.
+    pub cloud_import: Vec<CloudProvider>,
+    /// When non-empty, every `/api` request needs one of these keys.
+    pub api_keys: Vec<ApiKeyConfig>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ApiKeyConfig {
+    /// Namespace for the key's uploads: letters, digits, `-` and `_`.
+    pub name: String,
+    pub key: String,
+    /// Override the per-client defaults for this key.
+    pub rate_limit_per_minute: Option<u32>,
+    pub rate_limit_burst: Option<u32>,
+    pub export_quota_daily: Option<u32>,
+    pub export_quota_monthly: Option<u32>,
+    pub max_dimension: Option<u32>,
+    pub max_megapixels: Option<f64>,
+}
+
+/// Layout of `API_KEYS_FILE`, so keys can be rotated without touching the main config.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ApiKeysFile {
+    api_keys: Vec<ApiKeyConfig>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Human-readable lines.
+    #[default]
+    Text,
+    /// One JSON object per line, for log aggregators.
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CloudProvider {
+    Dropbox,
+    GoogleDrive,
+}
+
+impl FromStr for CloudProvider {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "dropbox" => Ok(Self::Dropbox),
+            "google-drive" => Ok(Self::GoogleDrive),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Command-line values; they take precedence over both environment and file.
+#[derive(Debug, Default)]
+pub struct Overrides {
+    pub port: Option<u16>,
+    pub data_dir: Option<PathBuf>,
+    pub log_format: Option<LogFormat>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct S3Config {
+    pub bucket: String,
+    #[serde(default = "default_s3_prefix")]
+    pub prefix: String,
+    pub region: Option<String>,
+    /// Custom endpoint for S3-compatible services (MinIO, R2, ...).
+    pub endpoint: Option<String>,
+    pub access_key_id: Option<String>,
+    pub secret_access_key: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TlsConfig {
+    pub cert_path: PathBuf,
+    pub key_path: PathBuf,
+}
+
+/// Contents of `rideviz.toml`. Every field is optional; environment variables override
+/// whatever the file sets, and built-in defaults fill the rest.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FileConfig {
+    port: Option<u16>,
+    max_file_size_mb: Option<usize>,
+    cache_ttl_seconds: Option<u64>,
+    max_cached_activities: Option<usize>,
+    max_pending_uploads: Option<usize>,
+    render_cache_mb: Option<usize>,
+    render_cache_ttl_seconds: Option<u64>,
+    share_max_mb: Option<usize>,
+    share_max_ttl_hours: Option<u64>,
+    render_threads: Option<usize>,
+    max_static_renders: Option<usize>,
+    static_render_queue_seconds: Option<u64>,
+    blocking_threads: Option<usize>,
+    min_dimension: Option<u32>,
+    max_dimension: Option<u32>,
+    max_megapixels: Option<f64>,
+    upload_timeout_seconds: Option<u64>,
+    render_timeout_seconds: Option<u64>,
+    warm_up: Option<bool>,
+    work_dir: Option<PathBuf>,
+    data_dir: Option<PathBuf>,
+    s3: Option<S3Config>,
+    redis_url: Option<String>,
+    otlp_endpoint: Option<String>,
+    web_dir: Option<PathBuf>,
+    public_url: Option<String>,
+    rate_limit_per_minute: Option<u32>,
+    rate_limit_burst: Option<u32>,
+    export_quota_daily: Option<u32>,
+    export_quota_monthly: Option<u32>,
+    audit_log: Option<PathBuf>,
+    trust_proxy: Option<bool>,
+    trusted_proxy_hops: Option<usize>,
+    tls: Option<TlsConfig>,
+    log_format: Option<LogFormat>,
+    admin_token: Option<String>,
+    ridewithgps_api_key: Option<String>,
+    cloud_import: Option<Vec<CloudProvider>>,
+    telegram_bot_token: Option<String>,
+    telegram_webhook_secret: Option<String>,
+    #[serde(default)]
+    api_keys: Vec<ApiKeyConfig>,
+    api_keys_file: Option<PathBuf>,
+}
+
+fn default_s3_prefix() -> String {
+    "rideviz".to_string()
 }
 
 impl Config {
-    pub fn from_env() -> Self {
-        let port = std::env::var("PORT")
-            .ok()
-            .and_then(|s| s.parse().ok())
-            .unwrap_or(3000);
-
-        let max_file_size_mb = std::env::var("MAX_FILE_SIZE_MB")
-            .ok()
-            .and_then(|s| s.parse().ok())
-            .unwrap_or(25);
-
-        let cache_ttl_seconds = std::env::var("CACHE_TTL_SECONDS")
-            .ok()
-            .and_then(|s| s.parse().ok())
-            .unwrap_or(3600);
-
-        Self {
+    /// Reads `path` if given, applies environment then command-line overrides, then
+    /// validates the result. Every problem found is reported at once rather than failing on
+    /// the first.
+    pub fn load(path: Option<&Path>, overrides: Overrides) -> Result<Self, ConfigError> {
+        let file = match path {
+            Some(path) => read_toml::<FileConfig>(path)?,
+            None => FileConfig::default(),
+        };
+        let mut problems = Vec::new();
+
+        let port = env_or("PORT", file.port, 3000, &mut problems);
+        let log_format = env_or("LOG_FORMAT", file.log_format, LogFormat::Text, &mut problems);
+        let max_file_size_mb = env_or("MAX_FILE_SIZE_MB", file.max_file_size_mb, 25, &mut problems);
+        let cache_ttl_seconds =
+            env_or("CACHE_TTL_SECONDS", file.cache_ttl_seconds, 3600, &mut problems);
+        let max_cached_activities = env_or(
+            "MAX_CACHED_ACTIVITIES",
+            file.max_cached_activities,
+            1000,
+            &mut problems,
+        );
+        let max_pending_uploads = env_or("MAX_PENDING_UPLOADS", file.max_pending_uploads, 5, &mut problems);
+        let render_cache_mb = env_or("RENDER_CACHE_MB", file.render_cache_mb, 64, &mut problems);
+        let render_cache_ttl_seconds = env_or(
+            "RENDER_CACHE_TTL_SECONDS",
+            file.render_cache_ttl_seconds,
+            900,
+            &mut problems,
+        );
+        let share_max_mb = env_or("SHARE_MAX_MB", file.share_max_mb, 256, &mut problems);
+        let share_max_ttl_hours =
+            env_or("SHARE_MAX_TTL_HOURS", file.share_max_ttl_hours, 168, &mut problems);
+        let render_threads = env_or("RENDER_THREADS", file.render_threads, 0, &mut problems);
+        let max_static_renders = env_or(
+            "MAX_STATIC_RENDERS",
+            file.max_static_renders,
+            std::thread::available_parallelism().map_or(4, |n| n.get()),
+            &mut problems,
+        );
+        let static_render_queue_seconds = env_or(
+            "STATIC_RENDER_QUEUE_SECONDS",
+            file.static_render_queue_seconds,
+            10,
+            &mut problems,
+        );
+        let blocking_threads =
+            env_or("BLOCKING_THREADS", file.blocking_threads, 512, &mut problems);
+        let min_dimension = env_or("MIN_DIMENSION", file.min_dimension, 320, &mut problems);
+        let max_dimension = env_or("MAX_DIMENSION", file.max_dimension, 4096, &mut problems);
+        let max_megapixels = env_or("MAX_MEGAPIXELS", file.max_megapixels, 10.0, &mut problems);
+        let upload_timeout_seconds =
+            env_or("UPLOAD_TIMEOUT_SECONDS", file.upload_timeout_seconds, 60, &mut problems);
+        let render_timeout_seconds =
+            env_or("RENDER_TIMEOUT_SECONDS", file.render_timeout_seconds, 30, &mut problems);
+        let rate_limit_per_minute =
+            env_or("RATE_LIMIT_PER_MINUTE", file.rate_limit_per_minute, 0, &mut problems);
+        let rate_limit_burst = env_or("RATE_LIMIT_BURST", file.rate_limit_burst, 10, &mut problems);
+        let export_quota_daily =
+            env_or("EXPORT_QUOTA_DAILY", file.export_quota_daily, 0, &mut problems);
+        let export_quota_monthly =
+            env_or("EXPORT_QUOTA_MONTHLY", file.export_quota_monthly, 0, &mut problems);
+
+        let trust_proxy = match std::env::var("TRUST_PROXY") {
+            Ok(s) => matches!(s.trim(), "1" | "true" | "yes"),
+            Err(_) => file.trust_proxy.unwrap_or(false),
+        };
+        let trusted_proxy_hops =
+            env_or("TRUSTED_PROXY_HOPS", file.trusted_proxy_hops, 1, &mut problems);
+
+        let warm_up = match std::env::var("WARM_UP") {
+            Ok(s) => matches!(s.trim(), "1" | "true" | "yes"),
+            Err(_) => file.warm_up.unwrap_or(false),
+        };
+
+        let work_dir = non_empty_env("WORK_DIR")
+            .map(PathBuf::from)
+            .or(file.work_dir)
+            .unwrap_or_else(|| std::env::temp_dir().join("rideviz-uploads"));
+        let data_dir = non_empty_env("DATA_DIR").map(PathBuf::from).or(file.data_dir);
+
+        let s3 = match non_empty_env("S3_BUCKET") {
+            Some(bucket) => {
+                let file_s3 = file.s3.as_ref();
+                Some(S3Config {
+                    bucket,
+                    prefix: non_empty_env("S3_PREFIX")
+                        .or_else(|| file_s3.map(|s3| s3.prefix.clone()))
+                        .unwrap_or_else(default_s3_prefix),
+                    region: non_empty_env("S3_REGION").or_else(|| file_s3?.region.clone()),
+                    endpoint: non_empty_env("S3_ENDPOINT").or_else(|| file_s3?.endpoint.clone()),
+                    access_key_id: non_empty_env("S3_ACCESS_KEY_ID")
+                        .or_else(|| file_s3?.access_key_id.clone()),
+                    secret_access_key: non_empty_env("S3_SECRET_ACCESS_KEY")
+                        .or_else(|| file_s3?.secret_access_key.clone()),
+                })
+            }
+            None => file.s3,
+        };
+
+        let audit_log = non_empty_env("AUDIT_LOG_PATH").map(PathBuf::from).or(file.audit_log);
+        let redis_url = non_empty_env("REDIS_URL").or(file.redis_url);
+        let otlp_endpoint = non_empty_env("OTEL_EXPORTER_OTLP_ENDPOINT").or(file.otlp_endpoint);
+        let web_dir = non_empty_env("WEB_DIR").map(PathBuf::from).or(file.web_dir);
+        let public_url = non_empty_env("PUBLIC_URL")
+            .or(file.public_url)
+            .map(|url| url.trim_end_matches('/').to_string());
+        let admin_token = non_empty_env("ADMIN_TOKEN").or(file.admin_token);
+        let ridewithgps_api_key = non_empty_env("RIDEWITHGPS_API_KEY").or(file.ridewithgps_api_key);
+        let telegram_bot_token = non_empty_env("TELEGRAM_BOT_TOKEN").or(file.telegram_bot_token);
+        let telegram_webhook_secret =
+            non_empty_env("TELEGRAM_WEBHOOK_SECRET").or(file.telegram_webhook_secret);
+        let cloud_import = match non_empty_env("CLOUD_IMPORT") {
+            Some(raw) => raw
+                .split(',')
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .filter_map(|name| {
+                    let provider = name.parse().ok();
+                    if provider.is_none() {
+                        problems.push(format!(
+                            "CLOUD_IMPORT: unknown provider {:?} (use dropbox or google-drive)",
+                            name
+                        ));
+                    }
+                    provider
+                })
+                .collect(),
+            None => file.cloud_import.unwrap_or_default(),
+        };
+
+        let mut api_keys = file.api_keys;
+        let api_keys_file = non_empty_env("API_KEYS_FILE").map(PathBuf::from).or(file.api_keys_file);
+        if let Some(path) = api_keys_file {
+            api_keys.extend(read_toml::<ApiKeysFile>(&path)?.api_keys);
+        }
+
+        let tls = match (non_empty_env("TLS_CERT_PATH"), non_empty_env("TLS_KEY_PATH")) {
+            (Some(cert), Some(key)) => Some(TlsConfig {
+                cert_path: PathBuf::from(cert),
+                key_path: PathBuf::from(key),
+            }),
+            (None, None) => file.tls,
+            _ => {
+                problems.push("TLS_CERT_PATH and TLS_KEY_PATH must be set together".to_string());
+                None
+            }
+        };
+
+        let mut config = Self {
             port,
             max_file_size: max_file_size_mb * 1024 * 1024,
             cache_ttl: Duration::from_secs(cache_ttl_seconds),
+            max_cached_activities,
+            max_pending_uploads,
+            render_cache_bytes: render_cache_mb * 1024 * 1024,
+            render_cache_ttl: Duration::from_secs(render_cache_ttl_seconds),
+            share_max_bytes: share_max_mb * 1024 * 1024,
+            share_max_ttl: Duration::from_secs(share_max_ttl_hours.saturating_mul(3600)),
+            render_threads,
+            max_static_renders,
+            static_render_queue_timeout: Duration::from_secs(static_render_queue_seconds),
+            blocking_threads,
+            min_dimension,
+            max_dimension,
+            max_megapixels,
+            upload_timeout: Duration::from_secs(upload_timeout_seconds),
+            render_timeout: Duration::from_secs(render_timeout_seconds),
+            warm_up,
+            work_dir,
+            data_dir,
+            s3,
+            redis_url,
+            otlp_endpoint,
+            web_dir,
+            public_url,
+            rate_limit_per_minute,
+            rate_limit_burst,
+            export_quota_daily,
+            export_quota_monthly,
+            audit_log,
+            trust_proxy,
+            trusted_proxy_hops,
+            tls,
+            log_format,
+            admin_token,
+            ridewithgps_api_key,
+            telegram_bot_token,
+            telegram_webhook_secret,
+            cloud_import,
+            api_keys,
+        };
+        if let Some(port) = overrides.port {
+            config.port = port;
+        }
+        if let Some(dir) = overrides.data_dir {
+            config.data_dir = Some(dir);
+        }
+        if config.audit_log.is_none() {
+            config.audit_log = config
+                .data_dir
+                .as_ref()
+                .map(|dir| dir.join("audit").join("exports.jsonl"));
+        }
+        if let Some(format) = overrides.log_format {
+            config.log_format = format;
+        }
+        config.validate(&mut problems);
+
+        if problems.is_empty() {
+            Ok(config)
+        } else {
+            Err(ConfigError::Invalid(problems))
+        }
+    }
+
+    fn validate(&self, problems: &mut Vec<String>) {
+        if self.port == 0 {
+            problems.push("port must be between 1 and 65535".to_string());
+        }
+        if self.max_file_size == 0 {
+            problems.push("max_file_size_mb must be at least 1".to_string());
+        }
+        if self.cache_ttl.is_zero() {
+            problems.push("cache_ttl_seconds must be at least 1".to_string());
+        }
+        if self.upload_timeout.is_zero() || self.render_timeout.is_zero() {
+            problems.push("upload and render timeouts must be at least 1 second".to_string());
+        }
+        if self.share_max_bytes > 0 && self.share_max_ttl.is_zero() {
+            problems.push("share_max_ttl_hours must be at least 1 when sharing is on".to_string());
+        }
+        if self.trusted_proxy_hops == 0 {
+            problems.push("trusted_proxy_hops must be at least 1".to_string());
+        }
+        if self.max_static_renders == 0 {
+            problems.push("max_static_renders must be at least 1".to_string());
+        }
+        if self.blocking_threads == 0 {
+            problems.push("blocking_threads must be at least 1".to_string());
+        }
+        if self.min_dimension == 0 || self.min_dimension > self.max_dimension {
+            problems.push("min_dimension must be between 1 and max_dimension".to_string());
+        }
+        if !(self.max_megapixels.is_finite() && self.max_megapixels > 0.0) {
+            problems.push("max_megapixels must be a number greater than 0".to_string());
+        }
+        if self.rate_limit_per_minute > 0 && self.rate_limit_burst == 0 {
+            problems.push("rate_limit_burst must be at least 1 when rate limiting is on".to_string());
+        }
+        if let Some(token) = &self.admin_token {
+            if token.len() < 16 {
+                problems.push("admin_token must be at least 16 characters".to_string());
+            }
+        }
+        let mut names = std::collections::HashSet::new();
+        let mut keys = std::collections::HashSet::new();
+        for api_key in &self.api_keys {
+            let valid_name = !api_key.name.is_empty()
+                && api_key.name.len() <= 32
+                && api_key
+                    .name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+            if !valid_name {
+                problems.push(format!(
+                    "api key name {:?} must be 1-32 letters, digits, '-' or '_'",
+                    api_key.name
+                ));
+            }
+            if !names.insert(&api_key.name) {
+                problems.push(format!("api key name {:?} is used twice", api_key.name));
+            }
+            if api_key.key.trim().len() < 16 {
+                problems.push(format!("api key {:?} must be at least 16 characters", api_key.name));
+            }
+            if api_key.max_dimension.is_some_and(|max| max < self.min_dimension)
+                || api_key.max_megapixels.is_some_and(|max| !(max.is_finite() && max > 0.0))
+            {
+                problems.push(format!(
+                    "api key {:?} must allow at least min_dimension and more than 0 megapixels",
+                    api_key.name
+                ));
+            }
+            if !keys.insert(api_key.key.trim()) {
+                problems.push(format!("api key {:?} reuses another key's secret", api_key.name));
+            }
+        }
+        if self.work_dir.exists() && !self.work_dir.is_dir() {
+            problems.push(format!("work_dir {} is not a directory", self.work_dir.display()));
+        }
+        if let Some(dir) = &self.data_dir {
+            if dir.exists() && !dir.is_dir() {
+                problems.push(format!("data_dir {} is not a directory", dir.display()));
+            }
+        }
+        if let Some(dir) = &self.web_dir {
+            if !dir.is_dir() {
+                problems.push(format!("web_dir {} is not a directory", dir.display()));
+            }
+        }
+        if let Some(path) = &self.audit_log {
+            if path.is_dir() {
+                problems.push(format!("audit_log {} is a directory", path.display()));
+            }
+        }
+        if let Some(s3) = &self.s3 {
+            if s3.bucket.trim().is_empty() {
+                problems.push("s3.bucket must not be empty".to_string());
+            }
+            if let Some(endpoint) = &s3.endpoint {
+                check_url("s3.endpoint", endpoint, &["http", "https"], problems);
+            }
+        }
+        if self.telegram_bot_token.is_some() {
+            // Telegram's own limits on secret_token.
+            let valid = self.telegram_webhook_secret.as_deref().is_some_and(|secret| {
+                (1..=256).contains(&secret.len())
+                    && secret.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+            });
+            if !valid {
+                problems.push(
+                    "telegram_webhook_secret must be set with telegram_bot_token (1-256 of A-Z, a-z, 0-9, _ and -)"
+                        .to_string(),
+                );
+            }
+        }
+        if let Some(url) = &self.public_url {
+            check_url("public_url", url, &["http", "https"], problems);
+        }
+        if let Some(url) = &self.redis_url {
+            check_url("redis_url", url, &["redis", "rediss"], problems);
+        }
+        if let Some(endpoint) = &self.otlp_endpoint {
+            check_url("otlp_endpoint", endpoint, &["http", "https"], problems);
+        }
+        if let Some(tls) = &self.tls {
+            for (name, path) in [("tls.cert_path", &tls.cert_path), ("tls.key_path", &tls.key_path)] {
+                if !path.is_file() {
+                    problems.push(format!("{} {} does not exist", name, path.display()));
+                }
+            }
         }
     }
 }
+
+fn read_toml<T: serde::de::DeserializeOwned>(path: &Path) -> Result<T, ConfigError> {
+    let contents = std::fs::read_to_string(path).map_err(|source| ConfigError::Read {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    toml::from_str(&contents).map_err(|source| ConfigError::Parse {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+/// The environment variable if set, else the file value, else `default`. A variable that
+/// is set but doesn't parse is reported instead of silently falling back.
+fn env_or<T: FromStr>(key: &str, file: Option<T>, default: T, problems: &mut Vec<String>) -> T {
+    match non_empty_env(key) {
+        Some(raw) => raw.trim().parse().unwrap_or_else(|_| {
+            problems.push(format!("{}={:?} is not a valid value", key, raw));
+            default
+        }),
+        None => file.unwrap_or(default),
+    }
+}
+
+fn check_url(name: &str, value: &str, schemes: &[&str], problems: &mut Vec<String>) {
+    match Url::parse(value) {
+        Ok(url) if schemes.contains(&url.scheme()) => {}
+        Ok(url) => problems.push(format!(
+            "{} must use {}, got {}://",
+            name,
+            schemes.join(" or "),
+            url.scheme()
+        )),
+        Err(err) => problems.push(format!("{} {:?} is not a valid URL: {}", name, value, err)),
+    }
+}
+
+fn non_empty_env(key: &str) -> Option<String> {
+    std::env::var(key).ok().filter(|s| !s.trim().is_empty())
+}
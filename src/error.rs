@@ -1,42 +1,26 @@
-use axum::http::StatusCode;
+use axum::http::{header, StatusCode};
 use axum::response::{IntoResponse, Response};
 use axum::Json;
-use serde_json::json;
+use rideviz_core::error::{ParseError, PrepareError, ProcessError, RasterError, RenderError};
+use serde::Serialize;
+use utoipa::ToSchema;
 
-#[derive(Debug, thiserror::Error)]
-pub enum ParseError {
-    #[error("Invalid GPX: {0}")]
-    InvalidGpx(String),
-    #[error("Invalid FIT: {0}")]
-    InvalidFit(String),
-    #[error("No track points found in file")]
-    EmptyFile,
-}
-
-#[derive(Debug, thiserror::Error)]
-pub enum ProcessError {
-    #[error("Insufficient data points (need at least 2, got {0})")]
-    InsufficientPoints(usize),
-}
-
-#[derive(Debug, thiserror::Error)]
-pub enum PrepareError {
-    #[error("No {0} data available in this activity")]
-    MissingData(&'static str),
-}
-
-#[derive(Debug, thiserror::Error)]
-pub enum RenderError {
-    #[error("SVG generation failed: {0}")]
-    SvgError(String),
-}
+use crate::request_id;
 
 #[derive(Debug, thiserror::Error)]
-pub enum RasterError {
-    #[error("PNG rendering failed: {0}")]
-    RenderFailed(String),
-    #[error("Animation rendering failed: {0}")]
-    AnimationFailed(String),
+pub enum ConfigError {
+    #[error("Failed to read config file {}: {source}", path.display())]
+    Read {
+        path: std::path::PathBuf,
+        source: std::io::Error,
+    },
+    #[error("Invalid config file {}: {source}", path.display())]
+    Parse {
+        path: std::path::PathBuf,
+        source: toml::de::Error,
+    },
+    #[error("Invalid configuration:\n{}", .0.iter().map(|p| format!("  - {}", p)).collect::<Vec<_>>().join("\n"))]
+    Invalid(Vec<String>),
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -53,26 +37,128 @@ pub enum AppError {
     Raster(#[from] RasterError),
     #[error("Activity not found: {0}")]
     NotFound(String),
+    #[error("Export not found: {0}")]
+    ExportNotFound(String),
+    #[error("Upload not found: {0}")]
+    UploadNotFound(String),
+    #[error("Share not found: {0}")]
+    ShareNotFound(String),
+    #[error("No such endpoint: {0}")]
+    UnknownEndpoint(String),
+    #[error("Upload offset mismatch: expected {expected}, got {got}")]
+    UploadOffsetMismatch { expected: u64, got: u64 },
     #[error("Invalid request: {0}")]
     BadRequest(String),
+    #[error("Failed to fetch URL: {0}")]
+    Fetch(String),
+    #[error("{0} chunked uploads already in progress; finish or abort one first")]
+    TooManyPendingUploads(usize),
+    #[error("Share storage is full, try again later")]
+    ShareStorageFull,
+    #[error("{0}")]
+    Storage(String),
+    #[error("Request timed out after {0}s")]
+    Timeout(u64),
+    #[error("Missing or invalid credentials")]
+    Unauthorized,
+    #[error("Too many requests, retry in {retry_after_secs}s")]
+    RateLimited { retry_after_secs: u64 },
+    #[error("Server busy rendering, retry in {retry_after_secs}s")]
+    Busy { retry_after_secs: u64 },
+    #[error("{period} export quota of {limit} used up, resets in {retry_after_secs}s")]
+    QuotaExceeded {
+        period: &'static str,
+        limit: u32,
+        retry_after_secs: u64,
+    },
 }
 
-impl IntoResponse for AppError {
-    fn into_response(self) -> Response {
-        let (status, message) = match &self {
+/// Body of every error response.
+#[derive(Serialize, ToSchema)]
+pub struct ErrorResponse {
+    /// Human-readable message.
+    pub error: String,
+    /// Machine-readable reason, stable across releases.
+    #[schema(example = "activity_not_found")]
+    pub code: &'static str,
+    /// Id of the request, also sent as `X-Request-Id`; quote it when reporting a problem.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+}
+
+impl AppError {
+    fn status(&self) -> StatusCode {
+        match self {
             AppError::Parse(_) | AppError::Process(_) | AppError::Prepare(_) | AppError::BadRequest(_) => {
-                (StatusCode::BAD_REQUEST, self.to_string())
+                StatusCode::BAD_REQUEST
             }
-            AppError::NotFound(_) => (StatusCode::NOT_FOUND, self.to_string()),
-            AppError::Render(_) | AppError::Raster(_) => {
-                (StatusCode::INTERNAL_SERVER_ERROR, self.to_string())
+            AppError::NotFound(_)
+            | AppError::ExportNotFound(_)
+            | AppError::UploadNotFound(_)
+            | AppError::ShareNotFound(_)
+            | AppError::UnknownEndpoint(_) => StatusCode::NOT_FOUND,
+            AppError::Unauthorized => StatusCode::UNAUTHORIZED,
+            AppError::UploadOffsetMismatch { .. } => StatusCode::CONFLICT,
+            AppError::Fetch(_) => StatusCode::BAD_GATEWAY,
+            AppError::Timeout(_) => StatusCode::GATEWAY_TIMEOUT,
+            AppError::ShareStorageFull | AppError::Busy { .. } => StatusCode::SERVICE_UNAVAILABLE,
+            AppError::RateLimited { .. }
+            | AppError::QuotaExceeded { .. }
+            | AppError::TooManyPendingUploads(_) => StatusCode::TOO_MANY_REQUESTS,
+            AppError::Raster(RasterError::Cancelled) => StatusCode::CONFLICT,
+            AppError::Render(_) | AppError::Raster(_) | AppError::Storage(_) => {
+                StatusCode::INTERNAL_SERVER_ERROR
             }
-        };
+        }
+    }
 
-        let body = Json(json!({
-            "error": message
-        }));
+    /// The `code` of the error body. Clients branch on these, so they must not change.
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::Parse(_) | AppError::Process(_) => "invalid_file",
+            AppError::Prepare(_) => "invalid_options",
+            AppError::BadRequest(_) => "bad_request",
+            AppError::NotFound(_) => "activity_not_found",
+            AppError::ExportNotFound(_) => "export_not_found",
+            AppError::UploadNotFound(_) => "upload_not_found",
+            AppError::ShareNotFound(_) => "share_not_found",
+            AppError::UnknownEndpoint(_) => "unknown_endpoint",
+            AppError::Unauthorized => "unauthorized",
+            AppError::UploadOffsetMismatch { .. } => "upload_offset_mismatch",
+            AppError::Fetch(_) => "fetch_failed",
+            AppError::Timeout(_) => "timeout",
+            AppError::TooManyPendingUploads(_) => "too_many_pending_uploads",
+            AppError::ShareStorageFull => "share_storage_full",
+            AppError::Busy { .. } => "busy",
+            AppError::RateLimited { .. } => "rate_limited",
+            AppError::QuotaExceeded { .. } => "export_quota_exceeded",
+            AppError::Raster(RasterError::Cancelled) => "cancelled",
+            AppError::Render(_) | AppError::Raster(_) => "render_failed",
+            AppError::Storage(_) => "storage_failed",
+        }
+    }
 
-        (status, body).into_response()
+    fn retry_after_secs(&self) -> Option<u64> {
+        match self {
+            AppError::RateLimited { retry_after_secs }
+            | AppError::Busy { retry_after_secs }
+            | AppError::QuotaExceeded { retry_after_secs, .. } => Some(*retry_after_secs),
+            _ => None,
+        }
+    }
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        let body = Json(ErrorResponse {
+            error: self.to_string(),
+            code: self.code(),
+            request_id: request_id::current(),
+        });
+        let mut response = (self.status(), body).into_response();
+        if let Some(secs) = self.retry_after_secs() {
+            response.headers_mut().insert(header::RETRY_AFTER, secs.into());
+        }
+        response
     }
 }
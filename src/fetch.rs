@@ -0,0 +1,196 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::Duration;
+
+use reqwest::{redirect, StatusCode, Url};
+
+use crate::error::AppError;
+
+const FETCH_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// Downloads a user-supplied HTTPS URL with SSRF protections: the host must resolve only
+/// to public addresses, the connection is pinned to the address that was checked (so a
+/// second DNS answer can't point somewhere else), and redirects are not followed.
+pub async fn fetch_file(url: &Url, max_bytes: usize) -> Result<Vec<u8>, AppError> {
+    if url.scheme() != "https" {
+        return Err(AppError::BadRequest("Only https:// URLs are supported".to_string()));
+    }
+    let host = url
+        .host_str()
+        .ok_or_else(|| AppError::BadRequest("URL has no host".to_string()))?;
+    let addr = resolve_public(host, url.port_or_known_default().unwrap_or(443)).await?;
+
+    let client = reqwest::Client::builder()
+        .redirect(redirect::Policy::none())
+        .timeout(FETCH_TIMEOUT)
+        .resolve(host, addr)
+        .build()
+        .map_err(|e| AppError::Fetch(e.to_string()))?;
+
+    let mut response = client
+        .get(url.clone())
+        .send()
+        .await
+        .map_err(|e| AppError::Fetch(e.without_url().to_string()))?;
+
+    let status = response.status();
+    if status.is_redirection() {
+        return Err(AppError::Fetch(format!("{} (redirects are not followed)", status)));
+    }
+    if status != StatusCode::OK {
+        return Err(AppError::Fetch(format!("remote server responded with {}", status)));
+    }
+
+    let too_large = || {
+        AppError::BadRequest(format!(
+            "File exceeds the {} MB upload limit",
+            max_bytes / (1024 * 1024)
+        ))
+    };
+    if response.content_length().is_some_and(|len| len > max_bytes as u64) {
+        return Err(too_large());
+    }
+
+    let mut bytes = Vec::new();
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|e| AppError::Fetch(e.without_url().to_string()))?
+    {
+        if bytes.len() + chunk.len() > max_bytes {
+            return Err(too_large());
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    Ok(bytes)
+}
+
+async fn resolve_public(host: &str, port: u16) -> Result<SocketAddr, AppError> {
+    let blocked = || AppError::BadRequest(format!("URL host {} is not publicly routable", host));
+
+    // IPv6 literals come back from `host_str` wrapped in brackets.
+    let literal = host.trim_start_matches('[').trim_end_matches(']');
+    if let Ok(ip) = literal.parse::<IpAddr>() {
+        return if is_public(ip) {
+            Ok(SocketAddr::new(ip, port))
+        } else {
+            Err(blocked())
+        };
+    }
+
+    let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, port))
+        .await
+        .map_err(|e| AppError::Fetch(format!("could not resolve {}: {}", host, e)))?
+        .collect();
+    // Reject the host outright if any answer is internal rather than picking a public one.
+    if addrs.is_empty() || addrs.iter().any(|addr| !is_public(addr.ip())) {
+        return Err(blocked());
+    }
+    Ok(addrs[0])
+}
+
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_v4(ip),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(mapped) => is_public_v4(mapped),
+            None => is_public_v6(ip),
+        },
+    }
+}
+
+fn is_public_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, c, _] = ip.octets();
+    !(ip.is_private()
+        || ip.is_loopback()
+        || ip.is_link_local()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        || ip.is_unspecified()
+        || ip.is_multicast()
+        || a == 0
+        || (a == 100 && (64..128).contains(&b)) // carrier-grade NAT
+        || (a == 192 && b == 0 && c == 0) // IETF protocol assignments
+        || (a == 198 && (18..20).contains(&b)) // benchmarking
+        || a >= 240) // reserved
+}
+
+fn is_public_v6(ip: Ipv6Addr) -> bool {
+    let segments = ip.segments();
+    // 6to4 carries the relay's IPv4 address in the next 32 bits; judge it by that address.
+    if segments[0] == 0x2002 {
+        let [a, b] = segments[1].to_be_bytes();
+        let [c, d] = segments[2].to_be_bytes();
+        return is_public_v4(Ipv4Addr::new(a, b, c, d));
+    }
+    !(ip.is_loopback()
+        || ip.is_unspecified()
+        || ip.is_multicast()
+        || segments[..6].iter().all(|&s| s == 0) // IPv4-compatible, deprecated
+        || (segments[0] & 0xfe00) == 0xfc00 // unique local
+        || (segments[0] & 0xffc0) == 0xfe80 // link local
+        || (segments[0] & 0xffc0) == 0xfec0 // site local, deprecated
+        || (segments[0] == 0x2001 && segments[1] == 0x0000) // Teredo, may embed internal v4
+        || (segments[0] == 0x2001 && segments[1] == 0x0db8) // documentation
+        || (segments[0] == 0x0064 && segments[1] == 0xff9b)) // NAT64, may embed internal v4
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn public(addr: &str) -> bool {
+        is_public(addr.parse().unwrap())
+    }
+
+    #[test]
+    fn classifies_ipv4() {
+        assert!(public("1.1.1.1"));
+        assert!(public("93.184.216.34"));
+        for addr in [
+            "127.0.0.1",
+            "10.0.0.1",
+            "172.16.5.4",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "255.255.255.255",
+            "240.0.0.1",
+        ] {
+            assert!(!public(addr), "{} should be blocked", addr);
+        }
+    }
+
+    #[test]
+    fn classifies_ipv6() {
+        assert!(public("2606:4700:4700::1111"));
+        for addr in [
+            "::1",
+            "::",
+            "fc00::1",
+            "fe80::1",
+            "fec0::1",
+            "ff02::1",
+            "2001:db8::1",
+            "64:ff9b::a00:1",
+        ] {
+            assert!(!public(addr), "{} should be blocked", addr);
+        }
+    }
+
+    #[test]
+    fn judges_embedded_ipv4_by_the_embedded_address() {
+        // IPv4-mapped
+        assert!(public("::ffff:1.1.1.1"));
+        assert!(!public("::ffff:127.0.0.1"));
+        assert!(!public("::ffff:169.254.169.254"));
+        // 6to4
+        assert!(public("2002:0101:0101::1"));
+        assert!(!public("2002:7f00:0001::1"));
+        assert!(!public("2002:c0a8:0101::1"));
+        // IPv4-compatible and Teredo are rejected outright
+        assert!(!public("::127.0.0.1"));
+        assert!(!public("::1.1.1.1"));
+        assert!(!public("2001:0:4136:e378:8000:63bf:3fff:fdd2"));
+    }
+}
@@ -0,0 +1,132 @@
+use axum::async_trait;
+use chrono::{DateTime, Utc};
+use reqwest::header::AUTHORIZATION;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::error::AppError;
+use crate::import::{is_activity_file, ActivityProvider, Fetched, ListedActivity, Listing, PAGE_SIZE};
+
+const SERVICE: &str = "Dropbox";
+const API_URL: &str = "https://api.dropboxapi.com/2";
+const CONTENT_URL: &str = "https://content.dropboxapi.com/2";
+
+#[derive(Deserialize)]
+struct Folder {
+    entries: Vec<Entry>,
+    cursor: String,
+    has_more: bool,
+}
+
+#[derive(Deserialize)]
+struct Entry {
+    #[serde(rename = ".tag")]
+    tag: String,
+    name: String,
+    /// Stable across renames and moves, unlike the path.
+    id: Option<String>,
+    client_modified: Option<DateTime<Utc>>,
+}
+
+/// Metadata Dropbox returns alongside a download.
+#[derive(Deserialize)]
+struct Downloaded {
+    name: String,
+}
+
+/// GPX and FIT files anywhere in the user's Dropbox.
+pub struct Dropbox {
+    client: reqwest::Client,
+}
+
+impl Dropbox {
+    pub fn new() -> Self {
+        Self {
+            client: super::client(SERVICE),
+        }
+    }
+
+    async fn list_folder(&self, token: &str, cursor: Option<&str>) -> Result<Folder, AppError> {
+        let (url, body) = match cursor {
+            Some(cursor) => (
+                format!("{}/files/list_folder/continue", API_URL),
+                json!({ "cursor": cursor }),
+            ),
+            None => (
+                format!("{}/files/list_folder", API_URL),
+                json!({ "path": "", "recursive": true, "limit": PAGE_SIZE }),
+            ),
+        };
+        let request = self
+            .client
+            .post(url)
+            .header(AUTHORIZATION, format!("Bearer {}", token))
+            .header("content-type", "application/json")
+            .body(body.to_string());
+        super::send_listing(SERVICE, request).await?.json(SERVICE)
+    }
+}
+
+#[async_trait]
+impl ActivityProvider for Dropbox {
+    fn id(&self) -> &'static str {
+        "dropbox"
+    }
+
+    fn kinds(&self) -> &'static [&'static str] {
+        &["files"]
+    }
+
+    /// Dropbox pages over every entry, so folder-heavy accounts are walked until a page's
+    /// worth of activity files turns up. Order is Dropbox's, not by date.
+    async fn list(&self, token: &str, _kind: &str, page: Option<&str>) -> Result<Listing, AppError> {
+        let mut items = Vec::new();
+        let mut cursor = page.map(str::to_string);
+        loop {
+            let folder = self.list_folder(token, cursor.as_deref()).await?;
+            items.extend(
+                folder
+                    .entries
+                    .into_iter()
+                    .filter(|entry| entry.tag == "file" && is_activity_file(&entry.name))
+                    .filter_map(|entry| {
+                        Some(ListedActivity {
+                            id: entry.id?,
+                            name: entry.name,
+                            distance_km: None,
+                            elevation_gain_m: None,
+                            date: entry.client_modified,
+                        })
+                    }),
+            );
+            cursor = folder.has_more.then_some(folder.cursor);
+            if cursor.is_none() || items.len() >= PAGE_SIZE {
+                break;
+            }
+        }
+        Ok(Listing {
+            items,
+            next_page: cursor,
+            total: None,
+        })
+    }
+
+    async fn fetch(&self, token: &str, _kind: &str, id: &str, max_bytes: usize) -> Result<Fetched, AppError> {
+        let request = self
+            .client
+            .post(format!("{}/files/download", CONTENT_URL))
+            .header(AUTHORIZATION, format!("Bearer {}", token))
+            .header("Dropbox-API-Arg", json!({ "path": id }).to_string());
+        let response = super::send(SERVICE, request, max_bytes).await?;
+        let filename = response
+            .headers
+            .get("Dropbox-API-Result")
+            .and_then(|value| serde_json::from_slice::<Downloaded>(value.as_bytes()).ok())
+            .map(|result| result.name)
+            .ok_or_else(|| AppError::Fetch(format!("Unexpected {} response: no file metadata", SERVICE)))?;
+        Ok(Fetched::File {
+            filename,
+            bytes: response.bytes,
+        })
+    }
+}
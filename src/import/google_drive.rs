@@ -0,0 +1,106 @@
+use axum::async_trait;
+use chrono::{DateTime, Utc};
+use reqwest::header::AUTHORIZATION;
+use serde::Deserialize;
+
+use crate::error::AppError;
+use crate::import::{is_activity_file, ActivityProvider, Fetched, ListedActivity, Listing, PAGE_SIZE};
+
+const SERVICE: &str = "Google Drive";
+const API_URL: &str = "https://www.googleapis.com/drive/v3/files";
+
+/// Drive can't filter by extension, so this narrows the listing to likely names and the
+/// extension check does the rest.
+const QUERY: &str = "trashed = false and (name contains '.gpx' or name contains '.fit')";
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FileList {
+    #[serde(default)]
+    files: Vec<File>,
+    next_page_token: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct File {
+    id: String,
+    name: String,
+    modified_time: Option<DateTime<Utc>>,
+}
+
+/// GPX and FIT files in the user's Google Drive, most recently modified first.
+pub struct GoogleDrive {
+    client: reqwest::Client,
+}
+
+impl GoogleDrive {
+    pub fn new() -> Self {
+        Self {
+            client: super::client(SERVICE),
+        }
+    }
+
+    fn get(&self, token: &str, url: String) -> reqwest::RequestBuilder {
+        self.client
+            .get(url)
+            .header(AUTHORIZATION, format!("Bearer {}", token))
+    }
+}
+
+#[async_trait]
+impl ActivityProvider for GoogleDrive {
+    fn id(&self) -> &'static str {
+        "google-drive"
+    }
+
+    fn kinds(&self) -> &'static [&'static str] {
+        &["files"]
+    }
+
+    async fn list(&self, token: &str, _kind: &str, page: Option<&str>) -> Result<Listing, AppError> {
+        let page_size = PAGE_SIZE.to_string();
+        let mut query = vec![
+            ("q", QUERY),
+            ("pageSize", page_size.as_str()),
+            ("orderBy", "modifiedTime desc"),
+            ("fields", "nextPageToken,files(id,name,modifiedTime)"),
+        ];
+        if let Some(page) = page {
+            query.push(("pageToken", page));
+        }
+        let request = self.get(token, API_URL.to_string()).query(&query);
+        let list: FileList = super::send_listing(SERVICE, request).await?.json(SERVICE)?;
+        Ok(Listing {
+            items: list
+                .files
+                .into_iter()
+                .filter(|file| is_activity_file(&file.name))
+                .map(|file| ListedActivity {
+                    id: file.id,
+                    name: file.name,
+                    distance_km: None,
+                    elevation_gain_m: None,
+                    date: file.modified_time,
+                })
+                .collect(),
+            next_page: list.next_page_token,
+            total: None,
+        })
+    }
+
+    async fn fetch(&self, token: &str, _kind: &str, id: &str, max_bytes: usize) -> Result<Fetched, AppError> {
+        if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+            return Err(AppError::BadRequest(format!("Invalid Google Drive id: {}", id)));
+        }
+        let url = format!("{}/{}", API_URL, id);
+        let request = self.get(token, url.clone()).query(&[("fields", "id,name")]);
+        let file: File = super::send_listing(SERVICE, request).await?.json(SERVICE)?;
+        let request = self.get(token, url).query(&[("alt", "media")]);
+        let bytes = super::send(SERVICE, request, max_bytes).await?.bytes;
+        Ok(Fetched::File {
+            filename: file.name,
+            bytes,
+        })
+    }
+}
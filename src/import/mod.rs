@@ -0,0 +1,202 @@
+pub mod dropbox;
+pub mod google_drive;
+pub mod ridewithgps;
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::async_trait;
+use chrono::{DateTime, Utc};
+use reqwest::StatusCode;
+use rideviz_core::types::activity::{FileFormat, ParsedActivity};
+use serde::Deserialize;
+
+use crate::config::{CloudProvider, Config};
+use crate::error::AppError;
+
+const TIMEOUT: Duration = Duration::from_secs(20);
+/// Listing size for services that let the client choose.
+const PAGE_SIZE: usize = 50;
+/// Body cap for listings and file metadata, which are small JSON documents.
+const LISTING_MAX_BYTES: usize = 4 * 1024 * 1024;
+
+/// A service users can import activities from, mounted at `/import/<id>`. The user's own
+/// token for the service comes with each request and is passed through, never stored.
+#[async_trait]
+pub trait ActivityProvider: Send + Sync {
+    /// Path segment under `/import`.
+    fn id(&self) -> &'static str;
+
+    /// What `kind` can be in [`list`](Self::list) and [`fetch`](Self::fetch), e.g. trips
+    /// and routes. The first is the default.
+    fn kinds(&self) -> &'static [&'static str];
+
+    /// A page of the user's activities of `kind`, newest first where the service allows.
+    /// `page` is the previous listing's `next_page`, or `None` for the first.
+    async fn list(&self, token: &str, kind: &str, page: Option<&str>) -> Result<Listing, AppError>;
+
+    async fn fetch(&self, token: &str, kind: &str, id: &str, max_bytes: usize) -> Result<Fetched, AppError>;
+}
+
+pub struct Listing {
+    pub items: Vec<ListedActivity>,
+    /// Token for the following page; `None` on the last one.
+    pub next_page: Option<String>,
+    /// Across all pages, when the service reports it.
+    pub total: Option<u64>,
+}
+
+pub struct ListedActivity {
+    pub id: String,
+    pub name: String,
+    pub distance_km: Option<f64>,
+    pub elevation_gain_m: Option<f64>,
+    /// When it was recorded (or created, for planned routes and files).
+    pub date: Option<DateTime<Utc>>,
+}
+
+/// What a provider hands back for one activity.
+pub enum Fetched {
+    /// A GPX or FIT file, parsed like an upload.
+    File { filename: String, bytes: Vec<u8> },
+    /// A track already converted from the service's own format.
+    Track { name: String, activity: ParsedActivity },
+}
+
+/// Providers enabled by the configuration, by id.
+#[derive(Default)]
+pub struct Providers(BTreeMap<&'static str, Arc<dyn ActivityProvider>>);
+
+impl Providers {
+    pub fn from_config(config: &Config) -> Self {
+        let mut providers = Self::default();
+        if let Some(api_key) = &config.ridewithgps_api_key {
+            providers.add(ridewithgps::RideWithGps::new(api_key.clone()));
+        }
+        for cloud in &config.cloud_import {
+            match cloud {
+                CloudProvider::Dropbox => providers.add(dropbox::Dropbox::new()),
+                CloudProvider::GoogleDrive => providers.add(google_drive::GoogleDrive::new()),
+            }
+        }
+        providers
+    }
+
+    fn add(&mut self, provider: impl ActivityProvider + 'static) {
+        self.0.insert(provider.id(), Arc::new(provider));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn get(&self, id: &str) -> Result<Arc<dyn ActivityProvider>, AppError> {
+        self.0.get(id).cloned().ok_or_else(|| {
+            let configured: Vec<_> = self.0.keys().copied().collect();
+            AppError::BadRequest(format!(
+                "Unknown import provider: {}. Configured: {}",
+                id,
+                configured.join(", ")
+            ))
+        })
+    }
+}
+
+/// Rejects a `kind` the provider doesn't offer.
+pub fn check_kind(provider: &dyn ActivityProvider, kind: &str) -> Result<(), AppError> {
+    if provider.kinds().contains(&kind) {
+        Ok(())
+    } else {
+        Err(AppError::BadRequest(format!(
+            "Invalid kind for {}: {}. Use one of: {}",
+            provider.id(),
+            kind,
+            provider.kinds().join(", ")
+        )))
+    }
+}
+
+fn client(service: &str) -> reqwest::Client {
+    reqwest::Client::builder()
+        .timeout(TIMEOUT)
+        .build()
+        .unwrap_or_else(|e| panic!("failed to build {} client: {}", service, e))
+}
+
+/// Sends a request to `service` and reads the body, up to `max_bytes`. A rejected token
+/// or unknown id is the caller's problem (400); anything else unexpected is a 502.
+async fn send(service: &str, request: reqwest::RequestBuilder, max_bytes: usize) -> Result<Response, AppError> {
+    send_capped(service, request, max_bytes, || {
+        AppError::BadRequest(format!(
+            "File exceeds the {} MB upload limit",
+            max_bytes / (1024 * 1024)
+        ))
+    })
+    .await
+}
+
+/// Like [`send`] for listings and metadata, capped at `LISTING_MAX_BYTES`. A body larger
+/// than that is the service misbehaving (502), not an oversized upload.
+async fn send_listing(service: &str, request: reqwest::RequestBuilder) -> Result<Response, AppError> {
+    send_capped(service, request, LISTING_MAX_BYTES, || {
+        AppError::Fetch(format!(
+            "{} sent more than {} MB of listing data",
+            service,
+            LISTING_MAX_BYTES / (1024 * 1024)
+        ))
+    })
+    .await
+}
+
+async fn send_capped(
+    service: &str,
+    request: reqwest::RequestBuilder,
+    max_bytes: usize,
+    too_large: impl Fn() -> AppError,
+) -> Result<Response, AppError> {
+    let transport = |e: reqwest::Error| AppError::Fetch(format!("{}: {}", service, e.without_url()));
+    let mut response = request.send().await.map_err(transport)?;
+
+    match response.status() {
+        status if status.is_success() => {}
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+            return Err(AppError::BadRequest(format!("{} rejected the token", service)))
+        }
+        // Dropbox reports a missing path as a 409.
+        StatusCode::NOT_FOUND | StatusCode::CONFLICT => {
+            return Err(AppError::BadRequest(format!("Not found on {}", service)))
+        }
+        status => return Err(AppError::Fetch(format!("{} responded with {}", service, status))),
+    }
+
+    if response.content_length().is_some_and(|len| len > max_bytes as u64) {
+        return Err(too_large());
+    }
+    let headers = response.headers().clone();
+    let mut bytes = Vec::new();
+    while let Some(chunk) = response.chunk().await.map_err(transport)? {
+        if bytes.len() + chunk.len() > max_bytes {
+            return Err(too_large());
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    Ok(Response { headers, bytes })
+}
+
+struct Response {
+    headers: reqwest::header::HeaderMap,
+    bytes: Vec<u8>,
+}
+
+impl Response {
+    fn json<T: for<'de> Deserialize<'de>>(&self, service: &str) -> Result<T, AppError> {
+        serde_json::from_slice(&self.bytes)
+            .map_err(|e| AppError::Fetch(format!("Unexpected {} response: {}", service, e)))
+    }
+}
+
+/// Whether a file in a cloud drive looks importable.
+fn is_activity_file(name: &str) -> bool {
+    FileFormat::from_filename(name).is_some()
+}
@@ -0,0 +1,170 @@
+use axum::async_trait;
+use chrono::{DateTime, Utc};
+use rideviz_core::types::activity::{ParsedActivity, TrackPoint};
+use serde::Deserialize;
+
+use crate::error::AppError;
+use crate::import::{ActivityProvider, Fetched, ListedActivity, Listing};
+
+const SERVICE: &str = "RideWithGPS";
+const API_URL: &str = "https://ridewithgps.com/api/v1";
+
+/// Recorded rides are trips; planned courses are routes.
+const KINDS: &[&str] = &["trips", "routes"];
+
+/// The key of a single trip or route in a detail response.
+fn singular(kind: &str) -> &str {
+    kind.strip_suffix('s').unwrap_or(kind)
+}
+
+#[derive(Deserialize)]
+struct Summary {
+    id: u64,
+    #[serde(default)]
+    name: String,
+    /// Meters.
+    #[serde(default)]
+    distance: f64,
+    /// Meters.
+    #[serde(default)]
+    elevation_gain: f64,
+    /// When a trip was ridden; routes only have `created_at`.
+    departed_at: Option<DateTime<Utc>>,
+    created_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize)]
+struct Pagination {
+    #[serde(default)]
+    record_count: u64,
+    #[serde(default)]
+    page_count: u32,
+}
+
+#[derive(Deserialize)]
+struct Meta {
+    pagination: Pagination,
+}
+
+#[derive(Deserialize)]
+struct Detail {
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    track_points: Vec<RawPoint>,
+}
+
+/// RideWithGPS' compact track point: `x`/`y` are lon/lat, `t` is a Unix timestamp.
+#[derive(Deserialize)]
+struct RawPoint {
+    x: Option<f64>,
+    y: Option<f64>,
+    e: Option<f64>,
+    t: Option<i64>,
+    h: Option<u16>,
+    p: Option<u16>,
+    c: Option<u16>,
+    #[serde(rename = "T")]
+    temperature: Option<f32>,
+}
+
+/// Client for the RideWithGPS API. The service's API key identifies this app; each call
+/// also carries the user's own auth token.
+pub struct RideWithGps {
+    client: reqwest::Client,
+    api_key: String,
+}
+
+impl RideWithGps {
+    pub fn new(api_key: String) -> Self {
+        Self {
+            client: super::client(SERVICE),
+            api_key,
+        }
+    }
+
+    fn get(&self, token: &str, path: &str) -> reqwest::RequestBuilder {
+        self.client
+            .get(format!("{}/{}", API_URL, path))
+            .header("x-rwgps-api-key", &self.api_key)
+            .header("x-rwgps-auth-token", token)
+    }
+}
+
+#[async_trait]
+impl ActivityProvider for RideWithGps {
+    fn id(&self) -> &'static str {
+        "ridewithgps"
+    }
+
+    fn kinds(&self) -> &'static [&'static str] {
+        KINDS
+    }
+
+    async fn list(&self, token: &str, kind: &str, page: Option<&str>) -> Result<Listing, AppError> {
+        let page: u32 = match page {
+            Some(page) => page
+                .parse()
+                .map_err(|_| AppError::BadRequest(format!("Invalid page: {}", page)))?,
+            None => 1,
+        };
+        let path = format!("{}.json?page={}", kind, page.max(1));
+        let mut body: serde_json::Value =
+            super::send_listing(SERVICE, self.get(token, &path)).await?.json(SERVICE)?;
+        let items: Vec<Summary> = serde_json::from_value(body[kind].take()).map_err(invalid)?;
+        let meta: Meta = serde_json::from_value(body["meta"].take()).map_err(invalid)?;
+        Ok(Listing {
+            items: items
+                .into_iter()
+                .map(|item| ListedActivity {
+                    id: item.id.to_string(),
+                    name: item.name,
+                    distance_km: Some(item.distance / 1000.0),
+                    elevation_gain_m: Some(item.elevation_gain),
+                    date: item.departed_at.or(item.created_at),
+                })
+                .collect(),
+            next_page: (page < meta.pagination.page_count).then(|| (page + 1).to_string()),
+            total: Some(meta.pagination.record_count),
+        })
+    }
+
+    async fn fetch(&self, token: &str, kind: &str, id: &str, max_bytes: usize) -> Result<Fetched, AppError> {
+        let id: u64 = id
+            .parse()
+            .map_err(|_| AppError::BadRequest(format!("Invalid RideWithGPS id: {}", id)))?;
+        let path = format!("{}/{}.json", kind, id);
+        let mut body: serde_json::Value =
+            super::send(SERVICE, self.get(token, &path), max_bytes).await?.json(SERVICE)?;
+        let detail: Detail = serde_json::from_value(body[singular(kind)].take()).map_err(invalid)?;
+        let points = detail
+            .track_points
+            .into_iter()
+            .filter_map(|point| {
+                Some(TrackPoint {
+                    lat: point.y?,
+                    lon: point.x?,
+                    elevation: point.e,
+                    time: point.t.and_then(|t| DateTime::from_timestamp(t, 0)),
+                    heart_rate: point.h,
+                    power: point.p,
+                    cadence: point.c,
+                    temperature: point.temperature,
+                })
+            })
+            .collect();
+        let name = if detail.name.trim().is_empty() {
+            format!("RideWithGPS {} {}", singular(kind), id)
+        } else {
+            detail.name
+        };
+        Ok(Fetched::Track {
+            name,
+            activity: ParsedActivity { points },
+        })
+    }
+}
+
+fn invalid(err: serde_json::Error) -> AppError {
+    AppError::Fetch(format!("Unexpected {} response: {}", SERVICE, err))
+}
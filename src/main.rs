@@ -1,66 +1,219 @@
+mod assets;
+mod audit;
+mod auth;
+mod cache;
+mod cli;
 mod config;
 mod error;
-mod pipeline;
+mod fetch;
+mod import;
+mod offline;
+mod quota;
+mod rate_limit;
+mod render_pool;
+mod request_id;
 mod routes;
+mod share;
 mod state;
-mod types;
+mod store;
+mod telegram;
+mod telemetry;
+mod usage;
+mod warm_up;
+mod work_dir;
+
+use std::sync::Arc;
 
 use axum::Router;
+use axum_server::tls_rustls::RustlsConfig;
+use clap::Parser;
 use tower_http::cors::{Any, CorsLayer};
-use tower_http::services::{ServeDir, ServeFile};
 use tower_http::trace::TraceLayer;
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
-#[tokio::main]
-async fn main() {
+/// How often export quota and usage counts are written to the data dir.
+const COUNTER_FLUSH_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+
+fn main() {
+    let cli = cli::Cli::parse();
+    if let Some(command) = &cli.command {
+        if let Err(e) = offline::run(command) {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    let config = config::Config::load(cli.config.as_deref(), cli.overrides()).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(2);
+    });
+    if cli.check_config {
+        println!("Configuration OK");
+        let checks = routes::health::preflight(&config);
+        for check in &checks {
+            println!("{}", check);
+        }
+        if !checks.iter().all(|check| check.passed()) {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // Built by hand rather than with `#[tokio::main]` so the blocking pool can be sized
+    // from the config.
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .max_blocking_threads(config.blocking_threads)
+        .enable_all()
+        .build()
+        .unwrap_or_else(|e| panic!("Failed to start the Tokio runtime: {}", e));
+    runtime.block_on(serve(config));
+}
+
+async fn serve(config: config::Config) {
     // Initialize tracing
-    tracing_subscriber::registry()
-        .with(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "rideviz_rs=info,tower_http=debug".into()),
+    let tracer_provider = telemetry::init(&config);
+    let activities: Arc<dyn store::ActivityStore> = if let Some(s3) = &config.s3 {
+        Arc::new(
+            store::S3Store::open(s3)
+                .unwrap_or_else(|e| panic!("Failed to configure S3 bucket {}: {}", s3.bucket, e)),
         )
-        .with(tracing_subscriber::fmt::layer())
-        .init();
-
-    let config = config::Config::from_env();
-    let state = state::AppState::new();
+    } else if let Some(url) = &config.redis_url {
+        Arc::new(
+            store::RedisStore::open(url, "rideviz", config.cache_ttl)
+                .unwrap_or_else(|e| panic!("Failed to connect to Redis: {}", e)),
+        )
+    } else if let Some(dir) = &config.data_dir {
+        Arc::new(
+            store::DiskStore::open(dir)
+                .unwrap_or_else(|e| panic!("Failed to open data dir {}: {}", dir.display(), e)),
+        )
+    } else {
+        Arc::new(store::MemoryStore::new())
+    };
+    work_dir::create(&config.work_dir)
+        .unwrap_or_else(|e| panic!("Failed to create work dir {}: {}", config.work_dir.display(), e));
+    let state = state::AppState::new(config.clone(), activities);
+    let swept = work_dir::sweep(&config.work_dir, state.scratch_max_age());
+    if swept > 0 {
+        tracing::info!("Removed {} files left in the work dir by an earlier run", swept);
+    }
+    if config.warm_up {
+        warm_up::run(&state).await;
+    }
 
     // Start cache eviction task
     let eviction_state = state.clone();
-    let eviction_ttl = config.cache_ttl;
     tokio::spawn(async move {
         loop {
             tokio::time::sleep(tokio::time::Duration::from_secs(300)).await; // Every 5 minutes
-            eviction_state.evict_expired(eviction_ttl);
+            // Eviction deletes files and rewrites indexes, so keep it off the runtime.
+            let state = eviction_state.clone();
+            if let Err(err) = tokio::task::spawn_blocking(move || state.evict_expired()).await {
+                tracing::error!("Cache eviction failed: {}", err);
+            }
         }
     });
 
-    // Build router
-    let serve_dir = ServeDir::new("assets/web")
-        .not_found_service(ServeFile::new("assets/web/index.html"));
+    // Quota and usage counts are only written here and on shutdown, never per request.
+    let flush_state = state.clone();
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(COUNTER_FLUSH_INTERVAL).await;
+            let state = flush_state.clone();
+            if let Err(err) = tokio::task::spawn_blocking(move || state.flush_counters()).await {
+                tracing::error!("Flushing counters failed: {}", err);
+            }
+        }
+    });
+    let shutdown_state = state.clone();
 
-    let app = Router::new()
+    // Build router
+    let api_v1 = routes::api_v1(&state);
+    let api = Router::new()
         .merge(routes::health::router())
-        .merge(routes::upload::router())
-        .merge(routes::visualize::router())
-        .fallback_service(serve_dir)
+        .merge(if config.share_max_bytes > 0 {
+            routes::share::public_router(&state)
+        } else {
+            Router::new()
+        })
+        .nest(routes::API_V1, api_v1.clone())
+        .nest(routes::API_LEGACY, api_v1);
+    let app = assets::serve_web(api, config.web_dir.as_deref())
         .layer(
             CorsLayer::new()
                 .allow_origin(Any)
                 .allow_methods(Any)
-                .allow_headers(Any),
+                .allow_headers(Any)
+                .expose_headers([
+                    axum::http::header::ETAG,
+                    axum::http::header::RETRY_AFTER,
+                    request_id::REQUEST_ID_HEADER,
+                    routes::visualize::FALLBACK_HEADER,
+                    routes::visualize::FINGERPRINT_HEADER,
+                ]
+                .into_iter()
+                .chain(quota::HEADERS)
+                .collect::<Vec<_>>()),
         )
+        .layer(axum::middleware::from_fn(request_id::assign))
         .layer(axum::extract::DefaultBodyLimit::max(config.max_file_size))
         .layer(TraceLayer::new_for_http())
-        .with_state(state);
+        .with_state(state)
+        .into_make_service_with_connect_info::<std::net::SocketAddr>();
 
-    let addr = format!("0.0.0.0:{}", config.port);
-    let listener = tokio::net::TcpListener::bind(&addr).await.unwrap();
+    let addr = std::net::SocketAddr::from(([0, 0, 0, 0], config.port));
+    let scheme = if config.tls.is_some() { "https" } else { "http" };
 
     tracing::info!("RideViz-RS listening on {}", addr);
-    tracing::info!("Health check: http://{}/health", addr);
-    tracing::info!("Upload: POST http://{}/api/upload", addr);
-    tracing::info!("Visualize: POST http://{}/api/visualize", addr);
+    tracing::info!("Health check: {}://{}/health", scheme, addr);
+    tracing::info!("API docs: {}://{}/api/v1/docs", scheme, addr);
+    tracing::info!("Upload: POST {}://{}/api/v1/upload", scheme, addr);
+    tracing::info!("Upload by URL: POST {}://{}/api/v1/upload/url", scheme, addr);
+    tracing::info!("Chunked upload: POST {}://{}/api/v1/upload/chunked", scheme, addr);
+    tracing::info!("Visualize: POST {}://{}/api/v1/visualize", scheme, addr);
+    tracing::info!("Export: GET {}://{}/api/v1/export/{{gpx,csv}}/:file_id", scheme, addr);
+    tracing::info!("Legacy /api/* paths are aliases of /api/v1/*");
+
+    if let Some(tls) = &config.tls {
+        // Same crypto backend reqwest already links, rather than pulling in a second one.
+        let _ = rustls::crypto::ring::default_provider().install_default();
+        let rustls = RustlsConfig::from_pem_file(&tls.cert_path, &tls.key_path)
+            .await
+            .unwrap_or_else(|e| {
+                panic!(
+                    "Failed to load TLS certificate {} / key {}: {}",
+                    tls.cert_path.display(),
+                    tls.key_path.display(),
+                    e
+                )
+            });
+        let handle = axum_server::Handle::new();
+        let shutdown_handle = handle.clone();
+        tokio::spawn(async move {
+            let _ = tokio::signal::ctrl_c().await;
+            shutdown_handle.graceful_shutdown(None);
+        });
+        axum_server::bind_rustls(addr, rustls)
+            .handle(handle)
+            .serve(app)
+            .await
+            .unwrap();
+    } else {
+        let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
+        axum::serve(listener, app)
+            .with_graceful_shutdown(async {
+                let _ = tokio::signal::ctrl_c().await;
+            })
+            .await
+            .unwrap();
+    }
 
-    axum::serve(listener, app).await.unwrap();
+    if let Err(err) = tokio::task::spawn_blocking(move || shutdown_state.flush_counters()).await {
+        tracing::error!("Flushing counters failed: {}", err);
+    }
+
+    if let Some(provider) = tracer_provider {
+        if let Err(err) = provider.shutdown() {
+            eprintln!("Failed to flush traces: {}", err);
+        }
+    }
 }
@@ -0,0 +1,46 @@
+use std::error::Error;
+use std::path::Path;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+
+use rideviz_core::pipeline::{parse, process};
+use rideviz_core::types::activity::FileFormat;
+
+use crate::cli::Command;
+use crate::routes::visualize::{DimensionLimits, RenderJob, VisualizeRequest};
+
+/// Runs `rideviz-rs render|animate`: parses the input, renders it like `/visualize` would
+/// and writes the image.
+pub fn run(command: &Command) -> Result<(), Box<dyn Error>> {
+    let args = command.render_args();
+    let filename = args
+        .input
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or_default();
+    let format = FileFormat::from_filename(filename)
+        .ok_or_else(|| format!("{}: expected a .gpx or .fit file", args.input.display()))?;
+    let bytes = std::fs::read(&args.input)
+        .map_err(|e| format!("Failed to read {}: {}", args.input.display(), e))?;
+
+    let parsed = parse::parse(&bytes, format)?;
+    let processed = Arc::new(process::process(&parsed)?);
+    let req: VisualizeRequest = serde_json::from_value(command.request(filename))
+        .map_err(|e| format!("Invalid option: {}", e))?;
+    let image = RenderJob::new(&req, &processed, &DimensionLimits::default())?.render(&AtomicBool::new(false))?;
+
+    let output = args
+        .output
+        .clone()
+        .unwrap_or_else(|| args.input.with_extension("png"));
+    write_output(&output, &image)?;
+    println!("Wrote {} ({} bytes)", output.display(), image.len());
+    Ok(())
+}
+
+fn write_output(path: &Path, image: &[u8]) -> Result<(), Box<dyn Error>> {
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, image).map_err(|e| format!("Failed to write {}: {}", path.display(), e).into())
+}
@@ -0,0 +1,259 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use axum::http::{HeaderName, HeaderValue};
+use chrono::{DateTime, Datelike, Months, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::auth::Caller;
+use crate::config::Config;
+use crate::error::AppError;
+
+const DAILY_LIMIT: HeaderName = HeaderName::from_static("x-export-quota-daily-limit");
+const DAILY_REMAINING: HeaderName = HeaderName::from_static("x-export-quota-daily-remaining");
+const MONTHLY_LIMIT: HeaderName = HeaderName::from_static("x-export-quota-monthly-limit");
+const MONTHLY_REMAINING: HeaderName = HeaderName::from_static("x-export-quota-monthly-remaining");
+
+/// Response headers reporting quota status, for CORS `expose_headers`.
+pub const HEADERS: [HeaderName; 4] = [DAILY_LIMIT, DAILY_REMAINING, MONTHLY_LIMIT, MONTHLY_REMAINING];
+
+/// Export limits for one subject; 0 means unlimited.
+#[derive(Debug, Clone, Copy)]
+pub struct QuotaLimits {
+    pub daily: u32,
+    pub monthly: u32,
+}
+
+impl QuotaLimits {
+    /// The caller's API key overrides, else the service defaults.
+    pub fn for_caller(config: &Config, caller: &Caller) -> Self {
+        let key = caller.api_key();
+        Self {
+            daily: key
+                .and_then(|key| key.export_quota_daily)
+                .unwrap_or(config.export_quota_daily),
+            monthly: key
+                .and_then(|key| key.export_quota_monthly)
+                .unwrap_or(config.export_quota_monthly),
+        }
+    }
+
+    pub fn is_unlimited(&self) -> bool {
+        self.daily == 0 && self.monthly == 0
+    }
+}
+
+/// Animated exports used per subject in the current UTC day and month. Counts survive
+/// restarts when a data dir is configured (written by [`ExportQuotas::flush`]); otherwise
+/// they live in memory only.
+pub struct ExportQuotas {
+    usage: Mutex<HashMap<String, Usage>>,
+    path: Option<PathBuf>,
+    /// Set when the counts changed since the last flush.
+    dirty: AtomicBool,
+    saving: Mutex<()>,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+struct Usage {
+    day: Option<NaiveDate>,
+    day_count: u32,
+    /// First day of the month being counted.
+    month: Option<NaiveDate>,
+    month_count: u32,
+}
+
+impl Usage {
+    /// Resets counters whose period has rolled over.
+    fn roll(&mut self, today: NaiveDate) {
+        if self.day != Some(today) {
+            self.day = Some(today);
+            self.day_count = 0;
+        }
+        let month = first_of_month(today);
+        if self.month != Some(month) {
+            self.month = Some(month);
+            self.month_count = 0;
+        }
+    }
+}
+
+/// What's left after (or before) an export, for response headers.
+#[derive(Debug, Clone, Copy)]
+pub struct QuotaStatus {
+    limits: QuotaLimits,
+    day_count: u32,
+    month_count: u32,
+}
+
+impl QuotaStatus {
+    pub fn headers(&self) -> Vec<(HeaderName, HeaderValue)> {
+        let mut headers = Vec::new();
+        for (limit_header, remaining_header, limit, used) in [
+            (DAILY_LIMIT, DAILY_REMAINING, self.limits.daily, self.day_count),
+            (MONTHLY_LIMIT, MONTHLY_REMAINING, self.limits.monthly, self.month_count),
+        ] {
+            if limit == 0 {
+                continue;
+            }
+            headers.push((limit_header, HeaderValue::from(limit)));
+            headers.push((remaining_header, HeaderValue::from(limit.saturating_sub(used))));
+        }
+        headers
+    }
+}
+
+impl ExportQuotas {
+    pub fn new(data_dir: Option<&std::path::Path>) -> Self {
+        let path = data_dir.map(|dir| dir.join("quotas").join("exports.json"));
+        let usage = path
+            .as_ref()
+            .and_then(|path| std::fs::read(path).ok())
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .unwrap_or_default();
+        Self {
+            usage: Mutex::new(usage),
+            path,
+            dirty: AtomicBool::new(false),
+            saving: Mutex::new(()),
+        }
+    }
+
+    /// Counts one export against `subject` before it is rendered, or fails with
+    /// `QuotaExceeded` if either period has none left. Checking and counting under one
+    /// lock keeps concurrent exports from overshooting the limit.
+    pub fn reserve(&self, subject: &str, limits: QuotaLimits) -> Result<QuotaReservation<'_>, AppError> {
+        let now = Utc::now();
+        let today = now.date_naive();
+        let mut usage = self.lock();
+        let entry = usage.entry(subject.to_string()).or_default();
+        entry.roll(today);
+
+        if limits.daily > 0 && entry.day_count >= limits.daily {
+            return Err(exceeded("daily", limits.daily, next_day(now)));
+        }
+        if limits.monthly > 0 && entry.month_count >= limits.monthly {
+            return Err(exceeded("monthly", limits.monthly, next_month(now)));
+        }
+        entry.day_count += 1;
+        entry.month_count += 1;
+        let status = QuotaStatus {
+            limits,
+            day_count: entry.day_count,
+            month_count: entry.month_count,
+        };
+        self.dirty.store(true, Ordering::Relaxed);
+        Ok(QuotaReservation {
+            quotas: self,
+            subject: subject.to_string(),
+            day: today,
+            status,
+            committed: false,
+        })
+    }
+
+    /// Hands back an export reserved on `day`, unless its period has since rolled over.
+    fn release(&self, subject: &str, day: NaiveDate) {
+        let mut usage = self.lock();
+        let Some(entry) = usage.get_mut(subject) else {
+            return;
+        };
+        if entry.day == Some(day) {
+            entry.day_count = entry.day_count.saturating_sub(1);
+        }
+        if entry.month == Some(first_of_month(day)) {
+            entry.month_count = entry.month_count.saturating_sub(1);
+        }
+        self.dirty.store(true, Ordering::Relaxed);
+    }
+
+    /// Forgets subjects with nothing counted this month.
+    pub fn prune(&self) {
+        let this_month = first_of_month(Utc::now().date_naive());
+        self.lock().retain(|_, usage| usage.month == Some(this_month));
+        self.dirty.store(true, Ordering::Relaxed);
+    }
+
+    /// Writes the counts to disk if they changed since the last flush. Blocking; called
+    /// periodically and on shutdown so requests never wait on the file.
+    pub fn flush(&self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let _saving = self.saving.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if !self.dirty.swap(false, Ordering::Relaxed) {
+            return;
+        }
+        let usage = self.lock().clone();
+        let result = serde_json::to_vec(&usage)
+            .map_err(std::io::Error::other)
+            .and_then(|json| {
+                if let Some(dir) = path.parent() {
+                    std::fs::create_dir_all(dir)?;
+                }
+                let tmp = path.with_extension("tmp");
+                std::fs::write(&tmp, json)?;
+                std::fs::rename(&tmp, path)
+            });
+        if let Err(err) = result {
+            self.dirty.store(true, Ordering::Relaxed);
+            tracing::warn!("Failed to save export quotas to {}: {}", path.display(), err);
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Usage>> {
+        self.usage.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// An export counted by `ExportQuotas::reserve`. Dropped without `commit` (the render
+/// failed or the client went away), the export no longer counts.
+pub struct QuotaReservation<'a> {
+    quotas: &'a ExportQuotas,
+    subject: String,
+    day: NaiveDate,
+    status: QuotaStatus,
+    committed: bool,
+}
+
+impl QuotaReservation<'_> {
+    /// Keeps the export counted and returns what's left.
+    pub fn commit(mut self) -> QuotaStatus {
+        self.committed = true;
+        self.status
+    }
+}
+
+impl Drop for QuotaReservation<'_> {
+    fn drop(&mut self) {
+        if !self.committed {
+            self.quotas.release(&self.subject, self.day);
+        }
+    }
+}
+
+fn exceeded(period: &'static str, limit: u32, resets_at: DateTime<Utc>) -> AppError {
+    AppError::QuotaExceeded {
+        period,
+        limit,
+        retry_after_secs: (resets_at - Utc::now()).num_seconds().max(1) as u64,
+    }
+}
+
+fn first_of_month(day: NaiveDate) -> NaiveDate {
+    day.with_day(1).expect("every month has a first day")
+}
+
+fn next_day(now: DateTime<Utc>) -> DateTime<Utc> {
+    let tomorrow = now.date_naive().succ_opt().unwrap_or(NaiveDate::MAX);
+    tomorrow.and_hms_opt(0, 0, 0).expect("midnight is valid").and_utc()
+}
+
+fn next_month(now: DateTime<Utc>) -> DateTime<Utc> {
+    let next = first_of_month(now.date_naive())
+        .checked_add_months(Months::new(1))
+        .unwrap_or(NaiveDate::MAX);
+    next.and_hms_opt(0, 0, 0).expect("midnight is valid").and_utc()
+}
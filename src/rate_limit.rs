@@ -0,0 +1,143 @@
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::HeaderMap,
+    middleware::Next,
+    response::Response,
+};
+use dashmap::DashMap;
+
+use crate::auth::Caller;
+use crate::config::Config;
+use crate::error::AppError;
+use crate::state::AppState;
+
+/// Token bucket per key: up to `burst` requests at once, refilled at `per_minute`.
+pub struct KeyedRateLimiter {
+    per_minute: u32,
+    burst: u32,
+    buckets: DashMap<String, Bucket>,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+    /// Idle time after which the bucket is full again and can be forgotten.
+    full_after: Duration,
+}
+
+impl KeyedRateLimiter {
+    /// A `per_minute` of 0 disables limiting.
+    pub fn new(per_minute: u32, burst: u32) -> Self {
+        Self {
+            per_minute,
+            burst: burst.max(1),
+            buckets: DashMap::new(),
+        }
+    }
+
+    /// Takes one token for `key`, or returns how long until the next one is available.
+    pub fn check(&self, key: &str) -> Result<(), Duration> {
+        self.check_with(key, self.per_minute, self.burst)
+    }
+
+    /// Like `check`, with limits specific to this key.
+    pub fn check_with(&self, key: &str, per_minute: u32, burst: u32) -> Result<(), Duration> {
+        if per_minute == 0 {
+            return Ok(());
+        }
+        let burst = burst.max(1) as f64;
+        let refill_per_sec = per_minute as f64 / 60.0;
+        let now = Instant::now();
+        let mut bucket = self.buckets.entry(key.to_string()).or_insert(Bucket {
+            tokens: burst,
+            updated: now,
+            full_after: Duration::ZERO,
+        });
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * refill_per_sec).min(burst);
+        bucket.updated = now;
+        bucket.full_after = Duration::from_secs_f64(burst / refill_per_sec);
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / refill_per_sec))
+        }
+    }
+
+    /// Drops buckets that have refilled completely; they behave exactly like new ones.
+    pub fn prune(&self) {
+        self.buckets
+            .retain(|_, bucket| bucket.updated.elapsed() < bucket.full_after);
+    }
+}
+
+/// Middleware for CPU-heavy routes, keyed by API key when one was presented, else by
+/// client IP.
+pub async fn limit_by_client(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    let caller = request.extensions().get::<Caller>().cloned().unwrap_or_default();
+    let config = state.config();
+    let peer = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| *addr);
+    let key = client_key(config, &caller, request.headers(), peer);
+    let result = match caller.api_key() {
+        Some(api_key) => state.rate_limiter().check_with(
+            &key,
+            api_key.rate_limit_per_minute.unwrap_or(config.rate_limit_per_minute),
+            api_key.rate_limit_burst.unwrap_or(config.rate_limit_burst),
+        ),
+        None => state.rate_limiter().check(&key),
+    };
+    if let Err(retry_after) = result {
+        tracing::info!("Rate limited {} on {}", key, request.uri().path());
+        return Err(AppError::RateLimited {
+            retry_after_secs: retry_after.as_secs().max(1),
+        });
+    }
+    Ok(next.run(request).await)
+}
+
+/// Identifies who a limit applies to: the API key when one was presented, else the
+/// client IP.
+pub fn client_key(
+    config: &Config,
+    caller: &Caller,
+    headers: &HeaderMap,
+    peer: Option<SocketAddr>,
+) -> String {
+    match caller.api_key() {
+        Some(api_key) => format!("key:{}", api_key.name),
+        None => client_ip(config, headers, peer),
+    }
+}
+
+fn client_ip(config: &Config, headers: &HeaderMap, peer: Option<SocketAddr>) -> String {
+    if config.trust_proxy {
+        // Each proxy appends the address it saw, so only the rightmost entries can be
+        // trusted; anything further left came from the client.
+        let forwarded = headers
+            .get("x-forwarded-for")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| {
+                let entries: Vec<&str> = value.split(',').map(str::trim).collect();
+                let index = entries.len().saturating_sub(config.trusted_proxy_hops);
+                entries.get(index).copied()
+            })
+            .filter(|value| !value.is_empty());
+        if let Some(ip) = forwarded {
+            return ip.to_string();
+        }
+    }
+    peer.map(|addr| addr.ip().to_string())
+        .unwrap_or_else(|| "unknown".to_string())
+}
@@ -0,0 +1,86 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use rideviz_core::error::RasterError;
+use tokio::sync::{oneshot, Semaphore};
+
+use crate::error::AppError;
+
+/// Keeps rendering off Tokio's shared blocking pool. Animations run on a dedicated rayon
+/// pool (their per-frame `par_iter` stays inside it), while static renders go through the
+/// blocking pool behind a semaphore, so one long export can't starve image requests and a
+/// burst of image requests can't starve the runtime. Static renders that can't get a slot
+/// within `queue_timeout` are turned away as busy rather than piling up.
+pub struct RenderPool {
+    animations: rayon::ThreadPool,
+    static_permits: Arc<Semaphore>,
+    queue_timeout: Duration,
+}
+
+impl RenderPool {
+    /// `threads` of 0 means one per CPU.
+    pub fn new(threads: usize, max_static: usize, queue_timeout: Duration) -> Self {
+        let animations = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .thread_name(|idx| format!("render-{}", idx))
+            .build()
+            .expect("failed to start render thread pool");
+        tracing::info!(
+            "Render pool: {} animation threads, {} concurrent static renders",
+            animations.current_num_threads(),
+            max_static
+        );
+        Self {
+            animations,
+            static_permits: Arc::new(Semaphore::new(max_static.max(1))),
+            queue_timeout,
+        }
+    }
+
+    /// Runs an animation render on the dedicated pool.
+    pub async fn animate<T, F>(&self, job: F) -> Result<T, RasterError>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> T + Send + 'static,
+    {
+        let span = tracing::Span::current();
+        let (tx, rx) = oneshot::channel();
+        self.animations.spawn(move || {
+            let _ = tx.send(span.in_scope(job));
+        });
+        rx.await
+            .map_err(|_| RasterError::AnimationFailed("Render task panicked".to_string()))
+    }
+
+    /// Runs a single-frame render once a static render slot is free, or fails with
+    /// [`AppError::Busy`] if none frees up within the queue timeout.
+    pub async fn render_static<T, F>(&self, job: F) -> Result<T, AppError>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> T + Send + 'static,
+    {
+        let permits = Arc::clone(&self.static_permits);
+        let permit = tokio::time::timeout(self.queue_timeout, permits.acquire_owned())
+            .await
+            .map_err(|_| {
+                tracing::warn!(
+                    "Static render slots still busy after {:?}; turning request away",
+                    self.queue_timeout
+                );
+                AppError::Busy {
+                    retry_after_secs: self.queue_timeout.as_secs().max(1),
+                }
+            })?
+            .map_err(|err| RasterError::RenderFailed(err.to_string()))?;
+        let span = tracing::Span::current();
+        // The permit moves into the task: if the caller gives up (timeout, disconnect) the
+        // slot stays taken until the render itself has finished.
+        let rendered = tokio::task::spawn_blocking(move || {
+            let _permit = permit;
+            span.in_scope(job)
+        })
+            .await
+            .map_err(|err| RasterError::RenderFailed(format!("Render task failed: {}", err)))?;
+        Ok(rendered)
+    }
+}
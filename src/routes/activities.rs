@@ -0,0 +1,116 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    routing::{delete, get},
+    Json, Router,
+};
+use chrono::{DateTime, Utc};
+use rideviz_core::pipeline::stats;
+use rideviz_core::types::stats::ActivityStats;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::auth::Caller;
+use crate::error::AppError;
+use crate::state::AppState;
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/activities", get(list_activities))
+        .route("/activities/:file_id", delete(delete_activity))
+        .route("/activities/:file_id/stats", get(activity_stats))
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ActivityListResponse {
+    activities: Vec<ActivitySummary>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ActivitySummary {
+    file_id: String,
+    name: String,
+    distance_km: f64,
+    uploaded_at: DateTime<Utc>,
+    expires_at: DateTime<Utc>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/activities",
+    tag = "activities",
+    responses((status = 200, description = "Cached uploads, newest first", body = ActivityListResponse))
+)]
+async fn list_activities(State(state): State<AppState>, caller: Caller) -> Json<ActivityListResponse> {
+    let ttl = chrono::Duration::from_std(state.config().cache_ttl).unwrap_or(chrono::Duration::MAX);
+    let activities = state
+        .list(&caller)
+        .into_iter()
+        .map(|entry| ActivitySummary {
+            expires_at: entry.uploaded_at.checked_add_signed(ttl).unwrap_or(DateTime::<Utc>::MAX_UTC),
+            file_id: entry.file_id,
+            name: entry.name,
+            distance_km: entry.distance_km,
+            uploaded_at: entry.uploaded_at,
+        })
+        .collect();
+
+    Json(ActivityListResponse { activities })
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/activities/{file_id}",
+    tag = "activities",
+    params(("file_id" = String, Path, description = "Id returned by the upload")),
+    responses(
+        (status = 204, description = "Activity deleted"),
+        (status = 404, description = "Unknown or expired activity", body = ErrorResponse),
+    )
+)]
+async fn delete_activity(
+    State(state): State<AppState>,
+    caller: Caller,
+    Path(file_id): Path<String>,
+) -> Result<StatusCode, AppError> {
+    if !state.remove(&caller, &file_id) {
+        return Err(AppError::NotFound(file_id));
+    }
+    tracing::info!("Deleted activity {}", file_id);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct StatsQuery {
+    /// Rider's max heart rate for the zones; defaults to the activity's maximum.
+    max_heart_rate: Option<u16>,
+    /// Functional threshold power in watts; power zones are only computed with it.
+    ftp: Option<u16>,
+}
+
+/// Splits, climbs, zones and peak efforts for a stats panel, without the route geometry.
+#[utoipa::path(
+    get,
+    path = "/api/v1/activities/{file_id}/stats",
+    tag = "activities",
+    params(("file_id" = String, Path, description = "Id returned by the upload"), StatsQuery),
+    responses(
+        (status = 200, description = "Extended statistics", body = ActivityStats),
+        (status = 400, description = "max_heart_rate or ftp is 0", body = ErrorResponse),
+        (status = 404, description = "Unknown or expired activity", body = ErrorResponse),
+    )
+)]
+async fn activity_stats(
+    State(state): State<AppState>,
+    caller: Caller,
+    Path(file_id): Path<String>,
+    Query(query): Query<StatsQuery>,
+) -> Result<Json<ActivityStats>, AppError> {
+    if query.max_heart_rate == Some(0) || query.ftp == Some(0) {
+        return Err(AppError::BadRequest("max_heart_rate and ftp must be positive".to_string()));
+    }
+    let processed = state
+        .get(&caller, &file_id)
+        .ok_or_else(|| AppError::NotFound(file_id.clone()))?;
+    Ok(Json(stats::summarize(&processed, query.max_heart_rate, query.ftp)))
+}
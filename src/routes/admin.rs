@@ -0,0 +1,282 @@
+use axum::{
+    extract::{Path, Query, Request, State},
+    http::{header, StatusCode},
+    middleware::{self, Next},
+    response::Response,
+    routing::{delete, get},
+    Json, Router,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::audit::{ExportFilter, ExportOutcome, ExportRecord};
+use crate::auth::Caller;
+use crate::error::AppError;
+use crate::state::AppState;
+use crate::usage::SubjectUsage;
+
+/// How many of the oldest activities the stats endpoint lists.
+const OLDEST_LIMIT: usize = 10;
+/// Default and maximum number of audit records per response.
+const EXPORTS_DEFAULT_LIMIT: usize = 100;
+const EXPORTS_MAX_LIMIT: usize = 1000;
+
+/// Operator endpoints, only mounted when `ADMIN_TOKEN` is configured.
+pub fn router(state: &AppState) -> Router<AppState> {
+    Router::new()
+        .route("/admin/cache", get(cache_stats).delete(flush_cache))
+        .route("/admin/cache/:file_id", delete(evict_activity))
+        .route("/admin/exports", get(list_exports))
+        .route("/admin/usage", get(list_usage))
+        .route_layer(middleware::from_fn_with_state(state.clone(), require_admin))
+}
+
+async fn require_admin(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    let expected = state.config().admin_token.as_deref().ok_or(AppError::Unauthorized)?;
+    let provided = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .ok_or(AppError::Unauthorized)?;
+    // Compare digests so the check takes the same time however much of the token matches.
+    if Sha256::digest(provided.trim()) != Sha256::digest(expected) {
+        tracing::warn!("Rejected admin request to {}", request.uri().path());
+        return Err(AppError::Unauthorized);
+    }
+    Ok(next.run(request).await)
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct CacheStatsResponse {
+    activities: ActivityCacheStats,
+    renders: RenderCacheStats,
+    shares: ShareStoreStats,
+    /// Chunked uploads still receiving data.
+    pending_uploads: usize,
+    /// Exports currently rendering.
+    running_exports: usize,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ActivityCacheStats {
+    count: usize,
+    /// Oldest entries first; these are the next to expire.
+    oldest: Vec<CachedActivity>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct CachedActivity {
+    file_id: String,
+    name: String,
+    uploaded_at: DateTime<Utc>,
+    age_seconds: i64,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct RenderCacheStats {
+    count: usize,
+    bytes: usize,
+    max_bytes: usize,
+}
+
+/// Share links that haven't expired yet.
+#[derive(Serialize, ToSchema)]
+pub struct ShareStoreStats {
+    count: usize,
+    bytes: usize,
+    max_bytes: usize,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct FlushResponse {
+    activities_removed: usize,
+    renders_removed: usize,
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct ExportsQuery {
+    /// `key:<name>` or a client IP.
+    subject: Option<String>,
+    file_id: Option<String>,
+    outcome: Option<ExportOutcome>,
+    /// Only exports started at or after this time (RFC 3339).
+    since: Option<DateTime<Utc>>,
+    /// At most this many records (default 100, max 1000).
+    limit: Option<usize>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ExportLogResponse {
+    /// Newest first.
+    exports: Vec<ExportRecord>,
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct UsageQuery {
+    /// Only this subject (`key:<name>` or a client IP).
+    subject: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct UsageReportResponse {
+    /// Most animated exports this month first.
+    subjects: Vec<SubjectUsageEntry>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct SubjectUsageEntry {
+    subject: String,
+    #[serde(flatten)]
+    usage: SubjectUsage,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/cache",
+    tag = "admin",
+    security(("admin_token" = [])),
+    responses(
+        (status = 200, description = "Cache sizes and the oldest cached activities", body = CacheStatsResponse),
+        (status = 401, description = "Missing or wrong admin token", body = ErrorResponse),
+    )
+)]
+async fn cache_stats(State(state): State<AppState>) -> Json<CacheStatsResponse> {
+    let stats = state.cache_stats();
+    let now = Utc::now();
+    let mut activities = stats.activities;
+    activities.sort_by_key(|entry| entry.uploaded_at);
+    let count = activities.len();
+    let oldest = activities
+        .into_iter()
+        .take(OLDEST_LIMIT)
+        .map(|entry| CachedActivity {
+            age_seconds: (now - entry.uploaded_at).num_seconds(),
+            file_id: entry.file_id,
+            name: entry.name,
+            uploaded_at: entry.uploaded_at,
+        })
+        .collect();
+
+    Json(CacheStatsResponse {
+        activities: ActivityCacheStats { count, oldest },
+        renders: RenderCacheStats {
+            count: stats.renders,
+            bytes: stats.render_bytes,
+            max_bytes: stats.render_max_bytes,
+        },
+        shares: ShareStoreStats {
+            count: stats.shares,
+            bytes: stats.share_bytes,
+            max_bytes: stats.share_max_bytes,
+        },
+        pending_uploads: stats.pending_uploads,
+        running_exports: stats.running_exports,
+    })
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/admin/cache",
+    tag = "admin",
+    security(("admin_token" = [])),
+    responses(
+        (status = 200, description = "All cached activities and renders dropped", body = FlushResponse),
+        (status = 401, description = "Missing or wrong admin token", body = ErrorResponse),
+    )
+)]
+async fn flush_cache(State(state): State<AppState>) -> Json<FlushResponse> {
+    let (activities_removed, renders_removed) = state.flush_caches();
+    Json(FlushResponse {
+        activities_removed,
+        renders_removed,
+    })
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/admin/cache/{file_id}",
+    tag = "admin",
+    security(("admin_token" = [])),
+    params(("file_id" = String, Path, description = "Activity to evict")),
+    responses(
+        (status = 204, description = "Activity and its renders evicted"),
+        (status = 401, description = "Missing or wrong admin token", body = ErrorResponse),
+        (status = 404, description = "Unknown or expired activity", body = ErrorResponse),
+    )
+)]
+async fn evict_activity(
+    State(state): State<AppState>,
+    Path(file_id): Path<String>,
+) -> Result<StatusCode, AppError> {
+    // Admin requests aren't tied to an API key, so they can reach every namespace.
+    if !state.remove(&Caller::default(), &file_id) {
+        return Err(AppError::NotFound(file_id));
+    }
+    tracing::info!("Evicted activity {} via admin API", file_id);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/exports",
+    tag = "admin",
+    security(("admin_token" = [])),
+    params(ExportsQuery),
+    responses(
+        (status = 200, description = "Recent exports from the audit log, newest first", body = ExportLogResponse),
+        (status = 400, description = "Invalid filter"),
+        (status = 401, description = "Missing or wrong admin token", body = ErrorResponse),
+    )
+)]
+async fn list_exports(
+    State(state): State<AppState>,
+    Query(query): Query<ExportsQuery>,
+) -> Json<ExportLogResponse> {
+    let filter = ExportFilter {
+        subject: query.subject,
+        file_id: query.file_id,
+        outcome: query.outcome,
+        since: query.since,
+        limit: query.limit.unwrap_or(EXPORTS_DEFAULT_LIMIT).min(EXPORTS_MAX_LIMIT),
+    };
+    Json(ExportLogResponse {
+        exports: state.export_log().query(&filter),
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/usage",
+    tag = "admin",
+    security(("admin_token" = [])),
+    params(UsageQuery),
+    responses(
+        (status = 200, description = "Render and export counts per API key or client IP", body = UsageReportResponse),
+        (status = 401, description = "Missing or wrong admin token", body = ErrorResponse),
+    )
+)]
+async fn list_usage(
+    State(state): State<AppState>,
+    Query(query): Query<UsageQuery>,
+) -> Json<UsageReportResponse> {
+    let subjects = match query.subject {
+        Some(subject) => vec![SubjectUsageEntry {
+            usage: state.usage().get(&subject),
+            subject,
+        }],
+        None => state
+            .usage()
+            .all()
+            .into_iter()
+            .map(|(subject, usage)| SubjectUsageEntry { subject, usage })
+            .collect(),
+    };
+    Json(UsageReportResponse { subjects })
+}
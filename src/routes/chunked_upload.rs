@@ -0,0 +1,278 @@
+use axum::{
+    body::Bytes,
+    extract::{ConnectInfo, Path, State},
+    http::{HeaderMap, StatusCode},
+    routing::{get, post},
+    Json, Router,
+};
+use chrono::Utc;
+use rideviz_core::types::activity::FileFormat;
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use tokio::io::AsyncWriteExt;
+use utoipa::ToSchema;
+
+use crate::auth::Caller;
+use crate::error::AppError;
+use crate::rate_limit;
+use crate::routes::upload::{ingest_file, TempFile, UploadResponse};
+use crate::state::{AppState, PendingUpload};
+
+/// Header carrying the byte offset a chunk starts at.
+const OFFSET_HEADER: &str = "upload-offset";
+
+/// Resumable uploads: `POST /api/upload/chunked` to start (see `start_router()`), `PUT`
+/// chunks at increasing offsets, `GET` to find where to resume after a dropped
+/// connection, then `finalize`.
+pub fn router() -> Router<AppState> {
+    Router::new().route(
+            "/upload/chunked/:upload_id",
+            get(upload_status).put(append_chunk).delete(abort_upload),
+        )
+}
+
+/// Starting an upload is rate limited like a plain upload; the chunks that follow are not,
+/// so a large file isn't throttled halfway through.
+pub fn start_router() -> Router<AppState> {
+    Router::new().route("/upload/chunked", post(start_upload))
+}
+
+/// Kept apart from `router()` so parsing gets the upload timeout without cutting off
+/// slow chunk transfers.
+pub fn finalize_router() -> Router<AppState> {
+    Router::new().route("/upload/chunked/:upload_id/finalize", post(finalize_upload))
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct StartUploadRequest {
+    filename: String,
+    /// Total file size in bytes.
+    size: u64,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct UploadStatus {
+    upload_id: String,
+    /// Bytes received so far; the next chunk must start here.
+    offset: u64,
+    size: u64,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/upload/chunked",
+    tag = "upload",
+    request_body = StartUploadRequest,
+    responses(
+        (status = 200, description = "Upload started", body = UploadStatus),
+        (status = 400, description = "Unsupported format or size out of range", body = ErrorResponse),
+        (status = 429, description = "Rate limited, or too many uploads already in progress", body = ErrorResponse),
+    )
+)]
+async fn start_upload(
+    State(state): State<AppState>,
+    caller: Caller,
+    peer: Option<ConnectInfo<SocketAddr>>,
+    headers: HeaderMap,
+    Json(req): Json<StartUploadRequest>,
+) -> Result<Json<UploadStatus>, AppError> {
+    FileFormat::from_filename(&req.filename)
+        .ok_or_else(|| AppError::BadRequest("Unsupported file format".to_string()))?;
+    let max_size = state.config().max_file_size as u64;
+    if req.size == 0 || req.size > max_size {
+        return Err(AppError::BadRequest(format!(
+            "size must be between 1 and {} bytes",
+            max_size
+        )));
+    }
+
+    let owner = rate_limit::client_key(state.config(), &caller, &headers, peer.map(|ConnectInfo(addr)| addr));
+    let dir = &state.config().work_dir;
+    let upload_id = caller.new_id();
+    let path = dir.join(format!("{}.part", upload_id));
+    state.start_upload(
+        upload_id.clone(),
+        owner,
+        PendingUpload {
+            filename: req.filename.clone(),
+            size: req.size,
+            offset: 0,
+            path: path.clone(),
+            created_at: Utc::now(),
+        },
+    )?;
+    let created = tokio::fs::create_dir_all(dir)
+        .await
+        .and(tokio::fs::File::create(&path).await.map(drop));
+    if let Err(e) = created {
+        state.finish_upload(&upload_id);
+        return Err(AppError::Storage(format!("Failed to start upload: {}", e)));
+    }
+
+    tracing::info!(
+        "Started chunked upload {} for {} ({} bytes)",
+        upload_id,
+        req.filename,
+        req.size
+    );
+
+    Ok(Json(UploadStatus {
+        upload_id,
+        offset: 0,
+        size: req.size,
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/upload/chunked/{upload_id}",
+    tag = "upload",
+    params(("upload_id" = String, Path, description = "Id returned when the upload was started")),
+    responses(
+        (status = 200, description = "Current offset to resume from", body = UploadStatus),
+        (status = 404, description = "Unknown or expired upload", body = ErrorResponse),
+    )
+)]
+async fn upload_status(
+    State(state): State<AppState>,
+    caller: Caller,
+    Path(upload_id): Path<String>,
+) -> Result<Json<UploadStatus>, AppError> {
+    let pending = state
+        .pending_upload(&caller, &upload_id)
+        .ok_or_else(|| AppError::UploadNotFound(upload_id.clone()))?;
+    let upload = pending.lock().await;
+    Ok(Json(UploadStatus {
+        offset: upload.offset,
+        size: upload.size,
+        upload_id,
+    }))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/upload/chunked/{upload_id}",
+    tag = "upload",
+    params(
+        ("upload_id" = String, Path, description = "Id returned when the upload was started"),
+        ("Upload-Offset" = u64, Header, description = "Byte offset this chunk starts at"),
+    ),
+    request_body(content = Binary, content_type = "application/octet-stream"),
+    responses(
+        (status = 200, description = "Chunk stored", body = UploadStatus),
+        (status = 400, description = "Missing offset header or chunk past the declared size", body = ErrorResponse),
+        (status = 404, description = "Unknown or expired upload", body = ErrorResponse),
+        (status = 409, description = "Offset does not match the bytes received so far", body = ErrorResponse),
+    )
+)]
+async fn append_chunk(
+    State(state): State<AppState>,
+    caller: Caller,
+    Path(upload_id): Path<String>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Json<UploadStatus>, AppError> {
+    let offset: u64 = headers
+        .get(OFFSET_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse().ok())
+        .ok_or_else(|| AppError::BadRequest("Missing or invalid Upload-Offset header".to_string()))?;
+    let pending = state
+        .pending_upload(&caller, &upload_id)
+        .ok_or_else(|| AppError::UploadNotFound(upload_id.clone()))?;
+    let mut upload = pending.lock().await;
+
+    if offset != upload.offset {
+        return Err(AppError::UploadOffsetMismatch {
+            expected: upload.offset,
+            got: offset,
+        });
+    }
+    if offset + body.len() as u64 > upload.size {
+        return Err(AppError::BadRequest(format!(
+            "Chunk exceeds declared size of {} bytes",
+            upload.size
+        )));
+    }
+
+    let write = async {
+        let mut file = tokio::fs::OpenOptions::new()
+            .append(true)
+            .open(&upload.path)
+            .await?;
+        file.write_all(&body).await?;
+        file.flush().await
+    };
+    if let Err(e) = write.await {
+        // A partial write leaves the file longer than `offset`; trim it so the client can retry.
+        if let Ok(file) = std::fs::OpenOptions::new().write(true).open(&upload.path) {
+            let _ = file.set_len(upload.offset);
+        }
+        return Err(AppError::Storage(format!("Failed to store chunk: {}", e)));
+    }
+    upload.offset += body.len() as u64;
+
+    Ok(Json(UploadStatus {
+        offset: upload.offset,
+        size: upload.size,
+        upload_id,
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/upload/chunked/{upload_id}/finalize",
+    tag = "upload",
+    params(("upload_id" = String, Path, description = "Id returned when the upload was started")),
+    responses(
+        (status = 200, description = "File parsed and cached", body = UploadResponse),
+        (status = 400, description = "Upload incomplete or file unparseable", body = ErrorResponse),
+        (status = 404, description = "Unknown or expired upload", body = ErrorResponse),
+    )
+)]
+async fn finalize_upload(
+    State(state): State<AppState>,
+    caller: Caller,
+    Path(upload_id): Path<String>,
+) -> Result<Json<UploadResponse>, AppError> {
+    let pending = state
+        .pending_upload(&caller, &upload_id)
+        .ok_or_else(|| AppError::UploadNotFound(upload_id.clone()))?;
+    let upload = pending.lock().await;
+    if upload.offset != upload.size {
+        return Err(AppError::BadRequest(format!(
+            "Upload incomplete: received {} of {} bytes",
+            upload.offset, upload.size
+        )));
+    }
+
+    state.finish_upload(&upload_id);
+    let file = TempFile::new(upload.path.clone());
+
+    ingest_file(&state, &caller, upload.filename.clone(), file).await.map(Json)
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/upload/chunked/{upload_id}",
+    tag = "upload",
+    params(("upload_id" = String, Path, description = "Id returned when the upload was started")),
+    responses(
+        (status = 204, description = "Upload discarded"),
+        (status = 404, description = "Unknown or expired upload", body = ErrorResponse),
+    )
+)]
+async fn abort_upload(
+    State(state): State<AppState>,
+    caller: Caller,
+    Path(upload_id): Path<String>,
+) -> Result<StatusCode, AppError> {
+    let pending = state
+        .pending_upload(&caller, &upload_id)
+        .ok_or_else(|| AppError::UploadNotFound(upload_id.clone()))?;
+    let upload = pending.lock().await;
+    state.finish_upload(&upload_id);
+    let _ = tokio::fs::remove_file(&upload.path).await;
+    tracing::info!("Aborted chunked upload {}", upload_id);
+    Ok(StatusCode::NO_CONTENT)
+}
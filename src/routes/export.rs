@@ -0,0 +1,111 @@
+use axum::{
+    extract::{Path, State},
+    http::{header, StatusCode},
+    response::IntoResponse,
+    routing::{delete, get},
+    Router,
+};
+use rideviz_core::pipeline::export;
+
+use crate::auth::Caller;
+use crate::error::AppError;
+use crate::state::AppState;
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/export/gpx/:file_id", get(export_gpx))
+        .route("/export/csv/:file_id", get(export_csv))
+        .route("/export/jobs/:export_id", delete(cancel_export))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/export/gpx/{file_id}",
+    tag = "export",
+    params(("file_id" = String, Path, description = "Id returned by the upload")),
+    responses(
+        (status = 200, description = "Processed track as GPX 1.1", body = String, content_type = "application/gpx+xml"),
+        (status = 404, description = "Unknown or expired activity", body = ErrorResponse),
+    )
+)]
+async fn export_gpx(
+    State(state): State<AppState>,
+    caller: Caller,
+    Path(file_id): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    let processed = state
+        .get(&caller, &file_id)
+        .ok_or_else(|| AppError::NotFound(file_id.clone()))?;
+
+    let gpx = export::to_gpx(&processed, "RideViz export");
+    tracing::info!("Exported GPX for file {} ({} points)", file_id, processed.points.len());
+
+    Ok((
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, "application/gpx+xml".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"rideviz-{}.gpx\"", file_id),
+            ),
+        ],
+        gpx,
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/export/csv/{file_id}",
+    tag = "export",
+    params(("file_id" = String, Path, description = "Id returned by the upload")),
+    responses(
+        (status = 200, description = "Per-point telemetry as CSV", body = String, content_type = "text/csv"),
+        (status = 404, description = "Unknown or expired activity", body = ErrorResponse),
+    )
+)]
+async fn export_csv(
+    State(state): State<AppState>,
+    caller: Caller,
+    Path(file_id): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    let processed = state
+        .get(&caller, &file_id)
+        .ok_or_else(|| AppError::NotFound(file_id.clone()))?;
+
+    let csv = export::to_csv(&processed);
+    tracing::info!("Exported CSV for file {} ({} points)", file_id, processed.points.len());
+
+    Ok((
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, "text/csv; charset=utf-8".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"rideviz-{}.csv\"", file_id),
+            ),
+        ],
+        csv,
+    ))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/export/jobs/{export_id}",
+    tag = "export",
+    params(("export_id" = String, Path, description = "export_id sent with the visualize request")),
+    responses(
+        (status = 204, description = "Export cancelled"),
+        (status = 404, description = "No running export with this id", body = ErrorResponse),
+    )
+)]
+async fn cancel_export(
+    State(state): State<AppState>,
+    caller: Caller,
+    Path(export_id): Path<String>,
+) -> Result<StatusCode, AppError> {
+    if !state.cancel_export(&caller, &export_id) {
+        return Err(AppError::ExportNotFound(export_id));
+    }
+    tracing::info!("Cancelled export {}", export_id);
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -1,13 +1,164 @@
-use axum::{routing::get, Json, Router};
-use serde_json::{json, Value};
+use std::fmt;
+use std::path::Path;
 
-pub fn router() -> Router<crate::state::AppState> {
-    Router::new().route("/health", get(health))
+use axum::{extract::State, http::StatusCode, routing::get, Json, Router};
+use rideviz_core::pipeline::rasterize;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::config::Config;
+use crate::state::AppState;
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/health", get(health))
+        .route("/health/ready", get(ready))
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct HealthResponse {
+    #[schema(example = "ok")]
+    status: &'static str,
+    version: &'static str,
+}
+
+#[utoipa::path(
+    get,
+    path = "/health",
+    tag = "health",
+    responses((status = 200, description = "Service is up", body = HealthResponse))
+)]
+async fn health() -> Json<HealthResponse> {
+    Json(HealthResponse {
+        status: "ok",
+        version: env!("CARGO_PKG_VERSION"),
+    })
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ReadinessResponse {
+    /// `ready` or `not_ready`.
+    status: &'static str,
+    checks: Vec<ReadinessCheck>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ReadinessCheck {
+    name: &'static str,
+    ok: bool,
+    detail: String,
+}
+
+impl ReadinessCheck {
+    fn from_result(name: &'static str, result: Result<String, String>) -> Self {
+        let ok = result.is_ok();
+        Self {
+            name,
+            ok,
+            detail: result.unwrap_or_else(|err| err),
+        }
+    }
+
+    pub fn passed(&self) -> bool {
+        self.ok
+    }
+}
+
+impl fmt::Display for ReadinessCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let status = if self.ok { "ok" } else { "FAIL" };
+        write!(f, "{:<4}  {}: {}", status, self.name, self.detail)
+    }
+}
+
+/// What `--check-config` verifies beyond the config itself, so a broken deploy fails
+/// before it takes traffic: the readiness checks plus the files and directories the
+/// config points at.
+pub fn preflight(config: &Config) -> Vec<ReadinessCheck> {
+    let mut checks = vec![
+        ReadinessCheck::from_result("fonts", check_fonts()),
+        ReadinessCheck::from_result("temp_dir", check_writable(&config.work_dir)),
+    ];
+    if let Some(dir) = &config.data_dir {
+        checks.push(ReadinessCheck::from_result("data_dir", check_writable(dir)));
+    }
+    if let Some(dir) = config.audit_log.as_deref().and_then(Path::parent) {
+        checks.push(ReadinessCheck::from_result("audit_log", check_writable(dir)));
+    }
+    if let Some(tls) = &config.tls {
+        checks.push(ReadinessCheck::from_result("tls_cert", check_readable(&tls.cert_path)));
+        checks.push(ReadinessCheck::from_result("tls_key", check_readable(&tls.key_path)));
+    }
+    checks
+}
+
+/// Deep check for load balancers: fails if this instance can't actually render or accept
+/// uploads, so traffic is routed away from broken pods.
+#[utoipa::path(
+    get,
+    path = "/health/ready",
+    tag = "health",
+    responses(
+        (status = 200, description = "All checks passed", body = ReadinessResponse),
+        (status = 503, description = "At least one check failed", body = ReadinessResponse),
+    )
+)]
+async fn ready(State(state): State<AppState>) -> (StatusCode, Json<ReadinessResponse>) {
+    let checks = tokio::task::spawn_blocking(move || {
+        vec![
+            ReadinessCheck::from_result("fonts", check_fonts()),
+            ReadinessCheck::from_result("temp_dir", check_writable(&state.config().work_dir)),
+            ReadinessCheck::from_result(
+                "storage",
+                state.ping_store().map(|_| "reachable".to_string()),
+            ),
+        ]
+    })
+    .await
+    .unwrap_or_else(|err| {
+        vec![ReadinessCheck {
+            name: "checks",
+            ok: false,
+            detail: err.to_string(),
+        }]
+    });
+
+    let ready = checks.iter().all(|check| check.ok);
+    if !ready {
+        for check in checks.iter().filter(|check| !check.ok) {
+            tracing::warn!("Readiness check {} failed: {}", check.name, check.detail);
+        }
+    }
+    let status = if ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    let body = ReadinessResponse {
+        status: if ready { "ready" } else { "not_ready" },
+        checks,
+    };
+    (status, Json(body))
+}
+
+fn check_fonts() -> Result<String, String> {
+    match rasterize::loaded_font_count() {
+        0 => Err("no fonts loaded; text overlays would render blank".to_string()),
+        count => Ok(format!("{} font faces", count)),
+    }
+}
+
+fn check_writable(dir: &Path) -> Result<String, String> {
+    let probe = dir.join(".ready");
+    std::fs::create_dir_all(dir)
+        .and_then(|_| std::fs::write(&probe, b"ok"))
+        .and_then(|_| std::fs::remove_file(&probe))
+        .map(|_| format!("{} writable", dir.display()))
+        .map_err(|err| format!("{} is not writable: {}", dir.display(), err))
 }
 
-async fn health() -> Json<Value> {
-    Json(json!({
-        "status": "ok",
-        "version": env!("CARGO_PKG_VERSION")
-    }))
+fn check_readable(path: &Path) -> Result<String, String> {
+    std::fs::read(path)
+        .map(|bytes| format!("{} readable ({} bytes)", path.display(), bytes.len()))
+        .map_err(|err| format!("{} is not readable: {}", path.display(), err))
 }
@@ -0,0 +1,176 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::HeaderMap,
+    routing::{get, post},
+    Json, Router,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Deserializer, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::auth::Caller;
+use crate::error::AppError;
+use crate::import::{self, Fetched};
+use crate::routes::upload::{ingest, ingest_with, UploadResponse};
+use crate::state::AppState;
+
+/// Header carrying the user's token for the provider being imported from.
+const TOKEN_HEADER: &str = "x-import-token";
+
+/// Only mounted when at least one provider is configured.
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/import/:provider", post(import))
+        .route("/import/:provider/:kind", get(list))
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct ListQuery {
+    /// `next_page` from the previous listing; omit for the first page.
+    page: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ImportListResponse {
+    items: Vec<ImportItem>,
+    /// Pass as `page` for the following page; absent on the last one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    next_page: Option<String>,
+    /// Across all pages, when the provider reports it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    total: Option<u64>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ImportItem {
+    /// Pass back as `id` to import it.
+    id: String,
+    name: String,
+    /// Only known for providers with their own activity format, not for plain files.
+    distance_km: Option<f64>,
+    elevation_gain_m: Option<f64>,
+    /// When it was recorded, created for planned routes, or last modified for files.
+    date: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct ImportRequest {
+    /// One of the provider's kinds (`trips` or `routes` for `ridewithgps`, `files` for
+    /// cloud drives); defaults to the first.
+    kind: Option<String>,
+    /// As listed; numeric ids may also be sent as numbers.
+    #[serde(deserialize_with = "string_or_number")]
+    #[schema(value_type = String)]
+    id: String,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/import/{provider}/{kind}",
+    tag = "import",
+    params(
+        ("provider" = String, Path, description = "`ridewithgps`, `dropbox` or `google-drive`"),
+        ("kind" = String, Path, description = "`trips` or `routes` for `ridewithgps`, `files` for cloud drives"),
+        ("X-Import-Token" = String, Header, description = "The user's token for the provider"),
+        ListQuery,
+    ),
+    responses(
+        (status = 200, description = "One page of the user's activities or activity files", body = ImportListResponse),
+        (status = 400, description = "Unknown provider or kind, or missing or rejected token", body = ErrorResponse),
+        (status = 502, description = "The provider could not be reached", body = ErrorResponse),
+    )
+)]
+async fn list(
+    State(state): State<AppState>,
+    Path((provider, kind)): Path<(String, String)>,
+    Query(query): Query<ListQuery>,
+    headers: HeaderMap,
+) -> Result<Json<ImportListResponse>, AppError> {
+    let provider = state.import_providers().get(&provider)?;
+    import::check_kind(provider.as_ref(), &kind)?;
+    let listing = provider
+        .list(token(&headers)?, &kind, query.page.as_deref())
+        .await?;
+    let items = listing
+        .items
+        .into_iter()
+        .map(|item| ImportItem {
+            id: item.id,
+            name: item.name,
+            distance_km: item.distance_km,
+            elevation_gain_m: item.elevation_gain_m,
+            date: item.date,
+        })
+        .collect();
+    Ok(Json(ImportListResponse {
+        items,
+        next_page: listing.next_page,
+        total: listing.total,
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/import/{provider}",
+    tag = "import",
+    request_body = ImportRequest,
+    params(
+        ("provider" = String, Path, description = "`ridewithgps`, `dropbox` or `google-drive`"),
+        ("X-Import-Token" = String, Header, description = "The user's token for the provider"),
+    ),
+    responses(
+        (status = 200, description = "Track downloaded, processed and cached like an upload", body = UploadResponse),
+        (status = 400, description = "Unknown provider, kind or id, missing or rejected token, unparseable file or too few points", body = ErrorResponse),
+        (status = 429, description = "Rate limited; see Retry-After", body = ErrorResponse),
+        (status = 502, description = "The provider could not be reached", body = ErrorResponse),
+    )
+)]
+async fn import(
+    State(state): State<AppState>,
+    caller: Caller,
+    Path(provider): Path<String>,
+    headers: HeaderMap,
+    Json(req): Json<ImportRequest>,
+) -> Result<Json<UploadResponse>, AppError> {
+    let provider = state.import_providers().get(&provider)?;
+    let kind = match req.kind {
+        Some(kind) => {
+            import::check_kind(provider.as_ref(), &kind)?;
+            kind
+        }
+        None => provider.kinds()[0].to_string(),
+    };
+    tracing::info!("Importing {} {} {}", provider.id(), kind, req.id);
+    let fetched = provider
+        .fetch(token(&headers)?, &kind, &req.id, state.config().max_file_size)
+        .await?;
+    let response = match fetched {
+        Fetched::File { filename, bytes } => ingest(&state, &caller, filename, bytes).await,
+        Fetched::Track { name, activity } => {
+            ingest_with(&state, &caller, name, provider.id(), move || Ok(activity)).await
+        }
+    };
+    response.map(Json)
+}
+
+fn token(headers: &HeaderMap) -> Result<&str, AppError> {
+    headers
+        .get(TOKEN_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::trim)
+        .filter(|token| !token.is_empty())
+        .ok_or_else(|| AppError::BadRequest("Missing X-Import-Token header".to_string()))
+}
+
+fn string_or_number<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Id {
+        Number(u64),
+        Text(String),
+    }
+    Ok(match Id::deserialize(deserializer)? {
+        Id::Number(id) => id.to_string(),
+        Id::Text(id) => id,
+    })
+}
@@ -1,3 +1,107 @@
+pub mod activities;
+pub mod admin;
+pub mod chunked_upload;
+pub mod export;
 pub mod health;
+pub mod import;
+pub mod openapi;
+pub mod share;
+pub mod telegram;
 pub mod upload;
+pub mod usage;
+pub mod thumbnail;
 pub mod visualize;
+
+use std::time::Duration;
+
+use axum::{
+    error_handling::HandleErrorLayer,
+    extract::OriginalUri,
+    http::Method,
+    middleware, BoxError, Router,
+};
+use tower::{timeout::TimeoutLayer, ServiceBuilder};
+
+use crate::auth;
+use crate::error::AppError;
+
+use crate::rate_limit;
+use crate::state::AppState;
+
+/// Current API version prefix. Shape changes that would break clients go in a new version.
+pub const API_V1: &str = "/api/v1";
+
+/// Legacy unversioned prefix, kept as an alias of v1 for frontends built before versioning.
+pub const API_LEGACY: &str = "/api";
+
+/// All `/api` endpoints, relative to their version prefix.
+pub fn api_v1(state: &AppState) -> Router<AppState> {
+    let config = state.config();
+    // Parsing uploads and full-size renders are the CPU-heavy paths.
+    let mut rate_limited = Router::new()
+        .merge(with_timeout(upload::router(), config.upload_timeout))
+        .merge(chunked_upload::start_router())
+        .merge(visualize::router());
+    if !state.import_providers().is_empty() {
+        rate_limited = rate_limited.merge(with_timeout(import::router(), config.upload_timeout));
+    }
+    let sharing = config.share_max_bytes > 0;
+    if sharing {
+        rate_limited = rate_limited.merge(share::router());
+    }
+    let rate_limited = rate_limited
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            rate_limit::limit_by_client,
+        ));
+
+    // Runs before the rate limiter so limits can be per key. Docs and the admin API
+    // (which has its own token) are merged afterwards and stay open.
+    let router = Router::new()
+        .merge(rate_limited)
+        .merge(chunked_upload::router())
+        .merge(with_timeout(chunked_upload::finalize_router(), config.upload_timeout))
+        .merge(activities::router())
+        .merge(with_timeout(visualize::preview_router(), config.render_timeout))
+        .merge(export::router())
+        .merge(thumbnail::router())
+        .merge(usage::router())
+        .merge(if sharing { share::manage_router() } else { Router::new() })
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            auth::require_api_key,
+        ))
+        .merge(openapi::router());
+
+    let router = if state.config().admin_token.is_some() {
+        router.merge(admin::router(state))
+    } else {
+        router
+    };
+    let router = if state.telegram().is_some() {
+        router.merge(telegram::router())
+    } else {
+        router
+    };
+    // Without this, unknown API paths would fall through to the frontend's index.html
+    // and clients would get a 200 HTML page instead of an error.
+    router.fallback(unknown_endpoint)
+}
+
+async fn unknown_endpoint(method: Method, OriginalUri(uri): OriginalUri) -> AppError {
+    AppError::UnknownEndpoint(format!("{} {}", method, uri.path()))
+}
+
+/// Answers with a 504 once a request has run for `limit`. Work already handed to a
+/// blocking thread finishes in the background; only the response is cut off.
+fn with_timeout(router: Router<AppState>, limit: Duration) -> Router<AppState> {
+    let secs = limit.as_secs();
+    router.route_layer(
+        ServiceBuilder::new()
+            .layer(HandleErrorLayer::new(move |err: BoxError| async move {
+                tracing::warn!("Request aborted: {}", err);
+                AppError::Timeout(secs)
+            }))
+            .layer(TimeoutLayer::new(limit)),
+    )
+}
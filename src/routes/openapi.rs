@@ -0,0 +1,152 @@
+use axum::{response::Html, routing::get, Json, Router};
+use rideviz_core::types::{
+    activity::{AvailableData, Metrics},
+    stats::{ActivityStats, Climb, Peak, Split, Zone},
+    viz::{AnimationEasing, AnimationMode, CameraMode, LoopMode, OutputFormat},
+};
+use utoipa::openapi::security::{Http, HttpAuthScheme, SecurityScheme};
+use utoipa::{Modify, OpenApi, ToSchema};
+
+use crate::audit::{ExportOutcome, ExportRecord};
+use crate::error::ErrorResponse;
+use crate::routes::{
+    activities, admin, chunked_upload, export, health, import, share, thumbnail, upload, usage,
+    visualize,
+};
+use crate::state::AppState;
+use crate::usage::{SubjectUsage, UsageCounts};
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/openapi.json", get(openapi_json))
+        .route("/docs", get(swagger_ui))
+}
+
+/// Raw bytes: uploaded chunks and rendered images.
+#[derive(ToSchema)]
+#[schema(value_type = String, format = Binary)]
+pub struct Binary(#[allow(dead_code)] Vec<u8>);
+
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "RideViz-RS", description = "3D animated route overlays from GPX/FIT activities."),
+    paths(
+        health::health,
+        health::ready,
+        upload::upload,
+        upload::upload_url,
+        import::list,
+        import::import,
+        chunked_upload::start_upload,
+        chunked_upload::upload_status,
+        chunked_upload::append_chunk,
+        chunked_upload::finalize_upload,
+        chunked_upload::abort_upload,
+        activities::list_activities,
+        activities::delete_activity,
+        activities::activity_stats,
+        thumbnail::thumbnail,
+        usage::usage,
+        visualize::visualize,
+        visualize::preview,
+        share::create,
+        share::remove,
+        share::open,
+        share::page,
+        export::export_gpx,
+        export::export_csv,
+        export::cancel_export,
+        admin::cache_stats,
+        admin::flush_cache,
+        admin::evict_activity,
+        admin::list_exports,
+        admin::list_usage,
+    ),
+    components(schemas(
+        ErrorResponse,
+        Binary,
+        health::HealthResponse,
+        health::ReadinessResponse,
+        health::ReadinessCheck,
+        upload::UploadResponse,
+        upload::UploadForm,
+        upload::UploadUrlRequest,
+        import::ImportListResponse,
+        import::ImportItem,
+        import::ImportRequest,
+        chunked_upload::StartUploadRequest,
+        chunked_upload::UploadStatus,
+        activities::ActivityListResponse,
+        activities::ActivitySummary,
+        admin::CacheStatsResponse,
+        admin::ActivityCacheStats,
+        admin::CachedActivity,
+        admin::RenderCacheStats,
+        admin::ShareStoreStats,
+        admin::FlushResponse,
+        admin::ExportLogResponse,
+        admin::UsageReportResponse,
+        admin::SubjectUsageEntry,
+        ExportRecord,
+        ExportOutcome,
+        usage::UsageResponse,
+        SubjectUsage,
+        UsageCounts,
+        visualize::VisualizeRequest,
+        share::ShareRequest,
+        share::ShareResponse,
+        Metrics,
+        AvailableData,
+        ActivityStats,
+        Split,
+        Climb,
+        Zone,
+        Peak,
+        AnimationEasing,
+        AnimationMode,
+        LoopMode,
+        CameraMode,
+        OutputFormat,
+    )),
+    modifiers(&AdminTokenScheme)
+)]
+pub struct ApiDoc;
+
+/// `Authorization: Bearer <ADMIN_TOKEN>` on the admin endpoints.
+struct AdminTokenScheme;
+
+impl Modify for AdminTokenScheme {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        if let Some(components) = openapi.components.as_mut() {
+            components.add_security_scheme(
+                "admin_token",
+                SecurityScheme::Http(Http::new(HttpAuthScheme::Bearer)),
+            );
+        }
+    }
+}
+
+async fn openapi_json() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
+
+/// Swagger UI assets come from a CDN so the binary doesn't have to bundle them.
+async fn swagger_ui() -> Html<&'static str> {
+    Html(
+        r##"<!doctype html>
+<html>
+<head>
+  <meta charset="utf-8">
+  <title>RideViz-RS API</title>
+  <link rel="stylesheet" href="https://unpkg.com/swagger-ui-dist@5/swagger-ui.css">
+</head>
+<body>
+  <div id="swagger-ui"></div>
+  <script src="https://unpkg.com/swagger-ui-dist@5/swagger-ui-bundle.js"></script>
+  <script>
+    window.ui = SwaggerUIBundle({ url: "/api/v1/openapi.json", dom_id: "#swagger-ui" });
+  </script>
+</body>
+</html>"##,
+    )
+}
@@ -0,0 +1,406 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use axum::{
+    extract::{ConnectInfo, Path, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    middleware,
+    response::{Html, IntoResponse, Response},
+    routing::{delete, get, post},
+    Json, Router,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::auth::Caller;
+use crate::error::AppError;
+use crate::rate_limit;
+use crate::request_id;
+use crate::routes::visualize::{audited_export, render_fingerprint, DimensionLimits, VisualizeRequest};
+use crate::share::{Share, ShareStore, ShareSummary};
+use crate::state::AppState;
+
+/// Public prefix of share links, outside `/api` so they work without an API key.
+pub const SHARE_PREFIX: &str = "/s";
+
+const MAX_PASSWORD_CHARS: usize = 128;
+const MAX_TITLE_CHARS: usize = 80;
+
+/// Creating a share renders, so it sits with the rate-limited routes.
+pub fn router() -> Router<AppState> {
+    Router::new().route("/share", post(create))
+}
+
+pub fn manage_router() -> Router<AppState> {
+    Router::new().route("/share/:token", delete(remove))
+}
+
+/// Serves share links; mounted at the root next to `/health`. Rate limited per client IP
+/// so passwords can't be guessed at full speed.
+pub fn public_router(state: &AppState) -> Router<AppState> {
+    Router::new()
+        .route(&format!("{}/:token", SHARE_PREFIX), get(open))
+        .route(&format!("{}/:token/page", SHARE_PREFIX), get(page))
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            rate_limit::limit_by_client,
+        ))
+}
+
+#[derive(Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ShareRequest {
+    /// The same options as `/visualize`; an identical earlier render is reused.
+    visualize: VisualizeRequest,
+    /// Hours until the link stops working; defaults to (and is capped at) the server's
+    /// `share_max_ttl_hours`.
+    expires_in_hours: Option<u32>,
+    /// Viewers are asked for it (HTTP Basic auth, any user name).
+    password: Option<String>,
+    /// Heading of the share page and link previews (default "Ride").
+    title: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ShareResponse {
+    token: String,
+    /// Path of the image, relative to this server.
+    #[schema(example = "/s/3f2b9c0e8d7a4e56b1c2d3e4f5a6b7c8")]
+    url: String,
+    /// Path of an HTML page showing the image, with Open Graph tags so the link unfurls
+    /// in chats and social feeds.
+    #[schema(example = "/s/3f2b9c0e8d7a4e56b1c2d3e4f5a6b7c8/page")]
+    page_url: String,
+    content_type: String,
+    bytes: usize,
+    expires_at: DateTime<Utc>,
+    password_protected: bool,
+    /// Same as the `X-Render-Fingerprint` header of `/visualize`: equal for identical
+    /// renders, even of separate uploads.
+    fingerprint: String,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/share",
+    tag = "share",
+    request_body = ShareRequest,
+    responses(
+        (status = 200, description = "Render stored under a public link", body = ShareResponse),
+        (status = 400, description = "Invalid options or password", body = ErrorResponse),
+        (status = 404, description = "Unknown or expired activity", body = ErrorResponse),
+        (status = 429, description = "Rate limited, or export quota used up; see Retry-After", body = ErrorResponse),
+        (status = 503, description = "Share storage is full, or no render slot freed up in time (code `busy`)", body = ErrorResponse),
+    )
+)]
+async fn create(
+    State(state): State<AppState>,
+    caller: Caller,
+    peer: Option<ConnectInfo<SocketAddr>>,
+    headers: HeaderMap,
+    Json(req): Json<ShareRequest>,
+) -> Result<Json<ShareResponse>, AppError> {
+    let password = req.password.filter(|password| !password.is_empty());
+    if password.as_ref().is_some_and(|password| password.chars().count() > MAX_PASSWORD_CHARS) {
+        return Err(AppError::BadRequest(format!(
+            "Password must be at most {} characters",
+            MAX_PASSWORD_CHARS
+        )));
+    }
+    let max_ttl = chrono::Duration::from_std(state.config().share_max_ttl).unwrap_or(chrono::Duration::MAX);
+    let ttl = match req.expires_in_hours {
+        Some(0) => return Err(AppError::BadRequest("expires_in_hours must be at least 1".to_string())),
+        Some(hours) => chrono::Duration::hours(hours.into()).min(max_ttl),
+        None => max_ttl,
+    };
+
+    let file_id = req.visualize.file_id();
+    let processed = state
+        .get(&caller, file_id)
+        .ok_or_else(|| AppError::NotFound(file_id.to_string()))?;
+    let subject = rate_limit::client_key(state.config(), &caller, &headers, peer.map(|ConnectInfo(addr)| addr));
+    let request_id = request_id::current().unwrap_or_else(|| Uuid::new_v4().to_string());
+    let limits = DimensionLimits::for_caller(state.config(), &caller);
+    let fingerprint = render_fingerprint(&req.visualize, &limits, &processed)?;
+    let (render, _) = audited_export(&state, &caller, &subject, request_id, &req.visualize, &processed).await?;
+
+    let title = req
+        .title
+        .as_deref()
+        .map(str::trim)
+        .filter(|title| !title.is_empty())
+        .map(|title| title.chars().take(MAX_TITLE_CHARS).collect())
+        .unwrap_or_else(|| "Ride".to_string());
+    let summary = ShareSummary::from(&processed.metrics);
+    let expires_at = Utc::now().checked_add_signed(ttl).unwrap_or(DateTime::<Utc>::MAX_UTC);
+    let (token, share) = with_store(&state, move |shares| {
+        shares.insert(&caller, render, title, summary, expires_at, password.as_deref())
+    })
+    .await??;
+    tracing::info!("Shared render of file {} as {} until {}", file_id, token, expires_at);
+    Ok(Json(ShareResponse {
+        url: format!("{}/{}", SHARE_PREFIX, token),
+        page_url: format!("{}/{}/page", SHARE_PREFIX, token),
+        token,
+        password_protected: share.has_password(),
+        content_type: share.content_type,
+        bytes: share.size,
+        expires_at: share.expires_at,
+        fingerprint,
+    }))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/share/{token}",
+    tag = "share",
+    params(("token" = String, Path, description = "Token returned when the share was created")),
+    responses(
+        (status = 204, description = "Share deleted"),
+        (status = 404, description = "Unknown share, or made with another API key", body = ErrorResponse),
+    )
+)]
+async fn remove(
+    State(state): State<AppState>,
+    caller: Caller,
+    Path(token): Path<String>,
+) -> Result<StatusCode, AppError> {
+    let removed = {
+        let token = token.clone();
+        with_store(&state, move |shares| shares.remove(&caller, &token)).await?
+    };
+    if removed {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(AppError::ShareNotFound(token))
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/s/{token}",
+    tag = "share",
+    params(("token" = String, Path, description = "Token returned when the share was created")),
+    responses(
+        (status = 200, description = "The shared image", body = Binary, content_type = "image/png"),
+        (status = 401, description = "Password required or wrong (HTTP Basic auth)", body = ErrorResponse),
+        (status = 404, description = "Unknown or expired share", body = ErrorResponse),
+        (status = 429, description = "Rate limited; see Retry-After", body = ErrorResponse),
+        (status = 503, description = "Too many password checks in progress (code `busy`); see Retry-After", body = ErrorResponse),
+    )
+)]
+async fn open(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let found = {
+        let token = token.clone();
+        with_store(&state, move |shares| shares.get(&token)).await?
+    };
+    let (share, bytes) = found.ok_or_else(|| AppError::ShareNotFound(token.clone()))?;
+    let (share, unlocked) = unlock(&state, share, &headers).await?;
+    if !unlocked {
+        return Ok(password_required());
+    }
+    let cache_control = cache_control(&share);
+    Ok(shared_response(
+        [
+            (header::CONTENT_TYPE, share.content_type),
+            (header::CACHE_CONTROL, cache_control),
+        ],
+        bytes,
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/s/{token}/page",
+    tag = "share",
+    params(("token" = String, Path, description = "Token returned when the share was created")),
+    responses(
+        (status = 200, description = "HTML page with the image and Open Graph / Twitter card tags", content_type = "text/html"),
+        (status = 401, description = "Password required or wrong (HTTP Basic auth)", body = ErrorResponse),
+        (status = 404, description = "Unknown or expired share", body = ErrorResponse),
+        (status = 429, description = "Rate limited; see Retry-After", body = ErrorResponse),
+        (status = 503, description = "Too many password checks in progress (code `busy`); see Retry-After", body = ErrorResponse),
+    )
+)]
+async fn page(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let share = state
+        .shares()
+        .details(&token)
+        .ok_or_else(|| AppError::ShareNotFound(token.clone()))?;
+    let (share, unlocked) = unlock(&state, share, &headers).await?;
+    if !unlocked {
+        return Ok(password_required());
+    }
+
+    // Link previews need an absolute image URL.
+    let image_url = format!("{}{}/{}", base_url(&state, &headers), SHARE_PREFIX, token);
+    let title = escape_html(&share.title);
+    let description = escape_html(&share.summary.describe());
+    let image_url = escape_html(&image_url);
+    let dimensions = share
+        .dimensions
+        .map(|(width, height)| {
+            format!(
+                "<meta property=\"og:image:width\" content=\"{}\">\n\
+                 <meta property=\"og:image:height\" content=\"{}\">\n",
+                width, height
+            )
+        })
+        .unwrap_or_default();
+    let html = format!(
+        r#"<!doctype html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<meta name="robots" content="noindex">
+<title>{title}</title>
+<meta property="og:type" content="website">
+<meta property="og:site_name" content="RideViz">
+<meta property="og:title" content="{title}">
+<meta property="og:description" content="{description}">
+<meta property="og:image" content="{image_url}">
+<meta property="og:image:type" content="{content_type}">
+{dimensions}<meta name="twitter:card" content="summary_large_image">
+<meta name="twitter:title" content="{title}">
+<meta name="twitter:description" content="{description}">
+<meta name="twitter:image" content="{image_url}">
+<style>
+  body {{ margin: 0; min-height: 100vh; display: flex; flex-direction: column; align-items: center;
+         justify-content: center; background: #111; color: #eee; font-family: system-ui, sans-serif; }}
+  img {{ max-width: 100%; max-height: 85vh; }}
+</style>
+</head>
+<body>
+<h1>{title}</h1>
+<img src="{image_url}" alt="{title}">
+<p>{description}</p>
+</body>
+</html>
+"#,
+        content_type = escape_html(&share.content_type),
+    );
+    let cache_control = cache_control(&share);
+    Ok(shared_response([(header::CACHE_CONTROL, cache_control)], Html(html)))
+}
+
+/// Runs `f` on the blocking pool, since the store reads and writes files (and hashes
+/// passwords on insert).
+async fn with_store<T, F>(state: &AppState, f: F) -> Result<T, AppError>
+where
+    T: Send + 'static,
+    F: FnOnce(&ShareStore) -> T + Send + 'static,
+{
+    let state = state.clone();
+    tokio::task::spawn_blocking(move || f(state.shares()))
+        .await
+        .map_err(|err| AppError::Storage(format!("Share task failed: {}", err)))
+}
+
+/// Checks the Basic auth password against `share` on the blocking pool, since the hash
+/// is deliberately slow.
+async fn unlock(
+    state: &AppState,
+    share: Share,
+    headers: &HeaderMap,
+) -> Result<(Share, bool), AppError> {
+    if !share.has_password() {
+        return Ok((share, true));
+    }
+    let password = basic_password(headers);
+    // Each check hashes with Argon2id; past a few at once, turn guesses away.
+    let permit = Arc::clone(state.password_checks())
+        .try_acquire_owned()
+        .map_err(|_| AppError::Busy { retry_after_secs: 1 })?;
+    tokio::task::spawn_blocking(move || {
+        let _permit = permit;
+        let unlocked = share.unlocks(password.as_deref());
+        (share, unlocked)
+    })
+    .await
+    .map_err(|err| AppError::Storage(format!("Share task failed: {}", err)))
+}
+
+fn password_required() -> Response {
+    (
+        StatusCode::UNAUTHORIZED,
+        [(header::WWW_AUTHENTICATE, "Basic realm=\"rideviz share\"")],
+        AppError::Unauthorized,
+    )
+        .into_response()
+}
+
+/// Never cache a protected share in shared caches, nor anything past its expiry.
+fn cache_control(share: &Share) -> String {
+    let max_age = (share.expires_at - Utc::now()).num_seconds().max(0);
+    let visibility = if share.has_password() { "private" } else { "public" };
+    format!("{}, max-age={}", visibility, max_age)
+}
+
+/// Share links are unlisted; keep them out of search engines.
+fn shared_response(parts: impl axum::response::IntoResponseParts, body: impl IntoResponse) -> Response {
+    let mut response = (parts, body).into_response();
+    response
+        .headers_mut()
+        .insert("x-robots-tag", HeaderValue::from_static("noindex"));
+    response
+}
+
+/// `PUBLIC_URL` if set, else the scheme and host this request came in on.
+fn base_url(state: &AppState, headers: &HeaderMap) -> String {
+    let config = state.config();
+    if let Some(url) = &config.public_url {
+        return url.clone();
+    }
+    let Some(host) = headers.get(header::HOST).and_then(|value| value.to_str().ok()) else {
+        return String::new();
+    };
+    let forwarded_proto = config
+        .trust_proxy
+        .then(|| headers.get("x-forwarded-proto").and_then(|value| value.to_str().ok()))
+        .flatten();
+    let scheme = match forwarded_proto {
+        Some(proto) if proto.eq_ignore_ascii_case("https") => "https",
+        Some(_) => "http",
+        None if config.tls.is_some() => "https",
+        None => "http",
+    };
+    format!("{}://{}", scheme, host)
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// The password from `Authorization: Basic`, ignoring the user name.
+fn basic_password(headers: &HeaderMap) -> Option<String> {
+    let encoded = headers
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Basic ")?;
+    let decoded = String::from_utf8(STANDARD.decode(encoded.trim()).ok()?).ok()?;
+    decoded.split_once(':').map(|(_, password)| password.to_string())
+}
@@ -0,0 +1,152 @@
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+
+use axum::{extract::State, http::HeaderMap, http::StatusCode, routing::post, Json, Router};
+use rideviz_core::pipeline::{parse, process};
+use rideviz_core::types::activity::FileFormat;
+use serde::Deserialize;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+
+use crate::audit::ExportOutcome;
+use crate::auth::Caller;
+use crate::error::AppError;
+use crate::routes::visualize::{DimensionLimits, RenderJob, VisualizeRequest};
+use crate::share::ShareSummary;
+use crate::state::AppState;
+use crate::telegram::{TelegramBot, MAX_DOWNLOAD_BYTES};
+
+/// Telegram echoes the webhook's `secret_token` in this header.
+const SECRET_HEADER: &str = "x-telegram-bot-api-secret-token";
+
+const HELP: &str = "Send me a GPX or FIT file and I'll reply with a 3D render of the route.";
+
+/// Only mounted when a bot token is configured. Sits outside API-key auth: Telegram
+/// authenticates with the webhook secret instead.
+pub fn router() -> Router<AppState> {
+    Router::new().route("/telegram/webhook", post(webhook))
+}
+
+/// The parts of a Telegram update the bot uses; everything else is ignored.
+#[derive(Deserialize)]
+struct Update {
+    message: Option<Message>,
+}
+
+#[derive(Deserialize)]
+struct Message {
+    message_id: i64,
+    chat: Chat,
+    document: Option<Document>,
+}
+
+#[derive(Deserialize)]
+struct Chat {
+    id: i64,
+    /// `private`, `group`, `supergroup` or `channel`.
+    #[serde(rename = "type")]
+    kind: String,
+}
+
+#[derive(Deserialize)]
+struct Document {
+    file_id: String,
+    file_name: Option<String>,
+    file_size: Option<u64>,
+}
+
+/// Receives updates from Telegram. Answers right away and replies from a background
+/// task, since Telegram holds back further updates (and eventually retries this one)
+/// until the webhook responds.
+async fn webhook(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(update): Json<Update>,
+) -> Result<StatusCode, AppError> {
+    let (Some(bot), Some(secret)) = (state.telegram().cloned(), &state.config().telegram_webhook_secret) else {
+        return Err(AppError::Unauthorized);
+    };
+    let presented = headers
+        .get(SECRET_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    // Compared by digest so the time taken doesn't depend on how much of a guess is right.
+    if Sha256::digest(presented) != Sha256::digest(secret) {
+        tracing::warn!("Rejected Telegram update with a wrong secret");
+        return Err(AppError::Unauthorized);
+    }
+
+    if let Some(message) = update.message {
+        tokio::spawn(async move {
+            if let Err(err) = reply(&state, &bot, &message).await {
+                tracing::info!("Telegram chat {}: {}", message.chat.id, err);
+                let text = format!("Sorry, that didn't work. {}", err);
+                if let Err(err) = bot.send_message(message.chat.id, message.message_id, &text).await {
+                    tracing::warn!("Failed to reply to Telegram chat {}: {}", message.chat.id, err);
+                }
+            }
+        });
+    }
+    Ok(StatusCode::OK)
+}
+
+async fn reply(state: &AppState, bot: &Arc<TelegramBot>, message: &Message) -> Result<(), AppError> {
+    let Some(document) = &message.document else {
+        // In groups the bot sees all kinds of chatter; only explain itself one-on-one.
+        if message.chat.kind == "private" {
+            bot.send_message(message.chat.id, message.message_id, HELP).await?;
+        }
+        return Ok(());
+    };
+    let filename = document.file_name.clone().unwrap_or_default();
+    let format = FileFormat::from_filename(&filename)
+        .ok_or_else(|| AppError::BadRequest("Only .gpx and .fit files are supported".to_string()))?;
+    let max_bytes = state.config().max_file_size.min(MAX_DOWNLOAD_BYTES);
+    if document.file_size.is_some_and(|size| size > max_bytes as u64) {
+        return Err(AppError::BadRequest(format!(
+            "File exceeds the {} MB upload limit",
+            max_bytes / (1024 * 1024)
+        )));
+    }
+    let subject = format!("telegram:{}", message.chat.id);
+    if let Err(retry_after) = state.rate_limiter().check(&subject) {
+        return Err(AppError::RateLimited {
+            retry_after_secs: retry_after.as_secs().max(1),
+        });
+    }
+
+    tracing::info!("Rendering {} for Telegram chat {}", filename, message.chat.id);
+    let bytes = bot.download(&document.file_id, max_bytes).await?;
+    let processed = tokio::task::spawn_blocking(move || -> Result<_, AppError> {
+        Ok(Arc::new(process::process(&parse::parse(&bytes, format)?)?))
+    })
+    .await
+    .map_err(|err| AppError::BadRequest(format!("Failed to parse upload: {}", err)))??;
+
+    let request: VisualizeRequest = serde_json::from_value(json!({
+        "file_id": "telegram",
+        "width": 1080,
+        "height": 1080,
+        "background": "black",
+        "stats": ["distance", "duration", "elevation_gain", "avg_speed"],
+    }))
+    .map_err(|e| AppError::BadRequest(e.to_string()))?;
+    let job = RenderJob::new(
+        &request,
+        &processed,
+        &DimensionLimits::for_caller(state.config(), &Caller::default()),
+    )?;
+    let limit = state.config().render_timeout;
+    let render = state
+        .render_pool()
+        .render_static(move || job.render(&AtomicBool::new(false)));
+    let png = tokio::time::timeout(limit, render)
+        .await
+        .map_err(|_| AppError::Timeout(limit.as_secs()))???;
+    state
+        .usage()
+        .record(&subject, false, ExportOutcome::Rendered, png.len());
+
+    let caption = ShareSummary::from(&processed.metrics).describe();
+    bot.send_photo(message.chat.id, message.message_id, png, &caption).await
+}
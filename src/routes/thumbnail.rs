@@ -0,0 +1,63 @@
+use axum::{
+    extract::{Path, State},
+    http::{header, StatusCode},
+    response::IntoResponse,
+    routing::get,
+    Router,
+};
+use rideviz_core::pipeline::{prepare, rasterize, render};
+use rideviz_core::types::activity::ProcessedActivity;
+use rideviz_core::types::viz::{OutputConfig, RenderOptions};
+
+use crate::auth::Caller;
+use crate::error::AppError;
+use crate::state::AppState;
+
+const THUMBNAIL_SIZE: u32 = 256;
+
+pub fn router() -> Router<AppState> {
+    Router::new().route("/thumbnail/:file_id", get(thumbnail))
+}
+
+/// Small square, transparent, glow-free render of the full route used by activity pickers.
+pub fn render_thumbnail(processed: &ProcessedActivity) -> Result<Vec<u8>, AppError> {
+    let mut options = RenderOptions::route_3d_defaults();
+    options.width = THUMBNAIL_SIZE;
+    options.height = THUMBNAIL_SIZE;
+    options.padding = 12;
+    options.stroke_width = 2.0;
+    options.glow = false;
+    options.wall_subdivisions = 1;
+
+    let viz_data = prepare::prepare(processed, &options)?;
+    let svg = render::render_svg_frame(&viz_data, &options, 1.0, 0.0, &[])?;
+    let output_config = OutputConfig {
+        width: options.width,
+        height: options.height,
+        background: None,
+        watermark: false,
+    };
+    Ok(rasterize::rasterize(&svg, &output_config)?)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/thumbnail/{file_id}",
+    tag = "activities",
+    params(("file_id" = String, Path, description = "Id returned by the upload")),
+    responses(
+        (status = 200, description = "256x256 transparent PNG of the route", body = Binary, content_type = "image/png"),
+        (status = 404, description = "Unknown activity or no thumbnail", body = ErrorResponse),
+    )
+)]
+async fn thumbnail(
+    State(state): State<AppState>,
+    caller: Caller,
+    Path(file_id): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    let png = state
+        .thumbnail(&caller, &file_id)
+        .ok_or_else(|| AppError::NotFound(file_id.clone()))?;
+
+    Ok((StatusCode::OK, [(header::CONTENT_TYPE, "image/png")], png))
+}
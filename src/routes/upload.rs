@@ -1,30 +1,65 @@
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
 use axum::{extract::State, routing::post, Json, Router};
-use axum::extract::Multipart;
+use axum::extract::multipart::{Field, Multipart};
+use memmap2::Mmap;
+use reqwest::Url;
+use rideviz_core::pipeline::{parse, process};
+use rideviz_core::types::activity::{AvailableData, FileFormat, Metrics, ParsedActivity};
 use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWriteExt;
+use utoipa::ToSchema;
 use uuid::Uuid;
 
+use crate::auth::Caller;
 use crate::error::AppError;
-use crate::pipeline::{parse, process};
+use crate::fetch;
+use crate::routes::thumbnail;
 use crate::state::AppState;
-use crate::types::activity::{AvailableData, FileFormat, Metrics};
 
 pub fn router() -> Router<AppState> {
-    Router::new().route("/api/upload", post(upload))
+    Router::new()
+        .route("/upload", post(upload))
+        .route("/upload/url", post(upload_url))
 }
 
-#[derive(Serialize, Deserialize)]
-struct UploadResponse {
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct UploadResponse {
     file_id: String,
+    #[schema(example = "gpx")]
     file_type: String,
     metrics: Metrics,
     available_data: AvailableData,
 }
 
+/// Multipart form accepted by `POST /api/upload`.
+#[derive(ToSchema)]
+#[allow(dead_code)]
+pub struct UploadForm {
+    /// A `.gpx` or `.fit` file.
+    #[schema(value_type = String, format = Binary)]
+    file: Vec<u8>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/upload",
+    tag = "upload",
+    request_body(content = UploadForm, content_type = "multipart/form-data"),
+    responses(
+        (status = 200, description = "File parsed and cached", body = UploadResponse),
+        (status = 400, description = "Missing, unsupported or unparseable file", body = ErrorResponse),
+        (status = 429, description = "Rate limited; see Retry-After", body = ErrorResponse),
+    )
+)]
 async fn upload(
     State(state): State<AppState>,
+    caller: Caller,
     mut multipart: Multipart,
 ) -> Result<Json<UploadResponse>, AppError> {
-    let mut file_bytes: Option<Vec<u8>> = None;
+    let mut spooled: Option<TempFile> = None;
     let mut filename: Option<String> = None;
 
     while let Some(field) = multipart.next_field().await.map_err(|e| {
@@ -34,25 +69,182 @@ async fn upload(
         
         if name == "file" {
             filename = field.file_name().map(|s| s.to_string());
-            file_bytes = Some(field.bytes().await.map_err(|e| {
-                AppError::BadRequest(format!("Failed to read file bytes: {}", e))
-            })?.to_vec());
+            spooled = Some(spool(field, &state.config().work_dir, state.config().max_file_size).await?);
         }
     }
 
-    let bytes = file_bytes.ok_or_else(|| AppError::BadRequest("No file provided".to_string()))?;
+    let file = spooled.ok_or_else(|| AppError::BadRequest("No file provided".to_string()))?;
     let filename = filename.ok_or_else(|| AppError::BadRequest("No filename provided".to_string()))?;
 
+    ingest_file(&state, &caller, filename, file).await.map(Json)
+}
+
+/// An upload waiting on disk to be parsed. The file is deleted when this is dropped,
+/// whether it was ingested, rejected or the request gave up.
+pub struct TempFile(PathBuf);
+
+impl TempFile {
+    pub fn new(path: PathBuf) -> Self {
+        Self(path)
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+/// Streams a multipart file field to disk chunk by chunk, so concurrent uploads don't
+/// each hold a whole file in memory.
+async fn spool(mut field: Field<'_>, dir: &Path, max_size: usize) -> Result<TempFile, AppError> {
+    let path = dir.join(format!("{}.upload", Uuid::new_v4()));
+    let storage = |e: std::io::Error| AppError::Storage(format!("Failed to store upload: {}", e));
+    tokio::fs::create_dir_all(dir).await.map_err(storage)?;
+    let mut file = tokio::fs::File::create(&path).await.map_err(storage)?;
+    let spooled = TempFile::new(path);
+
+    let mut written = 0;
+    while let Some(chunk) = field
+        .chunk()
+        .await
+        .map_err(|e| AppError::BadRequest(format!("Failed to read file bytes: {}", e)))?
+    {
+        written += chunk.len();
+        if written > max_size {
+            return Err(AppError::BadRequest(format!(
+                "File exceeds the {} MB upload limit",
+                max_size / (1024 * 1024)
+            )));
+        }
+        file.write_all(&chunk).await.map_err(storage)?;
+    }
+    file.flush().await.map_err(storage)?;
+    Ok(spooled)
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct UploadUrlRequest {
+    /// Public `https://` URL of a `.gpx` or `.fit` file.
+    url: String,
+    /// Overrides the name taken from the URL path, e.g. for download links without an extension.
+    filename: Option<String>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/upload/url",
+    tag = "upload",
+    request_body = UploadUrlRequest,
+    responses(
+        (status = 200, description = "File fetched, parsed and cached", body = UploadResponse),
+        (status = 400, description = "Invalid or non-public URL, or unparseable file", body = ErrorResponse),
+        (status = 429, description = "Rate limited; see Retry-After", body = ErrorResponse),
+        (status = 502, description = "Remote server could not be reached or refused", body = ErrorResponse),
+    )
+)]
+async fn upload_url(
+    State(state): State<AppState>,
+    caller: Caller,
+    Json(req): Json<UploadUrlRequest>,
+) -> Result<Json<UploadResponse>, AppError> {
+    let url = Url::parse(&req.url)
+        .map_err(|e| AppError::BadRequest(format!("Invalid URL: {}", e)))?;
+    let filename = match req.filename {
+        Some(name) if !name.trim().is_empty() => name,
+        _ => url
+            .path_segments()
+            .and_then(|mut segments| segments.next_back())
+            .filter(|segment| !segment.is_empty())
+            .map(str::to_string)
+            .ok_or_else(|| AppError::BadRequest("Could not determine a filename from the URL".to_string()))?,
+    };
+    FileFormat::from_filename(&filename)
+        .ok_or_else(|| AppError::BadRequest("Unsupported file format".to_string()))?;
+
+    tracing::info!("Fetching {} from {}", filename, url);
+    let bytes = fetch::fetch_file(&url, state.config().max_file_size).await?;
+
+    ingest(&state, &caller, filename, bytes).await.map(Json)
+}
+
+/// Parses, processes and stores an uploaded file. Parsing runs on the blocking pool so a
+/// slow file can't stall the runtime and request timeouts can still fire.
+pub async fn ingest(
+    state: &AppState,
+    caller: &Caller,
+    filename: String,
+    bytes: Vec<u8>,
+) -> Result<UploadResponse, AppError> {
     let format = FileFormat::from_filename(&filename)
         .ok_or_else(|| AppError::BadRequest("Unsupported file format".to_string()))?;
 
     tracing::info!("Parsing {} file: {}", format_name(format), filename);
 
-    let parsed = parse::parse(&bytes, format)?;
-    let processed = process::process(&parsed)?;
+    ingest_with(state, caller, filename, format_name(format), move || {
+        Ok(parse::parse(&bytes, format)?)
+    })
+    .await
+}
+
+/// Like [`ingest`], for a file already on disk. It is memory-mapped and parsed in place
+/// on the blocking pool, then deleted.
+pub async fn ingest_file(
+    state: &AppState,
+    caller: &Caller,
+    filename: String,
+    file: TempFile,
+) -> Result<UploadResponse, AppError> {
+    let format = FileFormat::from_filename(&filename)
+        .ok_or_else(|| AppError::BadRequest("Unsupported file format".to_string()))?;
+
+    tracing::info!("Parsing {} file: {}", format_name(format), filename);
+
+    ingest_with(state, caller, filename, format_name(format), move || {
+        let storage = |e: std::io::Error| AppError::Storage(format!("Failed to read upload: {}", e));
+        let opened = File::open(&file.0).map_err(storage)?;
+        // SAFETY: the temp file belongs to this request alone and nothing writes to it
+        // once spooled, so the mapping can't change underneath the parser.
+        let mapped = unsafe { Mmap::map(&opened) }.map_err(storage)?;
+        Ok(parse::parse(&mapped, format)?)
+    })
+    .await
+}
+
+/// Like [`ingest`], for tracks that don't come as a GPX/FIT file (e.g. imports from a
+/// provider's API). `parse` runs on the blocking pool along with processing.
+pub async fn ingest_with<F>(
+    state: &AppState,
+    caller: &Caller,
+    filename: String,
+    file_type: &str,
+    parse: F,
+) -> Result<UploadResponse, AppError>
+where
+    F: FnOnce() -> Result<ParsedActivity, AppError> + Send + 'static,
+{
+    let span = tracing::Span::current();
+    let name = filename.clone();
+    let (processed, thumbnail) = tokio::task::spawn_blocking(move || {
+        span.in_scope(|| -> Result<_, AppError> {
+            let parsed = parse()?;
+            let processed = process::process(&parsed)?;
+            let thumbnail = match thumbnail::render_thumbnail(&processed) {
+                Ok(png) => Some(png),
+                Err(err) => {
+                    tracing::debug!("Skipping thumbnail for {}: {}", name, err);
+                    None
+                }
+            };
+            Ok((processed, thumbnail))
+        })
+    })
+    .await
+    .map_err(|err| AppError::BadRequest(format!("Failed to parse upload: {}", err)))??;
 
-    let file_id = Uuid::new_v4().to_string();
-    state.insert(file_id.clone(), processed.clone());
+    let file_id = caller.new_id();
+    let processed = Arc::new(processed);
+    state.insert(file_id.clone(), filename.clone(), Arc::clone(&processed), thumbnail);
 
     tracing::info!(
         "Uploaded file {} with ID {} ({} points, {:.2} km)",
@@ -62,12 +254,12 @@ async fn upload(
         processed.metrics.distance_km
     );
 
-    Ok(Json(UploadResponse {
+    Ok(UploadResponse {
         file_id,
-        file_type: format_name(format).to_string(),
-        metrics: processed.metrics,
-        available_data: processed.available_data,
-    }))
+        file_type: file_type.to_string(),
+        metrics: processed.metrics.clone(),
+        available_data: processed.available_data.clone(),
+    })
 }
 
 fn format_name(format: FileFormat) -> &'static str {
@@ -0,0 +1,44 @@
+use std::net::SocketAddr;
+
+use axum::{
+    extract::{ConnectInfo, State},
+    http::HeaderMap,
+    routing::get,
+    Json, Router,
+};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::auth::Caller;
+use crate::rate_limit;
+use crate::state::AppState;
+use crate::usage::SubjectUsage;
+
+pub fn router() -> Router<AppState> {
+    Router::new().route("/usage", get(usage))
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct UsageResponse {
+    /// `key:<name>` for API-key callers, otherwise the client IP.
+    subject: String,
+    #[serde(flatten)]
+    usage: SubjectUsage,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/usage",
+    tag = "usage",
+    responses((status = 200, description = "Renders and exports counted against the caller's API key (or IP)", body = UsageResponse))
+)]
+async fn usage(
+    State(state): State<AppState>,
+    caller: Caller,
+    peer: Option<ConnectInfo<SocketAddr>>,
+    headers: HeaderMap,
+) -> Json<UsageResponse> {
+    let subject = rate_limit::client_key(state.config(), &caller, &headers, peer.map(|ConnectInfo(addr)| addr));
+    let usage = state.usage().get(&subject);
+    Json(UsageResponse { subject, usage })
+}
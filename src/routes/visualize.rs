@@ -1,43 +1,90 @@
 use axum::{
-    extract::State,
-    http::{header, StatusCode},
-    response::IntoResponse,
+    extract::{ConnectInfo, State},
+    http::{header, HeaderMap, HeaderName, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
     routing::post,
     Json, Router,
 };
+use chrono::FixedOffset;
+use rideviz_core::error::RasterError;
+use rideviz_core::pipeline::render;
+use rideviz_core::pipeline::{animate, prepare, rasterize};
+use rideviz_core::types::{
+    activity::{AvailableData, Metrics, ProcessedActivity},
+    gradient::Gradient,
+    viz::{
+        AnimationEasing, AnimationMode, CameraMode, ColorByMetric, LoopMode, OutputConfig, OutputFormat,
+        RenderOptions, StatOverlayItem, TitleCard, TitleCards,
+    },
+};
 use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
 use std::collections::HashSet;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use utoipa::ToSchema;
+use uuid::Uuid;
 
+use crate::audit::ExportOutcome;
+use crate::auth::Caller;
+use crate::cache::{CachedRender, ProjectionCache};
+use crate::config::Config;
 use crate::error::AppError;
-use crate::pipeline::{animate, prepare, rasterize, render};
+use crate::quota::QuotaLimits;
+use crate::rate_limit;
+use crate::request_id;
 use crate::state::AppState;
-use crate::types::{
-    activity::{AvailableData, Metrics},
-    gradient::Gradient,
-    viz::{ColorByMetric, OutputConfig, OutputFormat, RenderOptions, StatOverlayItem},
-};
 
 pub fn router() -> Router<AppState> {
-    Router::new().route("/api/visualize", post(visualize))
+    Router::new().route("/visualize", post(visualize))
+}
+
+/// Kept apart from `router()` so live previews aren't counted against the render rate limit.
+pub fn preview_router() -> Router<AppState> {
+    Router::new().route("/visualize/preview", post(preview))
 }
 
-#[derive(Deserialize, Serialize)]
+#[derive(Deserialize, Serialize, ToSchema)]
 #[serde(deny_unknown_fields)]
-struct VisualizeRequest {
+pub struct VisualizeRequest {
     file_id: String,
     #[serde(default = "default_gradient")]
+    #[schema(default = "fire")]
     gradient: String,
     width: Option<u32>,
     height: Option<u32>,
+    /// `elevation`, `speed`, `heartrate` or `power`.
+    #[schema(example = "elevation")]
     color_by: Option<String>,
     #[serde(default = "default_stroke_width")]
     stroke_width: f32,
     #[serde(default = "default_padding")]
     padding: u32,
+    /// Platform preset for the safe areas: `instagram_story`, `instagram_reels`, `tiktok`
+    /// or `youtube_shorts`.
+    #[serde(default)]
+    safe_area: Option<String>,
+    /// Pixels at the top kept clear of the route, stats and title cards; overrides the
+    /// preset.
+    #[serde(default)]
+    safe_area_top: Option<u32>,
+    /// Pixels at the bottom kept clear; overrides the preset.
+    #[serde(default)]
+    safe_area_bottom: Option<u32>,
+    /// Play the route from its finish to its start.
+    #[serde(default)]
+    reverse: bool,
+    /// For loops: start this many km into the ride (negative counts back from the
+    /// finish), e.g. at a landmark.
+    #[serde(default)]
+    start_offset_km: Option<f64>,
     #[serde(default = "default_smoothing")]
     smoothing: usize,
     #[serde(default = "default_true")]
     glow: bool,
+    /// `transparent` (default), `white` or `black`.
     background: Option<String>,
     duration_seconds: Option<f32>,
     fps: Option<u32>,
@@ -45,12 +92,46 @@ struct VisualizeRequest {
     animation_frames: Option<u32>,
     #[serde(default)]
     animation_duration_ms: Option<u32>,
+    #[serde(default)]
+    easing: AnimationEasing,
+    /// What the reveal advances along: `distance` (default), `time` or `uniform`.
+    #[serde(default)]
+    animation_mode: AnimationMode,
+    #[serde(default)]
+    loop_mode: LoopMode,
+    #[serde(default)]
+    camera: CameraMode,
+    #[serde(default)]
+    rotate: bool,
+    #[serde(default)]
+    end_hold_seconds: Option<f32>,
+    #[serde(default)]
+    intro_title: Option<String>,
+    #[serde(default)]
+    intro_seconds: Option<f32>,
+    /// UTC offset for the intro card's date, e.g. `+09:00`, `-0530` or `UTC`. Defaults
+    /// to an estimate from the start point's longitude.
+    #[serde(default)]
+    #[schema(example = "+09:00")]
+    timezone: Option<String>,
+    #[serde(default)]
+    outro_seconds: Option<f32>,
     #[serde(default = "default_true")]
     watermark: bool,
     #[serde(default)]
+    #[schema(example = json!(["distance", "elevation_gain"]))]
     stats: Option<Vec<String>>,
     #[serde(default)]
     format: OutputFormat,
+    /// Client-chosen id that lets `DELETE /api/export/jobs/:id` cancel this export.
+    #[serde(default)]
+    export_id: Option<String>,
+}
+
+impl VisualizeRequest {
+    pub fn file_id(&self) -> &str {
+        &self.file_id
+    }
 }
 
 fn default_gradient() -> String {
@@ -73,27 +154,78 @@ fn default_true() -> bool {
     true
 }
 
-fn validate_dimensions(width: u32, height: u32) -> Result<(), AppError> {
-    const MIN_DIM: u32 = 320;
-    const MAX_DIM: u32 = 4096;
-    const MAX_MEGAPIXELS: f64 = 10.0;
+/// Set to `flat` when the activity has no elevation data and the route is drawn without
+/// extrusion.
+pub const FALLBACK_HEADER: HeaderName = HeaderName::from_static("x-render-fallback");
 
-    if !(MIN_DIM..=MAX_DIM).contains(&width) || !(MIN_DIM..=MAX_DIM).contains(&height) {
-        return Err(AppError::BadRequest(format!(
-            "Invalid dimensions: {}x{}. Width/height must be between {} and {}",
-            width, height, MIN_DIM, MAX_DIM
-        )));
+/// [`render_fingerprint`] of the response body.
+pub const FINGERPRINT_HEADER: HeaderName = HeaderName::from_static("x-render-fingerprint");
+
+/// Longest side of `/api/visualize/preview` output, in pixels.
+const PREVIEW_MAX_SIDE: u32 = 480;
+
+/// Shares of the height covered by each platform's UI at the top and bottom of a 9:16
+/// video, rounded up a little.
+const SAFE_AREA_PRESETS: [(&str, f64, f64); 4] = [
+    ("instagram_story", 0.13, 0.13),
+    ("instagram_reels", 0.11, 0.22),
+    ("tiktok", 0.08, 0.25),
+    ("youtube_shorts", 0.10, 0.20),
+];
+
+const DEFAULT_OUTRO_STATS: [&str; 4] = ["distance", "duration", "elevation_gain", "avg_speed"];
+const MAX_TITLE_CHARS: usize = 80;
+
+/// Output sizes a caller may request.
+#[derive(Debug, Clone, Copy)]
+pub struct DimensionLimits {
+    pub min: u32,
+    pub max: u32,
+    pub max_megapixels: f64,
+}
+
+impl Default for DimensionLimits {
+    /// The built-in limits, for renders that don't go through the server config.
+    fn default() -> Self {
+        Self {
+            min: 320,
+            max: 4096,
+            max_megapixels: 10.0,
+        }
     }
+}
 
-    let megapixels = (width as f64 * height as f64) / 1_000_000.0;
-    if megapixels > MAX_MEGAPIXELS {
-        return Err(AppError::BadRequest(format!(
-            "Image too large: {}x{} ({:.2} MP). Max allowed is {:.1} MP",
-            width, height, megapixels, MAX_MEGAPIXELS
-        )));
+impl DimensionLimits {
+    /// The configured limits, with the caller's API key overrides.
+    pub fn for_caller(config: &Config, caller: &Caller) -> Self {
+        let key = caller.api_key();
+        Self {
+            min: config.min_dimension,
+            max: key.and_then(|key| key.max_dimension).unwrap_or(config.max_dimension),
+            max_megapixels: key
+                .and_then(|key| key.max_megapixels)
+                .unwrap_or(config.max_megapixels),
+        }
     }
 
-    Ok(())
+    fn validate(&self, width: u32, height: u32) -> Result<(), AppError> {
+        if !(self.min..=self.max).contains(&width) || !(self.min..=self.max).contains(&height) {
+            return Err(AppError::BadRequest(format!(
+                "Invalid dimensions: {}x{}. Width/height must be between {} and {}",
+                width, height, self.min, self.max
+            )));
+        }
+
+        let megapixels = (width as f64 * height as f64) / 1_000_000.0;
+        if megapixels > self.max_megapixels {
+            return Err(AppError::BadRequest(format!(
+                "Image too large: {}x{} ({:.2} MP). Max allowed is {:.1} MP",
+                width, height, megapixels, self.max_megapixels
+            )));
+        }
+
+        Ok(())
+    }
 }
 
 /// Maps smoothing level (0-100) to internal route rendering parameters.
@@ -105,7 +237,7 @@ fn smoothing_to_route_params(level: usize) -> (usize, f32) {
     (simplify, tension)
 }
 
-fn format_duration(duration_seconds: u64) -> String {
+pub fn format_duration(duration_seconds: u64) -> String {
     let hours = duration_seconds / 3600;
     let minutes = (duration_seconds % 3600) / 60;
     let seconds = duration_seconds % 60;
@@ -144,6 +276,15 @@ fn stat_key_to_overlay(
         "max_power" if available_data.has_power => metrics
             .max_power
             .map(|v| ("MAX PWR".to_string(), format!("{} W", v))),
+        "avg_cadence" => metrics
+            .avg_cadence
+            .map(|v| ("AVG CAD".to_string(), format!("{} rpm", v))),
+        "max_cadence" => metrics
+            .max_cadence
+            .map(|v| ("MAX CAD".to_string(), format!("{} rpm", v))),
+        "avg_temperature" => metrics
+            .avg_temperature
+            .map(|v| ("AVG TEMP".to_string(), format!("{:.0} °C", v))),
         _ => None,
     }
 }
@@ -169,6 +310,9 @@ fn build_stats_overlay_items(
         "max_heart_rate",
         "avg_power",
         "max_power",
+        "avg_cadence",
+        "max_cadence",
+        "avg_temperature",
     ]
     .into_iter()
     .collect();
@@ -176,7 +320,7 @@ fn build_stats_overlay_items(
     for key in keys {
         if !allowed.contains(key.as_str()) {
             return Err(AppError::BadRequest(format!(
-                "Invalid stat key: {}. Allowed: distance, duration, elevation_gain, avg_speed, avg_heart_rate, max_heart_rate, avg_power, max_power",
+                "Invalid stat key: {}. Allowed: distance, duration, elevation_gain, avg_speed, avg_heart_rate, max_heart_rate, avg_power, max_power, avg_cadence, max_cadence, avg_temperature",
                 key
             )));
         }
@@ -212,19 +356,138 @@ fn build_stats_overlay_items(
         .collect())
 }
 
-async fn visualize(
-    State(state): State<AppState>,
-    Json(req): Json<VisualizeRequest>,
-) -> Result<impl IntoResponse, AppError> {
-    let processed = state
-        .get(&req.file_id)
-        .ok_or_else(|| AppError::NotFound(req.file_id.clone()))?;
+fn card_duration_ms(seconds: Option<f32>) -> Option<u32> {
+    seconds
+        .map(|secs| (secs.clamp(0.0, 10.0) * 1000.0).round() as u32)
+        .filter(|ms| *ms > 0)
+}
+
+fn build_title_cards(
+    req: &VisualizeRequest,
+    processed: &ProcessedActivity,
+    stats_overlay: &[StatOverlayItem],
+) -> Result<TitleCards, AppError> {
+    let offset = match req.timezone.as_deref() {
+        Some(timezone) => parse_utc_offset(timezone).ok_or_else(|| {
+            AppError::BadRequest(format!(
+                "Invalid timezone: {}. Use a UTC offset like '+09:00' or 'UTC'",
+                timezone
+            ))
+        })?,
+        None => estimated_offset(processed),
+    };
+    let intro = card_duration_ms(req.intro_seconds).map(|duration_ms| {
+        let heading = req
+            .intro_title
+            .as_deref()
+            .map(str::trim)
+            .filter(|title| !title.is_empty())
+            .map(|title| title.chars().take(MAX_TITLE_CHARS).collect())
+            .unwrap_or_else(|| "RIDE".to_string());
+        TitleCard {
+            heading,
+            subheading: processed
+                .start_time
+                .map(|start| start.with_timezone(&offset).format("%-d %B %Y").to_string()),
+            items: Vec::new(),
+            duration_ms,
+        }
+    });
+
+    let outro = match card_duration_ms(req.outro_seconds) {
+        Some(duration_ms) => {
+            let items = if stats_overlay.is_empty() {
+                let keys: Vec<String> = DEFAULT_OUTRO_STATS.iter().map(|k| k.to_string()).collect();
+                build_stats_overlay_items(Some(&keys), &processed.metrics, &processed.available_data)?
+            } else {
+                stats_overlay.to_vec()
+            };
+            Some(TitleCard {
+                heading: "SUMMARY".to_string(),
+                subheading: None,
+                items,
+                duration_ms,
+            })
+        }
+        None => None,
+    };
+
+    Ok(TitleCards { intro, outro })
+}
 
+/// Parses `UTC`/`Z` or a signed offset: `+9`, `+09`, `+0930` or `+09:30`.
+fn parse_utc_offset(value: &str) -> Option<FixedOffset> {
+    let value = value.trim();
+    if value.eq_ignore_ascii_case("utc") || value.eq_ignore_ascii_case("z") {
+        return FixedOffset::east_opt(0);
+    }
+    let (sign, rest) = match value.as_bytes().first()? {
+        b'+' => (1, &value[1..]),
+        b'-' => (-1, &value[1..]),
+        _ => return None,
+    };
+    let digits = rest.replace(':', "");
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let (hours, minutes) = match digits.len() {
+        1 | 2 => (digits.parse::<i32>().ok()?, 0),
+        4 => (digits[..2].parse::<i32>().ok()?, digits[2..].parse::<i32>().ok()?),
+        _ => return None,
+    };
+    if hours > 14 || minutes >= 60 {
+        return None;
+    }
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+}
+
+/// Solar time at the start point: one hour per 15 degrees of longitude. No time zone
+/// database is bundled, so this can be an hour or so off near zone borders and ignores
+/// daylight saving time; it only has to get the date right for rides in daylight.
+fn estimated_offset(processed: &ProcessedActivity) -> FixedOffset {
+    let hours = processed
+        .points
+        .first()
+        .map(|point| (point.lon / 15.0).round().clamp(-12.0, 12.0) as i32)
+        .unwrap_or(0);
+    FixedOffset::east_opt(hours * 3600).expect("offset clamped to +-12 h")
+}
+
+/// Top and bottom safe areas in pixels: the preset's shares of `height`, overridden
+/// per side by explicit values.
+fn safe_area(req: &VisualizeRequest, height: u32) -> Result<(u32, u32), AppError> {
+    let (top_share, bottom_share) = match req.safe_area.as_deref() {
+        Some(name) => SAFE_AREA_PRESETS
+            .iter()
+            .find(|(preset, ..)| preset.eq_ignore_ascii_case(name))
+            .map(|&(_, top, bottom)| (top, bottom))
+            .ok_or_else(|| {
+                let presets: Vec<&str> = SAFE_AREA_PRESETS.iter().map(|(preset, ..)| *preset).collect();
+                AppError::BadRequest(format!("Invalid safe_area: {}. Use one of: {}", name, presets.join(", ")))
+            })?,
+        None => (0.0, 0.0),
+    };
+    let top = req
+        .safe_area_top
+        .unwrap_or((height as f64 * top_share).round() as u32);
+    let bottom = req
+        .safe_area_bottom
+        .unwrap_or((height as f64 * bottom_share).round() as u32);
+    if top.saturating_add(bottom) > height / 2 {
+        return Err(AppError::BadRequest(format!(
+            "safe_area_top and safe_area_bottom may cover at most half the height ({} px)",
+            height / 2
+        )));
+    }
+    Ok((top, bottom))
+}
+
+fn build_render_options(req: &VisualizeRequest, limits: &DimensionLimits) -> Result<RenderOptions, AppError> {
     let mut options = RenderOptions::route_3d_defaults();
-    options.gradient = Gradient::get(&req.gradient).unwrap_or_else(Gradient::default);
+    options.gradient = Gradient::get(&req.gradient).unwrap_or_default();
     match (req.width, req.height) {
         (Some(width), Some(height)) => {
-            validate_dimensions(width, height)?;
+            limits.validate(width, height)?;
             options.width = width;
             options.height = height;
         }
@@ -237,6 +500,9 @@ async fn visualize(
     }
     options.stroke_width = req.stroke_width;
     options.padding = req.padding;
+    (options.safe_area_top, options.safe_area_bottom) = safe_area(req, options.height)?;
+    options.reverse = req.reverse;
+    options.start_offset_km = req.start_offset_km;
     options.smoothing = req.smoothing;
     options.glow = req.glow;
     options.color_by = match req.color_by.as_deref() {
@@ -263,6 +529,15 @@ async fn visualize(
 
     options.animation_frames = animation_frames;
     options.animation_duration_ms = animation_duration_ms;
+    options.animation_easing = req.easing;
+    options.animation_mode = req.animation_mode;
+    options.loop_mode = req.loop_mode;
+    options.camera = req.camera;
+    options.rotate = req.rotate;
+    options.end_hold_ms = req
+        .end_hold_seconds
+        .map(|secs| (secs.clamp(0.0, 10.0) * 1000.0).round() as u32)
+        .unwrap_or(0);
 
     let megapixels = (options.width as f64 * options.height as f64) / 1_000_000.0;
     let frame_ceiling = if megapixels > 6.0 {
@@ -278,68 +553,514 @@ async fn visualize(
     options.simplify = simplify;
     options.curve_tension = curve_tension;
 
-    let viz_data = prepare::prepare(&processed, &options)?;
-    let stats_overlay = build_stats_overlay_items(
-        req.stats.as_ref(),
-        &processed.metrics,
-        &processed.available_data,
-    )?;
-    
-    let background = match req.background.as_deref() {
-        Some("white") => Some((255, 255, 255, 255)),
-        Some("black") => Some((0, 0, 0, 255)),
-        Some("transparent") | None => None,
-        Some(other) => {
-            return Err(AppError::BadRequest(format!(
-                "Invalid background: {}. Use 'transparent', 'white', or 'black'",
-                other
-            )));
+    Ok(options)
+}
+
+/// Flags the render as cancelled when dropped. Axum drops the handler future when the
+/// client disconnects or the render times out, so this also stops the blocking render at
+/// its next frame (or, for a static image, before its next stage).
+struct ExportGuard {
+    state: AppState,
+    export_id: Option<String>,
+    cancel: Arc<AtomicBool>,
+}
+
+impl Drop for ExportGuard {
+    fn drop(&mut self) {
+        self.cancel.store(true, Ordering::Relaxed);
+        if let Some(export_id) = &self.export_id {
+            self.state.finish_export(export_id);
+        }
+    }
+}
+
+fn check_cancelled(cancel: &AtomicBool) -> Result<(), AppError> {
+    if cancel.load(Ordering::Relaxed) {
+        return Err(RasterError::Cancelled.into());
+    }
+    Ok(())
+}
+
+fn parse_background(background: Option<&str>) -> Result<Option<(u8, u8, u8, u8)>, AppError> {
+    match background {
+        Some("white") => Ok(Some((255, 255, 255, 255))),
+        Some("black") => Ok(Some((0, 0, 0, 255))),
+        Some("transparent") | None => Ok(None),
+        Some(other) => Err(AppError::BadRequest(format!(
+            "Invalid background: {}. Use 'transparent', 'white', or 'black'",
+            other
+        ))),
+    }
+}
+
+/// The request as it will be rendered, for ETags and fingerprints. Size, gradient,
+/// `color_by`, background, safe areas, end hold and (for animations) frame count and
+/// duration come from the resolved options, so leaving one out and sending what it
+/// resolves to (no size vs. 1920x1080, `duration_seconds` vs. the same frames) hash the
+/// same. Other fields are compared as sent, after serde's defaults. `export_id` only
+/// names the job and is left out; object keys serialize sorted.
+fn canonical_options(req: &VisualizeRequest, limits: &DimensionLimits) -> Result<serde_json::Value, AppError> {
+    let options = build_render_options(req, limits)?;
+    let background = parse_background(req.background.as_deref())?;
+    let mut canonical = serde_json::to_value(req)
+        .map_err(|e| AppError::BadRequest(format!("Failed to canonicalize request: {}", e)))?;
+    if let Some(fields) = canonical.as_object_mut() {
+        for raw in ["export_id", "safe_area", "duration_seconds", "fps", "end_hold_seconds"] {
+            fields.remove(raw);
+        }
+        let animated = is_animated(req);
+        let resolved = [
+            ("gradient", json!(options.gradient.name)),
+            ("width", json!(options.width)),
+            ("height", json!(options.height)),
+            ("color_by", json!(options.color_by)),
+            ("background", json!(background)),
+            ("safe_area_top", json!(options.safe_area_top)),
+            ("safe_area_bottom", json!(options.safe_area_bottom)),
+            ("end_hold_ms", json!(options.end_hold_ms)),
+            ("animation_frames", json!(animated.then_some(options.animation_frames))),
+            ("animation_duration_ms", json!(animated.then_some(options.animation_duration_ms))),
+        ];
+        for (name, value) in resolved {
+            fields.insert(name.to_string(), value);
+        }
+    }
+    Ok(canonical)
+}
+
+/// Deterministic ETag for a visualize request, from its canonical options. The crate
+/// version is included so renderer changes invalidate old tags.
+fn request_etag(req: &VisualizeRequest, limits: &DimensionLimits) -> Result<String, AppError> {
+    let mut hasher = Sha256::new();
+    hasher.update(env!("CARGO_PKG_VERSION").as_bytes());
+    hasher.update(canonical_options(req, limits)?.to_string().as_bytes());
+    Ok(format!("\"{:x}\"", hasher.finalize()))
+}
+
+/// Identifies the image a request produces: a hash of the activity's track digest and the
+/// canonical options, without the file id. Unlike the ETag it matches across uploads of
+/// the same file, so clients can spot identical renders.
+pub fn render_fingerprint(
+    req: &VisualizeRequest,
+    limits: &DimensionLimits,
+    processed: &ProcessedActivity,
+) -> Result<String, AppError> {
+    let mut options = canonical_options(req, limits)?;
+    if let Some(fields) = options.as_object_mut() {
+        fields.remove("file_id");
+    }
+    let mut hasher = Sha256::new();
+    hasher.update(env!("CARGO_PKG_VERSION").as_bytes());
+    hasher.update(processed.digest.as_bytes());
+    hasher.update(options.to_string().as_bytes());
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+fn etag_matches(headers: &HeaderMap, etag: &str) -> bool {
+    headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|tag| tag.trim().trim_start_matches("W/"))
+        .any(|tag| tag == "*" || tag == etag)
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/visualize",
+    tag = "visualize",
+    request_body = VisualizeRequest,
+    params(("If-None-Match" = Option<String>, Header, description = "ETag of a previous response")),
+    responses(
+        (status = 200, description = "Static PNG, or APNG when animation options are set", body = Binary, content_type = "image/png",
+            headers(
+                ("ETag" = String, description = "Tag for conditional requests"),
+                ("X-Request-Id" = String, description = "Id of the export's audit log record (echoes the request header if sent)"),
+                ("X-Export-Quota-Daily-Remaining" = u32, description = "Animated exports left today, when a daily quota applies"),
+                ("X-Export-Quota-Monthly-Remaining" = u32, description = "Animated exports left this month, when a monthly quota applies"),
+                ("X-Render-Fallback" = String, description = "`flat` when the activity has no elevation data and the route is drawn flat"),
+                ("X-Render-Fingerprint" = String, description = "Hash of the activity data and canonical options; equal for identical renders, even of separate uploads"),
+            )),
+        (status = 304, description = "Unchanged since the ETag in If-None-Match"),
+        (status = 400, description = "Invalid options or data missing for color_by", body = ErrorResponse),
+        (status = 404, description = "Unknown or expired activity", body = ErrorResponse),
+        (status = 409, description = "Export was cancelled", body = ErrorResponse),
+        (status = 429, description = "Rate limited, or export quota used up (code `export_quota_exceeded`); see Retry-After", body = ErrorResponse),
+        (status = 503, description = "No static render slot freed up in time (code `busy`); see Retry-After", body = ErrorResponse),
+    )
+)]
+async fn visualize(
+    State(state): State<AppState>,
+    caller: Caller,
+    peer: Option<ConnectInfo<SocketAddr>>,
+    headers: HeaderMap,
+    Json(req): Json<VisualizeRequest>,
+) -> Result<Response, AppError> {
+    let processed = state
+        .get(&caller, &req.file_id)
+        .ok_or_else(|| AppError::NotFound(req.file_id.clone()))?;
+
+    let limits = DimensionLimits::for_caller(state.config(), &caller);
+    let etag = request_etag(&req, &limits)?;
+    if etag_matches(&headers, &etag) {
+        tracing::debug!("Render for file {} not modified", req.file_id);
+        return Ok((StatusCode::NOT_MODIFIED, [(header::ETAG, etag)]).into_response());
+    }
+    let fingerprint = render_fingerprint(&req, &limits, &processed)?;
+
+    let subject = rate_limit::client_key(state.config(), &caller, &headers, peer.map(|ConnectInfo(addr)| addr));
+    let request_id = request_id::current().unwrap_or_else(|| Uuid::new_v4().to_string());
+
+    let response = match audited_export(&state, &caller, &subject, request_id.clone(), &req, &processed).await {
+        Ok((render, quota_headers)) => {
+            let mut response = (
+                StatusCode::OK,
+                [
+                    (header::CONTENT_TYPE, render.content_type.to_string()),
+                    (header::ETAG, etag),
+                    (FINGERPRINT_HEADER, fingerprint),
+                ],
+                render.bytes,
+            )
+                .into_response();
+            response.headers_mut().extend(quota_headers);
+            response.headers_mut().extend(fallback_header(&processed));
+            response
         }
+        Err(err) => err.into_response(),
     };
-    
-    let output_config = OutputConfig {
-        width: options.width,
-        height: options.height,
-        background,
-        watermark: req.watermark,
+    Ok(response)
+}
+
+/// Exports `req` for `subject` with the audit record and usage count every export gets,
+/// returning the render and any quota headers. Shared by `/visualize` and `/share`.
+pub async fn audited_export(
+    state: &AppState,
+    caller: &Caller,
+    subject: &str,
+    request_id: String,
+    req: &VisualizeRequest,
+    processed: &Arc<ProcessedActivity>,
+) -> Result<(CachedRender, Vec<(HeaderName, HeaderValue)>), AppError> {
+    let limits = DimensionLimits::for_caller(state.config(), caller);
+    let cache_key = format!("{}:{}", req.file_id, request_etag(req, &limits)?);
+    let mut parameters = serde_json::to_value(req).unwrap_or_default();
+    if let Some(fields) = parameters.as_object_mut() {
+        fields.remove("file_id");
+    }
+    // Dropped unfinished (client disconnect), this records the export as cancelled.
+    let audit = state.export_log().begin(
+        request_id,
+        subject.to_string(),
+        req.file_id.clone(),
+        is_animated(req),
+        parameters,
+    );
+
+    match export(state, caller, subject, req, processed, cache_key).await {
+        Ok((render, outcome, quota_headers)) => {
+            audit.finish(outcome, render.bytes.len(), None);
+            state
+                .usage()
+                .record(subject, is_animated(req), outcome, render.bytes.len());
+            Ok((render, quota_headers))
+        }
+        Err(err) => {
+            let outcome = match err {
+                AppError::Raster(RasterError::Cancelled) => ExportOutcome::Cancelled,
+                _ => ExportOutcome::Failed,
+            };
+            audit.finish(outcome, 0, Some(err.to_string()));
+            Err(err)
+        }
+    }
+}
+
+/// Serves the render from cache or produces it, enforcing the export quota.
+async fn export(
+    state: &AppState,
+    caller: &Caller,
+    subject: &str,
+    req: &VisualizeRequest,
+    processed: &Arc<ProcessedActivity>,
+    cache_key: String,
+) -> Result<(CachedRender, ExportOutcome, Vec<(HeaderName, HeaderValue)>), AppError> {
+    if let Some(render) = state.cached_render(&cache_key) {
+        tracing::info!("Serving cached render for file {}", req.file_id);
+        return Ok((render, ExportOutcome::Cached, Vec::new()));
+    }
+
+    // Animated exports count against the caller's quota; cached repeats are free.
+    let limits = QuotaLimits::for_caller(state.config(), caller);
+    let reservation = if is_animated(req) && !limits.is_unlimited() {
+        Some(state.export_quotas().reserve(subject, limits)?)
+    } else {
+        None
     };
+    let render = render_visualization(state, caller, req, processed).await?;
+    let quota_headers = reservation
+        .map(|reservation| reservation.commit().headers())
+        .unwrap_or_default();
+    state.cache_render(cache_key, render.clone());
+    Ok((render, ExportOutcome::Rendered, quota_headers))
+}
 
-    let is_static = req.duration_seconds.is_none() && req.animation_frames.is_none() && req.animation_duration_ms.is_none();
-    let image_bytes = if is_static {
-        // Static image - render single frame at progress=1.0 (full route)
+fn fallback_header(processed: &ProcessedActivity) -> Option<(HeaderName, HeaderValue)> {
+    (!processed.available_data.has_elevation).then(|| (FALLBACK_HEADER, HeaderValue::from_static("flat")))
+}
+
+fn is_animated(req: &VisualizeRequest) -> bool {
+    req.duration_seconds.is_some() || req.animation_frames.is_some() || req.animation_duration_ms.is_some()
+}
+
+/// A visualize request resolved against its activity: everything needed to render it
+/// off the async runtime. Preparing the points and projecting the route are left to
+/// [`RenderJob::render`], so they run on the render pools too.
+pub struct RenderJob {
+    processed: Arc<ProcessedActivity>,
+    /// Cache and file id to reuse the route projection of an earlier render; the route
+    /// is projected afresh without one.
+    projections: Option<(Arc<ProjectionCache>, String)>,
+    options: RenderOptions,
+    output: OutputConfig,
+    stats_overlay: Vec<StatOverlayItem>,
+    cards: TitleCards,
+    animated: bool,
+}
+
+impl RenderJob {
+    pub fn new(
+        req: &VisualizeRequest,
+        processed: &Arc<ProcessedActivity>,
+        limits: &DimensionLimits,
+    ) -> Result<Self, AppError> {
+        Self::build(req, processed, limits, None)
+    }
+
+    /// Like [`RenderJob::new`], reusing the route projection of an earlier render of the
+    /// same file when the options allow.
+    pub fn cached(
+        state: &AppState,
+        caller: &Caller,
+        req: &VisualizeRequest,
+        processed: &Arc<ProcessedActivity>,
+    ) -> Result<Self, AppError> {
+        let limits = DimensionLimits::for_caller(state.config(), caller);
+        let projections = (Arc::clone(state.projections()), req.file_id.clone());
+        Self::build(req, processed, &limits, Some(projections))
+    }
+
+    fn build(
+        req: &VisualizeRequest,
+        processed: &Arc<ProcessedActivity>,
+        limits: &DimensionLimits,
+        projections: Option<(Arc<ProjectionCache>, String)>,
+    ) -> Result<Self, AppError> {
+        let options = build_render_options(req, limits)?;
+        let stats_overlay = build_stats_overlay_items(
+            req.stats.as_ref(),
+            &processed.metrics,
+            &processed.available_data,
+        )?;
+        let output = OutputConfig {
+            width: options.width,
+            height: options.height,
+            background: parse_background(req.background.as_deref())?,
+            watermark: req.watermark,
+        };
+        let animated = is_animated(req);
+        let cards = if animated {
+            build_title_cards(req, processed, &stats_overlay)?
+        } else {
+            TitleCards::default()
+        };
+        Ok(Self {
+            processed: Arc::clone(processed),
+            projections,
+            options,
+            output,
+            stats_overlay,
+            cards,
+            animated,
+        })
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        if self.animated {
+            "image/apng"
+        } else {
+            "image/png"
+        }
+    }
+
+    /// Renders the image, blocking. Stops early with [`RasterError::Cancelled`] once
+    /// `cancel` is set: animations between frames, static images between stages.
+    pub fn render(&self, cancel: &AtomicBool) -> Result<Vec<u8>, AppError> {
+        let viz_data = prepare::prepare(&self.processed, &self.options)?;
+        let projection = match &self.projections {
+            Some((projections, file_id)) => {
+                projections.get_or_project(file_id, &viz_data, &self.options)?
+            }
+            None => Arc::new(render::project_route(&viz_data, &self.options)?),
+        };
+        if self.animated {
+            Ok(animate::render_projected_apng(
+                &viz_data,
+                &projection,
+                &self.options,
+                &self.output,
+                &self.stats_overlay,
+                &self.cards,
+                cancel,
+            )?)
+        } else {
+            // Static image - render single frame at progress=1.0 (full route)
+            check_cancelled(cancel)?;
+            let svg = render::render_projected_svg_frame(&projection, &self.options, 1.0, 0.0, &self.stats_overlay)?;
+            check_cancelled(cancel)?;
+            Ok(rasterize::rasterize(&svg, &self.output)?)
+        }
+    }
+}
+
+async fn render_visualization(
+    state: &AppState,
+    caller: &Caller,
+    req: &VisualizeRequest,
+    processed: &Arc<ProcessedActivity>,
+) -> Result<CachedRender, AppError> {
+    let job = RenderJob::cached(state, caller, req, processed)?;
+    let content_type = job.content_type();
+
+    let image_bytes = if !job.animated {
         tracing::info!(
             "Generating static route-3d image for file {} ({}x{}, gradient: {})",
             req.file_id,
-            options.width,
-            options.height,
-            options.gradient.name
+            job.options.width,
+            job.options.height,
+            job.options.gradient.name
         );
-        let svg = render::render_svg_frame(&viz_data, &options, 1.0, &stats_overlay)?;
-        rasterize::rasterize(&svg, &output_config)?
+        // Shares the route with long animated exports, so the timeout is applied here
+        // rather than as a route layer.
+        let limit = state.config().render_timeout;
+        let cancel = Arc::new(AtomicBool::new(false));
+        let _guard = ExportGuard {
+            state: state.clone(),
+            export_id: None,
+            cancel: cancel.clone(),
+        };
+        let render = state.render_pool().render_static(move || job.render(&cancel));
+        tokio::time::timeout(limit, render)
+            .await
+            .map_err(|_| AppError::Timeout(limit.as_secs()))???
     } else {
-        // Animated output
         tracing::info!(
             "Generating route-3d animation for file {} ({}x{}, gradient: {}, format: {:?})",
             req.file_id,
-            options.width,
-            options.height,
-            options.gradient.name,
+            job.options.width,
+            job.options.height,
+            job.options.gradient.name,
             req.format
         );
-        animate::render_apng(&viz_data, &options, &output_config, &stats_overlay)?
+        let (export_id, cancel) = match req.export_id.as_deref() {
+            Some(export_id) => {
+                let (export_id, cancel) = state.register_export(caller, export_id).ok_or_else(|| {
+                    AppError::BadRequest(format!("export_id already in use: {}", export_id))
+                })?;
+                (Some(export_id), cancel)
+            }
+            None => (None, Arc::new(AtomicBool::new(false))),
+        };
+        let _guard = ExportGuard {
+            state: state.clone(),
+            export_id,
+            cancel: cancel.clone(),
+        };
+        state.render_pool().animate(move || job.render(&cancel)).await??
     };
-    
-    let (content_type, description) = if is_static {
-        ("image/png", "PNG")
-    } else {
-        ("image/apng", "APNG")
+
+    tracing::info!("Generated {}: {} bytes", content_type, image_bytes.len());
+
+    Ok(CachedRender {
+        content_type,
+        bytes: image_bytes.into(),
+    })
+}
+
+/// Cheap static render for live option previews: same look as the final export,
+/// scaled down to `PREVIEW_MAX_SIDE` with glow and curve subdivision disabled.
+#[utoipa::path(
+    post,
+    path = "/api/v1/visualize/preview",
+    tag = "visualize",
+    request_body = VisualizeRequest,
+    responses(
+        (status = 200, description = "Small static PNG preview", body = Binary, content_type = "image/png"),
+        (status = 400, description = "Invalid options", body = ErrorResponse),
+        (status = 404, description = "Unknown or expired activity", body = ErrorResponse),
+        (status = 503, description = "No static render slot freed up in time (code `busy`); see Retry-After", body = ErrorResponse),
+    )
+)]
+async fn preview(
+    State(state): State<AppState>,
+    caller: Caller,
+    Json(req): Json<VisualizeRequest>,
+) -> Result<Response, AppError> {
+    let processed = state
+        .get(&caller, &req.file_id)
+        .ok_or_else(|| AppError::NotFound(req.file_id.clone()))?;
+
+    let limits = DimensionLimits::for_caller(state.config(), &caller);
+    let mut options = build_render_options(&req, &limits)?;
+    let scale = PREVIEW_MAX_SIDE as f32 / options.width.max(options.height) as f32;
+    if scale < 1.0 {
+        options.width = ((options.width as f32 * scale).round() as u32).max(1);
+        options.height = ((options.height as f32 * scale).round() as u32).max(1);
+        options.padding = (options.padding as f32 * scale).round() as u32;
+        options.safe_area_top = (options.safe_area_top as f32 * scale).round() as u32;
+        options.safe_area_bottom = (options.safe_area_bottom as f32 * scale).round() as u32;
+        options.stroke_width = (options.stroke_width * scale).max(1.0);
+    }
+    options.glow = false;
+    options.wall_subdivisions = 1;
+
+    let stats_overlay = build_stats_overlay_items(
+        req.stats.as_ref(),
+        &processed.metrics,
+        &processed.available_data,
+    )?;
+    let output_config = OutputConfig {
+        width: options.width,
+        height: options.height,
+        background: parse_background(req.background.as_deref())?,
+        watermark: req.watermark,
     };
-    
-    tracing::info!("Generated {}: {} bytes", description, image_bytes.len());
-
-    Ok((
-        StatusCode::OK,
-        [(header::CONTENT_TYPE, content_type)],
-        image_bytes,
-    ))
+
+    let (width, height) = (options.width, options.height);
+    // Preparing and projecting the route is the bulk of the work on a cache miss, so it
+    // runs in the render slot too.
+    let projections = Arc::clone(state.projections());
+    let activity = Arc::clone(&processed);
+    let file_id = req.file_id.clone();
+    let image_bytes = state
+        .render_pool()
+        .render_static(move || -> Result<Vec<u8>, AppError> {
+            let viz_data = prepare::prepare(&activity, &options)?;
+            let projection = projections.get_or_project(&file_id, &viz_data, &options)?;
+            let svg = render::render_projected_svg_frame(&projection, &options, 1.0, 0.0, &stats_overlay)?;
+            Ok(rasterize::rasterize(&svg, &output_config)?)
+        })
+        .await??;
+    tracing::debug!(
+        "Generated preview for file {} ({}x{}): {} bytes",
+        req.file_id,
+        width,
+        height,
+        image_bytes.len()
+    );
+
+    let mut response = (StatusCode::OK, [(header::CONTENT_TYPE, "image/png")], image_bytes).into_response();
+    response.headers_mut().extend(fallback_header(&processed));
+    Ok(response)
 }
@@ -0,0 +1,339 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use argon2::Argon2;
+use axum::body::Bytes;
+use chrono::{DateTime, Utc};
+use rideviz_core::types::activity::Metrics;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+use uuid::Uuid;
+
+use crate::auth::Caller;
+use crate::cache::CachedRender;
+use crate::error::AppError;
+use crate::routes::visualize::format_duration;
+
+/// Finished renders published under unguessable public links until they expire. With a
+/// data dir, images are kept as files next to a JSON index and survive restarts;
+/// otherwise they are held in memory. Either way the total is capped by `max_bytes`.
+/// Files are read and written outside the index lock; callers on the async runtime should
+/// use the blocking pool.
+pub struct ShareStore {
+    shares: Mutex<HashMap<String, Share>>,
+    /// Held while the index is written, so saves land in order.
+    saving: Mutex<()>,
+    dir: Option<PathBuf>,
+    max_bytes: usize,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Share {
+    pub content_type: String,
+    pub size: usize,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    /// Shown on the share page and in link previews.
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub summary: ShareSummary,
+    /// Image size in pixels, for link preview tags; `None` if it couldn't be read.
+    #[serde(default)]
+    pub dimensions: Option<(u32, u32)>,
+    /// API key that created it, which alone may delete it.
+    owner: Option<String>,
+    password: Option<PasswordHash>,
+    /// Only set in memory mode; on disk the image is a file named after the token.
+    #[serde(skip)]
+    bytes: Option<Bytes>,
+}
+
+/// Stats of the shared activity, for link previews.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct ShareSummary {
+    pub distance_km: f64,
+    pub duration_seconds: u64,
+    pub elevation_gain_m: f64,
+}
+
+impl ShareSummary {
+    /// e.g. "42.3 km · 1:32:10 · 850 m climbing"
+    pub fn describe(&self) -> String {
+        let mut parts = vec![format!("{:.1} km", self.distance_km)];
+        if self.duration_seconds > 0 {
+            parts.push(format_duration(self.duration_seconds));
+        }
+        if self.elevation_gain_m >= 1.0 {
+            parts.push(format!("{:.0} m climbing", self.elevation_gain_m));
+        }
+        parts.join(" · ")
+    }
+}
+
+impl From<&Metrics> for ShareSummary {
+    fn from(metrics: &Metrics) -> Self {
+        Self {
+            distance_km: metrics.distance_km,
+            duration_seconds: metrics.duration_seconds,
+            elevation_gain_m: metrics.elevation_gain_m,
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct PasswordHash {
+    #[serde(default)]
+    kdf: Kdf,
+    salt: String,
+    digest: String,
+}
+
+/// How a share password was hashed. Shares saved before Argon2id have no `kdf` in the
+/// index and keep their salted SHA-256 digest until they expire.
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Kdf {
+    #[default]
+    Sha256,
+    Argon2id,
+}
+
+impl Kdf {
+    fn hash(self, salt: &str, password: &str) -> String {
+        match self {
+            Kdf::Sha256 => format!("{:x}", Sha256::new().chain_update(salt).chain_update(password).finalize()),
+            Kdf::Argon2id => {
+                let mut digest = [0u8; 32];
+                Argon2::default()
+                    .hash_password_into(password.as_bytes(), salt.as_bytes(), &mut digest)
+                    .expect("salt and digest lengths are within Argon2 limits");
+                digest.iter().map(|byte| format!("{:02x}", byte)).collect()
+            }
+        }
+    }
+}
+
+/// Password checks allowed to hash at once. Each Argon2id hash takes ~19 MiB and tens of
+/// milliseconds, so a burst of guesses beyond this is turned away rather than queued.
+pub const MAX_PASSWORD_CHECKS: usize = 4;
+
+impl PasswordHash {
+    /// Argon2id with its default (OWASP-recommended) cost. Takes tens of milliseconds,
+    /// so call it off the async runtime.
+    fn new(password: &str) -> Self {
+        let salt = Uuid::new_v4().simple().to_string();
+        let digest = Kdf::Argon2id.hash(&salt, password);
+        Self {
+            kdf: Kdf::Argon2id,
+            salt,
+            digest,
+        }
+    }
+
+    fn matches(&self, password: &str) -> bool {
+        let digest = self.kdf.hash(&self.salt, password);
+        digest.as_bytes().ct_eq(self.digest.as_bytes()).into()
+    }
+}
+
+impl Share {
+    pub fn has_password(&self) -> bool {
+        self.password.is_some()
+    }
+
+    /// Whether `password` unlocks the share; always true without one. Hashes the
+    /// password, so call it off the async runtime.
+    pub fn unlocks(&self, password: Option<&str>) -> bool {
+        match (&self.password, password) {
+            (None, _) => true,
+            (Some(hash), Some(password)) => hash.matches(password),
+            (Some(_), None) => false,
+        }
+    }
+}
+
+impl ShareStore {
+    pub fn new(data_dir: Option<&Path>, max_bytes: usize) -> Self {
+        let dir = data_dir.map(|dir| dir.join("shares"));
+        let mut shares: HashMap<String, Share> = dir
+            .as_ref()
+            .and_then(|dir| std::fs::read(dir.join("index.json")).ok())
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .unwrap_or_default();
+        if let Some(dir) = &dir {
+            shares.retain(|token, _| dir.join(token).is_file());
+        }
+        Self {
+            shares: Mutex::new(shares),
+            saving: Mutex::new(()),
+            dir,
+            max_bytes,
+        }
+    }
+
+    /// Stores a render and returns its token with the stored metadata. Hashes the
+    /// password and writes files, so call it off the async runtime.
+    pub fn insert(
+        &self,
+        caller: &Caller,
+        render: CachedRender,
+        title: String,
+        summary: ShareSummary,
+        expires_at: DateTime<Utc>,
+        password: Option<&str>,
+    ) -> Result<(String, Share), AppError> {
+        let token = Uuid::new_v4().simple().to_string();
+        let bytes = render.bytes;
+        let mut share = Share {
+            content_type: render.content_type.to_string(),
+            size: bytes.len(),
+            created_at: Utc::now(),
+            expires_at,
+            title,
+            summary,
+            dimensions: png_dimensions(&bytes),
+            owner: caller.api_key().map(|key| key.name.clone()),
+            password: password.map(PasswordHash::new),
+            bytes: None,
+        };
+
+        let Some(dir) = &self.dir else {
+            share.bytes = Some(bytes);
+            self.reserve(&token, &share)?;
+            return Ok((token, share));
+        };
+        // Counted before the file is written so concurrent shares can't overfill the store.
+        // Nobody knows the token yet, so the entry is never read before its file exists.
+        self.reserve(&token, &share)?;
+        let written = std::fs::create_dir_all(dir).and_then(|()| std::fs::write(dir.join(&token), &bytes));
+        if let Err(e) = written {
+            self.lock().remove(&token);
+            self.delete_file(&token);
+            return Err(AppError::Storage(format!("Failed to save share: {}", e)));
+        }
+        self.persist();
+        Ok((token, share))
+    }
+
+    /// Adds `share` to the index if it fits within `max_bytes`.
+    fn reserve(&self, token: &str, share: &Share) -> Result<(), AppError> {
+        let mut shares = self.lock();
+        let used: usize = shares.values().map(|share| share.size).sum();
+        if used + share.size > self.max_bytes {
+            return Err(AppError::ShareStorageFull);
+        }
+        shares.insert(token.to_string(), share.clone());
+        Ok(())
+    }
+
+    /// A share's details without reading its image, unless unknown or expired.
+    pub fn details(&self, token: &str) -> Option<Share> {
+        self.lock()
+            .get(token)
+            .filter(|share| share.expires_at > Utc::now())
+            .cloned()
+    }
+
+    /// The share and its image, unless unknown or expired.
+    pub fn get(&self, token: &str) -> Option<(Share, Bytes)> {
+        let share = self.details(token)?;
+        let bytes = match (&share.bytes, &self.dir) {
+            (Some(bytes), _) => bytes.clone(),
+            (None, Some(dir)) => std::fs::read(dir.join(token)).ok()?.into(),
+            (None, None) => return None,
+        };
+        Some((share, bytes))
+    }
+
+    /// Deletes a share made by `caller`. False if unknown or someone else's.
+    pub fn remove(&self, caller: &Caller, token: &str) -> bool {
+        let owned = {
+            let mut shares = self.lock();
+            let owned = shares.get(token).is_some_and(|share| match caller.api_key() {
+                Some(key) => share.owner.as_deref() == Some(key.name.as_str()),
+                None => true,
+            });
+            if owned {
+                shares.remove(token);
+            }
+            owned
+        };
+        if owned {
+            self.delete_file(token);
+            self.persist();
+        }
+        owned
+    }
+
+    /// Deletes expired shares.
+    pub fn prune(&self) {
+        let now = Utc::now();
+        let mut expired = Vec::new();
+        self.lock().retain(|token, share| {
+            let keep = share.expires_at > now;
+            if !keep {
+                expired.push(token.clone());
+            }
+            keep
+        });
+        if expired.is_empty() {
+            return;
+        }
+        for token in &expired {
+            self.delete_file(token);
+        }
+        tracing::info!("Deleted {} expired shares", expired.len());
+        self.persist();
+    }
+
+    /// Number of shares and their total size, for the admin API.
+    pub fn usage(&self) -> (usize, usize) {
+        let shares = self.lock();
+        (shares.len(), shares.values().map(|share| share.size).sum())
+    }
+
+    fn delete_file(&self, token: &str) {
+        if let Some(dir) = &self.dir {
+            let _ = std::fs::remove_file(dir.join(token));
+        }
+    }
+
+    /// Saves the index as it is now. The snapshot is taken after `saving` is held, so a
+    /// later save never loses to an earlier one.
+    fn persist(&self) {
+        let Some(dir) = &self.dir else {
+            return;
+        };
+        let _saving = self.saving.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let path = dir.join("index.json");
+        let snapshot = serde_json::to_vec(&*self.lock());
+        let result = snapshot
+            .map_err(std::io::Error::other)
+            .and_then(|json| {
+                std::fs::create_dir_all(dir)?;
+                let tmp = path.with_extension("tmp");
+                std::fs::write(&tmp, json)?;
+                std::fs::rename(&tmp, &path)
+            });
+        if let Err(err) = result {
+            tracing::warn!("Failed to save share index to {}: {}", path.display(), err);
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Share>> {
+        self.shares.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Width and height from a PNG (or APNG) header.
+fn png_dimensions(bytes: &[u8]) -> Option<(u32, u32)> {
+    if bytes.get(..8)? != b"\x89PNG\r\n\x1a\n" || bytes.get(12..16)? != b"IHDR" {
+        return None;
+    }
+    let width = u32::from_be_bytes(bytes.get(16..20)?.try_into().ok()?);
+    let height = u32::from_be_bytes(bytes.get(20..24)?.try_into().ok()?);
+    Some((width, height))
+}
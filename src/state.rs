@@ -1,44 +1,381 @@
-use crate::types::activity::ProcessedActivity;
+use crate::audit::ExportLog;
+use crate::auth::{ApiKeys, Caller};
+use crate::cache::{CachedRender, ProjectionCache, RenderCache};
+use crate::config::Config;
+use crate::error::AppError;
+use crate::import::Providers;
+use crate::quota::ExportQuotas;
+use crate::rate_limit::KeyedRateLimiter;
+use crate::render_pool::RenderPool;
+use crate::share::{ShareStore, MAX_PASSWORD_CHECKS};
+use crate::telegram::TelegramBot;
+use crate::store::{ActivityEntry, ActivityStore, StoredActivity};
+use crate::usage::UsageStats;
+use crate::work_dir;
+use chrono::{DateTime, Utc};
 use dashmap::DashMap;
+use rideviz_core::types::activity::ProcessedActivity;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, Semaphore};
 
 #[derive(Clone)]
 pub struct AppState {
-    cache: Arc<DashMap<String, CachedActivity>>,
+    config: Arc<Config>,
+    activities: Arc<dyn ActivityStore>,
+    exports: Arc<DashMap<String, Arc<AtomicBool>>>,
+    uploads: Arc<DashMap<String, UploadSlot>>,
+    /// Held while counting an owner's uploads and registering a new one, so two starts
+    /// from the same client can't both slip under the cap.
+    upload_admission: Arc<std::sync::Mutex<()>>,
+    renders: Arc<RenderCache>,
+    projections: Arc<ProjectionCache>,
+    rate_limiter: Arc<KeyedRateLimiter>,
+    render_pool: Arc<RenderPool>,
+    api_keys: Arc<ApiKeys>,
+    export_quotas: Arc<ExportQuotas>,
+    export_log: Arc<ExportLog>,
+    usage: Arc<UsageStats>,
+    import_providers: Arc<Providers>,
+    shares: Arc<ShareStore>,
+    password_checks: Arc<Semaphore>,
+    telegram: Option<Arc<TelegramBot>>,
 }
 
-struct CachedActivity {
-    activity: ProcessedActivity,
-    inserted_at: Instant,
+/// A resumable upload that is still receiving chunks, assembled in a temp file.
+pub struct PendingUpload {
+    pub filename: String,
+    pub size: u64,
+    /// Bytes received so far; the next chunk must start here.
+    pub offset: u64,
+    pub path: PathBuf,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A registered upload. The owner sits outside the lock so the per-client cap can be
+/// checked without waiting on uploads that are busy receiving a chunk.
+struct UploadSlot {
+    /// Rate limit key of the client that started it.
+    owner: String,
+    upload: Arc<Mutex<PendingUpload>>,
+}
+
+/// Snapshot of what the service is holding, for the admin API.
+pub struct CacheStats {
+    pub activities: Vec<ActivityEntry>,
+    pub renders: usize,
+    pub render_bytes: usize,
+    pub render_max_bytes: usize,
+    pub pending_uploads: usize,
+    pub running_exports: usize,
+    pub shares: usize,
+    pub share_bytes: usize,
+    pub share_max_bytes: usize,
 }
 
 impl AppState {
-    pub fn new() -> Self {
+    pub fn new(config: Config, activities: Arc<dyn ActivityStore>) -> Self {
+        let renders = RenderCache::new(config.render_cache_bytes, config.render_cache_ttl);
+        let rate_limiter = KeyedRateLimiter::new(config.rate_limit_per_minute, config.rate_limit_burst);
+        let render_pool = RenderPool::new(
+            config.render_threads,
+            config.max_static_renders,
+            config.static_render_queue_timeout,
+        );
+        let api_keys = ApiKeys::new(&config.api_keys);
+        let export_quotas = ExportQuotas::new(config.data_dir.as_deref());
+        let export_log = ExportLog::open(config.audit_log.as_deref())
+            .unwrap_or_else(|e| panic!("Failed to open export audit log: {}", e));
+        let usage = UsageStats::new(config.data_dir.as_deref());
+        let import_providers = Providers::from_config(&config);
+        let shares = ShareStore::new(config.data_dir.as_deref(), config.share_max_bytes);
+        let telegram = config
+            .telegram_bot_token
+            .clone()
+            .map(|token| Arc::new(TelegramBot::new(token)));
         Self {
-            cache: Arc::new(DashMap::new()),
+            config: Arc::new(config),
+            activities,
+            exports: Arc::new(DashMap::new()),
+            uploads: Arc::new(DashMap::new()),
+            upload_admission: Arc::new(std::sync::Mutex::new(())),
+            renders: Arc::new(renders),
+            projections: Arc::new(ProjectionCache::new()),
+            rate_limiter: Arc::new(rate_limiter),
+            render_pool: Arc::new(render_pool),
+            api_keys: Arc::new(api_keys),
+            export_quotas: Arc::new(export_quotas),
+            export_log: Arc::new(export_log),
+            usage: Arc::new(usage),
+            import_providers: Arc::new(import_providers),
+            shares: Arc::new(shares),
+            password_checks: Arc::new(Semaphore::new(MAX_PASSWORD_CHECKS)),
+            telegram,
         }
     }
 
-    pub fn insert(&self, file_id: String, activity: ProcessedActivity) {
-        self.cache.insert(
-            file_id,
-            CachedActivity {
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    pub fn rate_limiter(&self) -> &KeyedRateLimiter {
+        &self.rate_limiter
+    }
+
+    pub fn projections(&self) -> &Arc<ProjectionCache> {
+        &self.projections
+    }
+
+    pub fn render_pool(&self) -> &RenderPool {
+        &self.render_pool
+    }
+
+    pub fn api_keys(&self) -> &ApiKeys {
+        &self.api_keys
+    }
+
+    pub fn export_quotas(&self) -> &ExportQuotas {
+        &self.export_quotas
+    }
+
+    pub fn export_log(&self) -> &Arc<ExportLog> {
+        &self.export_log
+    }
+
+    pub fn usage(&self) -> &UsageStats {
+        &self.usage
+    }
+
+    pub fn import_providers(&self) -> &Providers {
+        &self.import_providers
+    }
+
+    pub fn shares(&self) -> &ShareStore {
+        &self.shares
+    }
+
+    /// Slots for hashing share passwords; see [`MAX_PASSWORD_CHECKS`].
+    pub fn password_checks(&self) -> &Arc<Semaphore> {
+        &self.password_checks
+    }
+
+    /// The bot, when a Telegram token is configured.
+    pub fn telegram(&self) -> Option<&Arc<TelegramBot>> {
+        self.telegram.as_ref()
+    }
+
+    pub fn insert(
+        &self,
+        file_id: String,
+        name: String,
+        activity: Arc<ProcessedActivity>,
+        thumbnail: Option<Vec<u8>>,
+    ) {
+        self.activities.insert(
+            &file_id,
+            StoredActivity {
+                name,
                 activity,
-                inserted_at: Instant::now(),
+                thumbnail,
+                uploaded_at: Utc::now(),
+            },
+        );
+
+        // Enforced here rather than left to the periodic sweep, so a burst of uploads
+        // can't pile up in memory in between.
+        let max = self.config.max_cached_activities;
+        if max > 0 {
+            let evicted = self.activities.evict_oldest(max);
+            for file_id in &evicted {
+                self.renders.remove_file(file_id);
+                self.projections.remove_file(file_id);
+            }
+            if !evicted.is_empty() {
+                tracing::info!("Evicted {} activities over the cap of {}", evicted.len(), max);
+            }
+        }
+    }
+
+    // Lookups are scoped to the caller: with API keys, a key only sees its own uploads.
+
+    pub fn get(&self, caller: &Caller, file_id: &str) -> Option<Arc<ProcessedActivity>> {
+        caller.owns(file_id).then(|| self.activities.get(file_id)).flatten()
+    }
+
+    pub fn thumbnail(&self, caller: &Caller, file_id: &str) -> Option<Vec<u8>> {
+        caller.owns(file_id).then(|| self.activities.thumbnail(file_id)).flatten()
+    }
+
+    pub fn list(&self, caller: &Caller) -> Vec<ActivityEntry> {
+        let mut entries = self.activities.list();
+        entries.retain(|entry| caller.owns(&entry.file_id));
+        entries
+    }
+
+    pub fn ping_store(&self) -> Result<(), String> {
+        self.activities.ping()
+    }
+
+    pub fn remove(&self, caller: &Caller, file_id: &str) -> bool {
+        if !caller.owns(file_id) {
+            return false;
+        }
+        self.renders.remove_file(file_id);
+        self.projections.remove_file(file_id);
+        self.activities.remove(file_id)
+    }
+
+    pub fn cached_render(&self, key: &str) -> Option<CachedRender> {
+        self.renders.get(key)
+    }
+
+    pub fn cache_render(&self, key: String, render: CachedRender) {
+        self.renders.insert(key, render);
+    }
+
+    /// Registers a running export under a client-chosen id and returns its cancel flag
+    /// with the id to pass to `finish_export`. Returns `None` if the id is already in use.
+    pub fn register_export(&self, caller: &Caller, export_id: &str) -> Option<(String, Arc<AtomicBool>)> {
+        let export_id = caller.scoped(export_id);
+        match self.exports.entry(export_id.clone()) {
+            dashmap::mapref::entry::Entry::Occupied(_) => None,
+            dashmap::mapref::entry::Entry::Vacant(entry) => {
+                let flag = Arc::new(AtomicBool::new(false));
+                entry.insert(flag.clone());
+                Some((export_id, flag))
+            }
+        }
+    }
+
+    pub fn cancel_export(&self, caller: &Caller, export_id: &str) -> bool {
+        match self.exports.get(&caller.scoped(export_id)) {
+            Some(flag) => {
+                flag.store(true, Ordering::Relaxed);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn finish_export(&self, export_id: &str) {
+        self.exports.remove(export_id);
+    }
+
+    /// Registers an upload unless its owner already has `max_pending_uploads` open.
+    /// `owner` is the rate limit key of the client starting it.
+    pub fn start_upload(
+        &self,
+        upload_id: String,
+        owner: String,
+        upload: PendingUpload,
+    ) -> Result<(), AppError> {
+        let max = self.config.max_pending_uploads;
+        let _admission = self
+            .upload_admission
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if max > 0 {
+            let open = self.uploads.iter().filter(|entry| entry.owner == owner).count();
+            if open >= max {
+                return Err(AppError::TooManyPendingUploads(open));
+            }
+        }
+        self.uploads.insert(
+            upload_id,
+            UploadSlot {
+                owner,
+                upload: Arc::new(Mutex::new(upload)),
             },
         );
+        Ok(())
+    }
+
+    pub fn pending_upload(&self, caller: &Caller, upload_id: &str) -> Option<Arc<Mutex<PendingUpload>>> {
+        if !caller.owns(upload_id) {
+            return None;
+        }
+        self.uploads.get(upload_id).map(|entry| Arc::clone(&entry.upload))
+    }
+
+    pub fn finish_upload(&self, upload_id: &str) {
+        self.uploads.remove(upload_id);
     }
 
-    pub fn get(&self, file_id: &str) -> Option<ProcessedActivity> {
-        self.cache.get(file_id).map(|entry| entry.activity.clone())
+    pub fn cache_stats(&self) -> CacheStats {
+        let (renders, render_bytes) = self.renders.usage();
+        let (shares, share_bytes) = self.shares.usage();
+        CacheStats {
+            activities: self.activities.list(),
+            renders,
+            render_bytes,
+            render_max_bytes: self.renders.max_bytes(),
+            pending_uploads: self.uploads.len(),
+            running_exports: self.exports.len(),
+            shares,
+            share_bytes,
+            share_max_bytes: self.config.share_max_bytes,
+        }
     }
 
-    pub fn evict_expired(&self, ttl: Duration) {
-        let now = Instant::now();
-        self.cache.retain(|_, cached| {
-            now.duration_since(cached.inserted_at) < ttl
+    /// Drops every cached activity and render. Returns how many of each were removed.
+    pub fn flush_caches(&self) -> (usize, usize) {
+        let activities = self
+            .activities
+            .list()
+            .into_iter()
+            .filter(|entry| self.activities.remove(&entry.file_id))
+            .count();
+        let renders = self.renders.clear();
+        self.projections.clear();
+        tracing::info!("Flushed {} activities and {} renders", activities, renders);
+        (activities, renders)
+    }
+
+    /// Writes export quotas and usage stats to the data dir if they changed. Blocking.
+    pub fn flush_counters(&self) {
+        self.export_quotas.flush();
+        self.usage.flush();
+    }
+
+    /// How long a scratch file can go unwritten before it is considered orphaned: longer
+    /// than both an abandoned chunked upload and the slowest allowed upload are kept.
+    pub fn scratch_max_age(&self) -> std::time::Duration {
+        self.config.cache_ttl.max(self.config.upload_timeout)
+    }
+
+    pub fn evict_expired(&self) {
+        let ttl = chrono::Duration::from_std(self.config.cache_ttl).unwrap_or(chrono::Duration::MAX);
+        let cutoff = Utc::now().checked_sub_signed(ttl).unwrap_or(chrono::DateTime::<Utc>::MIN_UTC);
+        let remaining = self.activities.evict_before(cutoff);
+
+        // Uploads busy receiving a chunk are skipped until the next pass.
+        self.uploads.retain(|upload_id, slot| match slot.upload.try_lock() {
+            Ok(upload) if upload.created_at < cutoff => {
+                tracing::info!("Discarding abandoned upload {}", upload_id);
+                let _ = std::fs::remove_file(&upload.path);
+                false
+            }
+            _ => true,
         });
-        tracing::info!("Cache eviction complete. Current size: {}", self.cache.len());
+
+        let swept = work_dir::sweep(&self.config.work_dir, self.scratch_max_age());
+        if swept > 0 {
+            tracing::info!("Removed {} stale files from the work dir", swept);
+        }
+
+        self.renders.evict_expired();
+        self.rate_limiter.prune();
+        self.export_quotas.prune();
+        self.usage.prune();
+        self.shares.prune();
+        let (renders, render_bytes) = self.renders.usage();
+
+        tracing::info!(
+            "Cache eviction complete. Current size: {} activities, {} renders ({} KB)",
+            remaining,
+            renders,
+            render_bytes / 1024
+        );
     }
 }
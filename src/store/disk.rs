@@ -0,0 +1,136 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use rideviz_core::types::activity::ProcessedActivity;
+
+use crate::store::{ActivityEntry, ActivityStore, MemoryStore, StoredActivity, StoredRecord};
+
+/// Write-through store: reads are served from memory, every change is mirrored to
+/// `<dir>/<file_id>.json` (plus `<file_id>.png` for the thumbnail) and reloaded on startup.
+pub struct DiskStore {
+    dir: PathBuf,
+    memory: MemoryStore,
+}
+
+impl DiskStore {
+    pub fn open(dir: impl Into<PathBuf>) -> io::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        let store = Self {
+            dir,
+            memory: MemoryStore::new(),
+        };
+
+        let mut loaded = 0;
+        for entry in fs::read_dir(&store.dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+                continue;
+            }
+            let Some(file_id) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                continue;
+            };
+            match read_record(&path) {
+                Ok(record) => {
+                    let thumbnail = fs::read(store.thumbnail_path(file_id)).ok();
+                    store.memory.insert(file_id, record.into_stored(thumbnail));
+                    loaded += 1;
+                }
+                Err(err) => tracing::warn!("Skipping unreadable activity {}: {}", path.display(), err),
+            }
+        }
+        tracing::info!("Loaded {} activities from {}", loaded, store.dir.display());
+        Ok(store)
+    }
+
+    fn record_path(&self, file_id: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", file_id))
+    }
+
+    fn thumbnail_path(&self, file_id: &str) -> PathBuf {
+        self.dir.join(format!("{}.png", file_id))
+    }
+
+    fn persist(&self, file_id: &str, entry: &StoredActivity) -> io::Result<()> {
+        let json = serde_json::to_vec(&StoredRecord::from(entry)).map_err(io::Error::other)?;
+        write_atomic(&self.record_path(file_id), &json)?;
+        if let Some(thumbnail) = &entry.thumbnail {
+            write_atomic(&self.thumbnail_path(file_id), thumbnail)?;
+        }
+        Ok(())
+    }
+
+    fn delete_files(&self, file_id: &str) {
+        for path in [self.record_path(file_id), self.thumbnail_path(file_id)] {
+            if let Err(err) = fs::remove_file(&path) {
+                if err.kind() != io::ErrorKind::NotFound {
+                    tracing::warn!("Failed to delete {}: {}", path.display(), err);
+                }
+            }
+        }
+    }
+}
+
+impl ActivityStore for DiskStore {
+    fn insert(&self, file_id: &str, entry: StoredActivity) {
+        if let Err(err) = self.persist(file_id, &entry) {
+            tracing::warn!("Failed to persist activity {}: {}", file_id, err);
+        }
+        self.memory.insert(file_id, entry);
+    }
+
+    fn get(&self, file_id: &str) -> Option<Arc<ProcessedActivity>> {
+        self.memory.get(file_id)
+    }
+
+    fn thumbnail(&self, file_id: &str) -> Option<Vec<u8>> {
+        self.memory.thumbnail(file_id)
+    }
+
+    fn list(&self) -> Vec<ActivityEntry> {
+        self.memory.list()
+    }
+
+    fn remove(&self, file_id: &str) -> bool {
+        // Only ids known to the in-memory index ever reach the filesystem.
+        let removed = self.memory.remove(file_id);
+        if removed {
+            self.delete_files(file_id);
+        }
+        removed
+    }
+
+    fn evict_before(&self, cutoff: DateTime<Utc>) -> usize {
+        for file_id in self.memory.expired_ids(cutoff) {
+            self.remove(&file_id);
+        }
+        self.memory.evict_before(cutoff)
+    }
+
+    fn evict_oldest(&self, keep: usize) -> Vec<String> {
+        let mut evicted = self.memory.oldest_ids(keep);
+        evicted.retain(|file_id| self.remove(file_id));
+        evicted
+    }
+
+    fn ping(&self) -> Result<(), String> {
+        let probe = self.dir.join(".ready");
+        write_atomic(&probe, b"ok")
+            .and_then(|_| fs::remove_file(&probe))
+            .map_err(|err| format!("{} is not writable: {}", self.dir.display(), err))
+    }
+}
+
+fn read_record(path: &Path) -> io::Result<StoredRecord> {
+    let bytes = fs::read(path)?;
+    serde_json::from_slice(&bytes).map_err(io::Error::other)
+}
+
+fn write_atomic(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, bytes)?;
+    fs::rename(&tmp, path)
+}
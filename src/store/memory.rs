@@ -0,0 +1,88 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use rideviz_core::types::activity::ProcessedActivity;
+
+use crate::store::{ActivityEntry, ActivityStore, StoredActivity};
+
+/// Process-local store. Everything is lost on restart.
+#[derive(Default)]
+pub struct MemoryStore {
+    entries: DashMap<String, StoredActivity>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub(super) fn expired_ids(&self, cutoff: DateTime<Utc>) -> Vec<String> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.uploaded_at < cutoff)
+            .map(|entry| entry.key().clone())
+            .collect()
+    }
+
+    /// Ids of all but the `keep` most recent uploads.
+    pub(super) fn oldest_ids(&self, keep: usize) -> Vec<String> {
+        if self.entries.len() <= keep {
+            return Vec::new();
+        }
+        let mut entries: Vec<(DateTime<Utc>, String)> = self
+            .entries
+            .iter()
+            .map(|entry| (entry.uploaded_at, entry.key().clone()))
+            .collect();
+        entries.sort_unstable();
+        let excess = entries.len().saturating_sub(keep);
+        entries.into_iter().take(excess).map(|(_, file_id)| file_id).collect()
+    }
+}
+
+impl ActivityStore for MemoryStore {
+    fn insert(&self, file_id: &str, entry: StoredActivity) {
+        self.entries.insert(file_id.to_string(), entry);
+    }
+
+    fn get(&self, file_id: &str) -> Option<Arc<ProcessedActivity>> {
+        self.entries.get(file_id).map(|entry| Arc::clone(&entry.activity))
+    }
+
+    fn thumbnail(&self, file_id: &str) -> Option<Vec<u8>> {
+        self.entries
+            .get(file_id)
+            .and_then(|entry| entry.thumbnail.clone())
+    }
+
+    fn list(&self) -> Vec<ActivityEntry> {
+        let mut entries: Vec<ActivityEntry> = self
+            .entries
+            .iter()
+            .map(|entry| ActivityEntry {
+                file_id: entry.key().clone(),
+                name: entry.name.clone(),
+                distance_km: entry.activity.metrics.distance_km,
+                uploaded_at: entry.uploaded_at,
+            })
+            .collect();
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.uploaded_at));
+        entries
+    }
+
+    fn remove(&self, file_id: &str) -> bool {
+        self.entries.remove(file_id).is_some()
+    }
+
+    fn evict_before(&self, cutoff: DateTime<Utc>) -> usize {
+        self.entries.retain(|_, entry| entry.uploaded_at >= cutoff);
+        self.entries.len()
+    }
+
+    fn evict_oldest(&self, keep: usize) -> Vec<String> {
+        let mut evicted = self.oldest_ids(keep);
+        evicted.retain(|file_id| self.entries.remove(file_id).is_some());
+        evicted
+    }
+}
@@ -0,0 +1,91 @@
+mod disk;
+mod memory;
+mod redis;
+mod s3;
+
+pub use disk::DiskStore;
+pub use memory::MemoryStore;
+pub use self::redis::RedisStore;
+pub use s3::S3Store;
+
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use rideviz_core::types::activity::ProcessedActivity;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone)]
+pub struct StoredActivity {
+    pub name: String,
+    /// Shared so reads hand out a reference count rather than a copy of every point.
+    pub activity: Arc<ProcessedActivity>,
+    pub thumbnail: Option<Vec<u8>>,
+    pub uploaded_at: DateTime<Utc>,
+}
+
+/// Serialized form shared by the persistent stores. Thumbnails are kept as separate
+/// PNG blobs so they can be served without decoding the activity.
+#[derive(Serialize, Deserialize)]
+struct StoredRecord {
+    name: String,
+    uploaded_at: DateTime<Utc>,
+    activity: ProcessedActivity,
+}
+
+impl StoredRecord {
+    fn into_stored(self, thumbnail: Option<Vec<u8>>) -> StoredActivity {
+        StoredActivity {
+            name: self.name,
+            activity: Self::activity_of(self.activity),
+            thumbnail,
+            uploaded_at: self.uploaded_at,
+        }
+    }
+
+    /// The record's activity, with its digest filled in if it was saved without one.
+    fn activity_of(mut activity: ProcessedActivity) -> Arc<ProcessedActivity> {
+        if activity.digest.is_empty() {
+            activity.digest = activity.compute_digest();
+        }
+        Arc::new(activity)
+    }
+}
+
+impl From<&StoredActivity> for StoredRecord {
+    fn from(entry: &StoredActivity) -> Self {
+        Self {
+            name: entry.name.clone(),
+            uploaded_at: entry.uploaded_at,
+            activity: ProcessedActivity::clone(&entry.activity),
+        }
+    }
+}
+
+pub struct ActivityEntry {
+    pub file_id: String,
+    pub name: String,
+    pub distance_km: f64,
+    pub uploaded_at: DateTime<Utc>,
+}
+
+/// Backing storage for uploaded activities. Implementations must be cheap to read
+/// from; `get` is on the hot path of every render.
+pub trait ActivityStore: Send + Sync {
+    fn insert(&self, file_id: &str, entry: StoredActivity);
+    fn get(&self, file_id: &str) -> Option<Arc<ProcessedActivity>>;
+    fn thumbnail(&self, file_id: &str) -> Option<Vec<u8>>;
+    /// All entries, newest first.
+    fn list(&self) -> Vec<ActivityEntry>;
+    fn remove(&self, file_id: &str) -> bool;
+    /// Drops entries uploaded before `cutoff` and returns how many remain.
+    fn evict_before(&self, cutoff: DateTime<Utc>) -> usize;
+    /// Drops the oldest entries held in memory beyond `keep` and returns their ids.
+    /// Stores that don't keep activities in this process have nothing to drop.
+    fn evict_oldest(&self, _keep: usize) -> Vec<String> {
+        Vec::new()
+    }
+    /// Checks that the backing storage is reachable, for readiness probes.
+    fn ping(&self) -> Result<(), String> {
+        Ok(())
+    }
+}
@@ -0,0 +1,250 @@
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use lru::LruCache;
+use redis::{Commands, Connection, RedisResult};
+use rideviz_core::types::activity::ProcessedActivity;
+use serde::{Deserialize, Serialize};
+
+use crate::store::{ActivityEntry, ActivityStore, StoredActivity, StoredRecord};
+
+/// Shared store for multi-replica deployments. Records and thumbnails are stored under
+/// `<prefix>:activity:<id>` / `<prefix>:thumbnail:<id>` with the cache TTL as expiry, so
+/// Redis drops stale uploads even if no replica runs eviction. `<prefix>:index` is a hash
+/// of listing metadata keyed by file id.
+///
+/// Only activities are shared: rate limits, export quotas and pending chunked uploads are
+/// still counted per replica.
+pub struct RedisStore {
+    client: redis::Client,
+    /// Connections not in use. Each call takes one (or opens one), so a slow call doesn't
+    /// hold up the others.
+    idle: Mutex<Vec<Connection>>,
+    /// Activities decoded on this replica, so a render doesn't fetch and parse the whole
+    /// record again.
+    decoded: Mutex<LruCache<String, Arc<ProcessedActivity>>>,
+    prefix: String,
+    ttl: Duration,
+}
+
+/// Idle connections kept open for reuse.
+const MAX_IDLE_CONNECTIONS: usize = 16;
+
+/// Decoded activities kept per replica.
+const DECODED_ENTRIES: NonZeroUsize = match NonZeroUsize::new(64) {
+    Some(entries) => entries,
+    None => unreachable!(),
+};
+
+#[derive(Serialize, Deserialize)]
+struct IndexEntry {
+    name: String,
+    distance_km: f64,
+    uploaded_at: DateTime<Utc>,
+}
+
+impl RedisStore {
+    pub fn open(url: &str, prefix: &str, ttl: Duration) -> RedisResult<Self> {
+        let client = redis::Client::open(url)?;
+        let connection = client.get_connection()?;
+        tracing::info!("Storing activities in Redis under {}:*", prefix);
+        Ok(Self {
+            client,
+            idle: Mutex::new(vec![connection]),
+            decoded: Mutex::new(LruCache::new(DECODED_ENTRIES)),
+            prefix: prefix.to_string(),
+            ttl,
+        })
+    }
+
+    fn record_key(&self, file_id: &str) -> String {
+        format!("{}:activity:{}", self.prefix, file_id)
+    }
+
+    fn thumbnail_key(&self, file_id: &str) -> String {
+        format!("{}:thumbnail:{}", self.prefix, file_id)
+    }
+
+    fn index_key(&self) -> String {
+        format!("{}:index", self.prefix)
+    }
+
+    /// Runs `op` on an idle connection, or a new one if none is free. The trait is
+    /// synchronous, so the call is made without stalling the runtime's workers. Connections
+    /// that failed with an I/O error are dropped rather than reused.
+    fn with_connection<T>(&self, op: impl FnOnce(&mut Connection) -> RedisResult<T>) -> RedisResult<T> {
+        tokio::task::block_in_place(|| {
+            let idle = lock(&self.idle).pop();
+            let mut connection = match idle {
+                Some(connection) => connection,
+                None => self.client.get_connection()?,
+            };
+            let result = op(&mut connection);
+            let broken = matches!(&result, Err(err) if err.is_io_error() || err.is_connection_dropped());
+            if !broken {
+                let mut idle = lock(&self.idle);
+                if idle.len() < MAX_IDLE_CONNECTIONS {
+                    idle.push(connection);
+                }
+            }
+            result
+        })
+    }
+
+    fn index(&self) -> RedisResult<Vec<(String, IndexEntry)>> {
+        let raw: Vec<(String, String)> = self.with_connection(|conn| conn.hgetall(self.index_key()))?;
+        Ok(raw
+            .into_iter()
+            .filter_map(|(file_id, json)| {
+                serde_json::from_str(&json).ok().map(|entry| (file_id, entry))
+            })
+            .collect())
+    }
+}
+
+impl ActivityStore for RedisStore {
+    fn insert(&self, file_id: &str, entry: StoredActivity) {
+        let record = match serde_json::to_vec(&StoredRecord::from(&entry)) {
+            Ok(record) => record,
+            Err(err) => {
+                tracing::warn!("Failed to serialize activity {}: {}", file_id, err);
+                return;
+            }
+        };
+        let index_entry = IndexEntry {
+            name: entry.name,
+            distance_km: entry.activity.metrics.distance_km,
+            uploaded_at: entry.uploaded_at,
+        };
+        let index_json = serde_json::to_string(&index_entry).unwrap_or_default();
+        let activity = Arc::clone(&entry.activity);
+        let ttl_secs = self.ttl.as_secs().max(1);
+
+        let result = self.with_connection(|conn| {
+            let mut pipe = redis::pipe();
+            pipe.atomic()
+                .set_ex(self.record_key(file_id), record, ttl_secs)
+                .ignore()
+                .hset(self.index_key(), file_id, index_json)
+                .ignore();
+            if let Some(thumbnail) = &entry.thumbnail {
+                pipe.set_ex(self.thumbnail_key(file_id), thumbnail.as_slice(), ttl_secs)
+                    .ignore();
+            }
+            pipe.query::<()>(conn)
+        });
+        match result {
+            Ok(()) => {
+                lock(&self.decoded).put(file_id.to_string(), activity);
+            }
+            Err(err) => tracing::warn!("Failed to store activity {} in Redis: {}", file_id, err),
+        }
+    }
+
+    fn get(&self, file_id: &str) -> Option<Arc<ProcessedActivity>> {
+        let decoded = lock(&self.decoded).get(file_id).cloned();
+        if let Some(activity) = decoded {
+            // Another replica may have deleted it, or it expired; checking is far cheaper
+            // than fetching the record.
+            let exists: bool = self
+                .with_connection(|conn| conn.exists(self.record_key(file_id)))
+                .map_err(|err| tracing::warn!("Failed to check activity {} in Redis: {}", file_id, err))
+                .ok()?;
+            if exists {
+                return Some(activity);
+            }
+            lock(&self.decoded).pop(file_id);
+            return None;
+        }
+        let bytes: Option<Vec<u8>> = self
+            .with_connection(|conn| conn.get(self.record_key(file_id)))
+            .map_err(|err| tracing::warn!("Failed to read activity {} from Redis: {}", file_id, err))
+            .ok()
+            .flatten();
+        let record: StoredRecord = serde_json::from_slice(&bytes?)
+            .map_err(|err| tracing::warn!("Skipping unreadable activity {}: {}", file_id, err))
+            .ok()?;
+        let activity = StoredRecord::activity_of(record.activity);
+        lock(&self.decoded).put(file_id.to_string(), Arc::clone(&activity));
+        Some(activity)
+    }
+
+    fn thumbnail(&self, file_id: &str) -> Option<Vec<u8>> {
+        self.with_connection(|conn| conn.get(self.thumbnail_key(file_id)))
+            .map_err(|err| tracing::warn!("Failed to read thumbnail {} from Redis: {}", file_id, err))
+            .ok()
+            .flatten()
+    }
+
+    fn list(&self) -> Vec<ActivityEntry> {
+        let index = match self.index() {
+            Ok(index) => index,
+            Err(err) => {
+                tracing::warn!("Failed to list activities in Redis: {}", err);
+                return Vec::new();
+            }
+        };
+        let mut entries: Vec<ActivityEntry> = index
+            .into_iter()
+            .map(|(file_id, entry)| ActivityEntry {
+                file_id,
+                name: entry.name,
+                distance_km: entry.distance_km,
+                uploaded_at: entry.uploaded_at,
+            })
+            .collect();
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.uploaded_at));
+        entries
+    }
+
+    fn remove(&self, file_id: &str) -> bool {
+        lock(&self.decoded).pop(file_id);
+        let result = self.with_connection(|conn| {
+            redis::pipe()
+                .atomic()
+                .del(self.record_key(file_id))
+                .del(self.thumbnail_key(file_id))
+                .ignore()
+                .hdel(self.index_key(), file_id)
+                .ignore()
+                .query::<(u32,)>(conn)
+        });
+        match result {
+            Ok((removed,)) => removed > 0,
+            Err(err) => {
+                tracing::warn!("Failed to delete activity {} from Redis: {}", file_id, err);
+                false
+            }
+        }
+    }
+
+    fn ping(&self) -> Result<(), String> {
+        self.with_connection(|conn| redis::cmd("PING").query::<String>(conn))
+            .map(drop)
+            .map_err(|err| err.to_string())
+    }
+
+    fn evict_before(&self, cutoff: DateTime<Utc>) -> usize {
+        // Records expire on their own; this only prunes their listing entries.
+        let index = match self.index() {
+            Ok(index) => index,
+            Err(err) => {
+                tracing::warn!("Failed to list activities in Redis: {}", err);
+                return 0;
+            }
+        };
+        let (expired, live): (Vec<_>, Vec<_>) = index
+            .into_iter()
+            .partition(|(_, entry)| entry.uploaded_at < cutoff);
+        for (file_id, _) in &expired {
+            self.remove(file_id);
+        }
+        live.len()
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
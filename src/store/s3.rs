@@ -0,0 +1,238 @@
+use std::future::Future;
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use futures::TryStreamExt;
+use object_store::aws::AmazonS3Builder;
+use object_store::path::Path;
+use object_store::{ObjectMeta, ObjectStore, PutPayload};
+use rideviz_core::types::activity::ProcessedActivity;
+use tokio::runtime::Handle;
+
+use crate::config::S3Config;
+use crate::store::{ActivityEntry, ActivityStore, MemoryStore, StoredActivity, StoredRecord};
+
+/// Bucket-backed store for multi-replica deployments. The bucket is the source of truth;
+/// the in-memory copy is only a read cache, so an activity uploaded to one replica can be
+/// rendered by any other. Objects live at `<prefix>/activities/<file_id>.{json,png}`.
+pub struct S3Store {
+    bucket: Arc<dyn ObjectStore>,
+    prefix: Path,
+    runtime: Handle,
+    cache: MemoryStore,
+}
+
+impl S3Store {
+    /// Must be called from within the Tokio runtime that will serve requests.
+    pub fn open(config: &S3Config) -> Result<Self, object_store::Error> {
+        let mut builder = AmazonS3Builder::from_env().with_bucket_name(&config.bucket);
+        if let Some(region) = &config.region {
+            builder = builder.with_region(region);
+        }
+        if let Some(endpoint) = &config.endpoint {
+            builder = builder
+                .with_endpoint(endpoint)
+                .with_allow_http(endpoint.starts_with("http://"));
+        }
+        if let Some(key_id) = &config.access_key_id {
+            builder = builder.with_access_key_id(key_id);
+        }
+        if let Some(secret) = &config.secret_access_key {
+            builder = builder.with_secret_access_key(secret);
+        }
+
+        let store = Self {
+            bucket: Arc::new(builder.build()?),
+            prefix: Path::from(config.prefix.as_str()).child("activities"),
+            runtime: Handle::current(),
+            cache: MemoryStore::new(),
+        };
+        tracing::info!("Storing activities in s3://{}/{}", config.bucket, store.prefix);
+        Ok(store)
+    }
+
+    fn record_path(&self, file_id: &str) -> Path {
+        self.prefix.child(format!("{}.json", file_id))
+    }
+
+    fn thumbnail_path(&self, file_id: &str) -> Path {
+        self.prefix.child(format!("{}.png", file_id))
+    }
+
+    /// The trait is synchronous, so bridge into the runtime without stalling its workers.
+    fn block_on<F: Future>(&self, future: F) -> F::Output {
+        tokio::task::block_in_place(|| self.runtime.block_on(future))
+    }
+
+    async fn persist(&self, file_id: &str, entry: &StoredActivity) -> object_store::Result<()> {
+        let json = serde_json::to_vec(&StoredRecord::from(entry)).map_err(|err| {
+            object_store::Error::Generic {
+                store: "S3",
+                source: Box::new(err),
+            }
+        })?;
+        self.bucket
+            .put(&self.record_path(file_id), PutPayload::from(json))
+            .await?;
+        if let Some(thumbnail) = &entry.thumbnail {
+            self.bucket
+                .put(&self.thumbnail_path(file_id), PutPayload::from(thumbnail.clone()))
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Fetches an activity uploaded through another replica into the local cache.
+    async fn fetch(&self, file_id: &str) -> object_store::Result<Option<StoredActivity>> {
+        let bytes = match self.bucket.get(&self.record_path(file_id)).await {
+            Ok(result) => result.bytes().await?,
+            Err(object_store::Error::NotFound { .. }) => return Ok(None),
+            Err(err) => return Err(err),
+        };
+        let record: StoredRecord = match serde_json::from_slice(&bytes) {
+            Ok(record) => record,
+            Err(err) => {
+                tracing::warn!("Skipping unreadable activity {}: {}", file_id, err);
+                return Ok(None);
+            }
+        };
+        let thumbnail = match self.bucket.get(&self.thumbnail_path(file_id)).await {
+            Ok(result) => Some(result.bytes().await?.to_vec()),
+            Err(object_store::Error::NotFound { .. }) => None,
+            Err(err) => return Err(err),
+        };
+        Ok(Some(record.into_stored(thumbnail)))
+    }
+
+    async fn list_records(&self) -> object_store::Result<Vec<ObjectMeta>> {
+        let objects: Vec<ObjectMeta> = self.bucket.list(Some(&self.prefix)).try_collect().await?;
+        Ok(objects
+            .into_iter()
+            .filter(|meta| meta.location.extension() == Some("json"))
+            .collect())
+    }
+
+    async fn delete(&self, file_id: &str) -> object_store::Result<()> {
+        for path in [self.record_path(file_id), self.thumbnail_path(file_id)] {
+            match self.bucket.delete(&path).await {
+                Ok(()) | Err(object_store::Error::NotFound { .. }) => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(())
+    }
+
+    fn load(&self, file_id: &str) -> bool {
+        match self.block_on(self.fetch(file_id)) {
+            Ok(Some(entry)) => {
+                self.cache.insert(file_id, entry);
+                true
+            }
+            Ok(None) => false,
+            Err(err) => {
+                tracing::warn!("Failed to fetch activity {} from S3: {}", file_id, err);
+                false
+            }
+        }
+    }
+}
+
+fn file_id_of(meta: &ObjectMeta) -> Option<String> {
+    meta.location
+        .filename()
+        .and_then(|name| name.strip_suffix(".json"))
+        .map(str::to_string)
+}
+
+impl ActivityStore for S3Store {
+    fn insert(&self, file_id: &str, entry: StoredActivity) {
+        if let Err(err) = self.block_on(self.persist(file_id, &entry)) {
+            tracing::warn!("Failed to persist activity {} to S3: {}", file_id, err);
+        }
+        self.cache.insert(file_id, entry);
+    }
+
+    fn get(&self, file_id: &str) -> Option<Arc<ProcessedActivity>> {
+        self.cache
+            .get(file_id)
+            .or_else(|| self.load(file_id).then(|| self.cache.get(file_id)).flatten())
+    }
+
+    fn thumbnail(&self, file_id: &str) -> Option<Vec<u8>> {
+        if self.cache.get(file_id).is_none() {
+            self.load(file_id);
+        }
+        self.cache.thumbnail(file_id)
+    }
+
+    fn list(&self) -> Vec<ActivityEntry> {
+        let records = match self.block_on(self.list_records()) {
+            Ok(records) => records,
+            Err(err) => {
+                tracing::warn!("Failed to list activities in S3: {}", err);
+                return self.cache.list();
+            }
+        };
+        let remote: Vec<String> = records.iter().filter_map(file_id_of).collect();
+        for file_id in &remote {
+            if self.cache.get(file_id).is_none() {
+                self.load(file_id);
+            }
+        }
+        // Drop cached entries another replica has deleted.
+        for entry in self.cache.list() {
+            if !remote.contains(&entry.file_id) {
+                self.cache.remove(&entry.file_id);
+            }
+        }
+        self.cache.list()
+    }
+
+    fn remove(&self, file_id: &str) -> bool {
+        let cached = self.cache.remove(file_id);
+        let existed = cached || self.block_on(self.bucket.head(&self.record_path(file_id))).is_ok();
+        if existed {
+            if let Err(err) = self.block_on(self.delete(file_id)) {
+                tracing::warn!("Failed to delete activity {} from S3: {}", file_id, err);
+            }
+        }
+        existed
+    }
+
+    fn ping(&self) -> Result<(), String> {
+        self.block_on(self.bucket.list_with_delimiter(Some(&self.prefix)))
+            .map(drop)
+            .map_err(|err| err.to_string())
+    }
+
+    fn evict_before(&self, cutoff: DateTime<Utc>) -> usize {
+        // Records are written once at upload, so the object timestamp tracks `uploaded_at`.
+        let records = match self.block_on(self.list_records()) {
+            Ok(records) => records,
+            Err(err) => {
+                tracing::warn!("Failed to list activities in S3: {}", err);
+                return self.cache.evict_before(cutoff);
+            }
+        };
+        let mut remaining = 0;
+        for meta in &records {
+            let Some(file_id) = file_id_of(meta) else {
+                continue;
+            };
+            if meta.last_modified < cutoff {
+                if let Err(err) = self.block_on(self.delete(&file_id)) {
+                    tracing::warn!("Failed to delete activity {} from S3: {}", file_id, err);
+                }
+            } else {
+                remaining += 1;
+            }
+        }
+        self.cache.evict_before(cutoff);
+        remaining
+    }
+
+    fn evict_oldest(&self, keep: usize) -> Vec<String> {
+        // Only the read cache is trimmed; the bucket still has them for the next `get`.
+        self.cache.evict_oldest(keep)
+    }
+}
@@ -0,0 +1,142 @@
+use std::time::Duration;
+
+use reqwest::multipart::{Form, Part};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::error::AppError;
+
+const API_URL: &str = "https://api.telegram.org";
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Bots may only download files up to this size through the Bot API.
+pub const MAX_DOWNLOAD_BYTES: usize = 20 * 1024 * 1024;
+
+/// Minimal Telegram Bot API client: just what the webhook needs to fetch a document and
+/// reply to it.
+pub struct TelegramBot {
+    client: reqwest::Client,
+    token: String,
+}
+
+/// Every Bot API method answers with this envelope.
+#[derive(Deserialize)]
+struct Envelope<T> {
+    ok: bool,
+    result: Option<T>,
+    description: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct File {
+    file_path: Option<String>,
+}
+
+impl TelegramBot {
+    pub fn new(token: String) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(TIMEOUT)
+            .build()
+            .unwrap_or_else(|e| panic!("failed to build Telegram client: {}", e));
+        Self { client, token }
+    }
+
+    /// Downloads a file the bot received, up to `max_bytes`.
+    pub async fn download(&self, file_id: &str, max_bytes: usize) -> Result<Vec<u8>, AppError> {
+        let file: File = self
+            .call("getFile", self.json("getFile", json!({ "file_id": file_id })))
+            .await?;
+        let path = file
+            .file_path
+            .ok_or_else(|| AppError::Fetch("Telegram returned no file path".to_string()))?;
+        let mut response = self
+            .client
+            .get(format!("{}/file/bot{}/{}", API_URL, self.token, path))
+            .send()
+            .await
+            .map_err(transport)?;
+        if !response.status().is_success() {
+            return Err(AppError::Fetch(format!("Telegram responded with {}", response.status())));
+        }
+        let mut bytes = Vec::new();
+        while let Some(chunk) = response.chunk().await.map_err(transport)? {
+            if bytes.len() + chunk.len() > max_bytes {
+                return Err(AppError::BadRequest(format!(
+                    "File exceeds the {} MB upload limit",
+                    max_bytes / (1024 * 1024)
+                )));
+            }
+            bytes.extend_from_slice(&chunk);
+        }
+        Ok(bytes)
+    }
+
+    /// Replies to `message_id` with a PNG.
+    pub async fn send_photo(
+        &self,
+        chat_id: i64,
+        message_id: i64,
+        png: Vec<u8>,
+        caption: &str,
+    ) -> Result<(), AppError> {
+        let photo = Part::bytes(png)
+            .file_name("rideviz.png")
+            .mime_str("image/png")
+            .map_err(transport)?;
+        let form = Form::new()
+            .text("chat_id", chat_id.to_string())
+            .text("caption", caption.to_string())
+            .text("reply_parameters", json!({ "message_id": message_id }).to_string())
+            .part("photo", photo);
+        let request = self.method("sendPhoto").multipart(form);
+        self.call::<serde_json::Value>("sendPhoto", request).await.map(drop)
+    }
+
+    /// Replies to `message_id` with plain text.
+    pub async fn send_message(&self, chat_id: i64, message_id: i64, text: &str) -> Result<(), AppError> {
+        let body = json!({
+            "chat_id": chat_id,
+            "text": text,
+            "reply_parameters": { "message_id": message_id },
+        });
+        let request = self.json("sendMessage", body);
+        self.call::<serde_json::Value>("sendMessage", request).await.map(drop)
+    }
+
+    fn method(&self, method: &str) -> reqwest::RequestBuilder {
+        self.client
+            .post(format!("{}/bot{}/{}", API_URL, self.token, method))
+    }
+
+    fn json(&self, method: &str, body: serde_json::Value) -> reqwest::RequestBuilder {
+        self.method(method)
+            .header("content-type", "application/json")
+            .body(body.to_string())
+    }
+
+    async fn call<T: DeserializeOwned>(&self, method: &str, request: reqwest::RequestBuilder) -> Result<T, AppError> {
+        let bytes = request
+            .send()
+            .await
+            .map_err(transport)?
+            .bytes()
+            .await
+            .map_err(transport)?;
+        let envelope: Envelope<T> = serde_json::from_slice(&bytes)
+            .map_err(|e| AppError::Fetch(format!("Unexpected Telegram {} response: {}", method, e)))?;
+        match envelope.result {
+            Some(result) if envelope.ok => Ok(result),
+            _ => Err(AppError::Fetch(format!(
+                "Telegram {} failed: {}",
+                method,
+                envelope.description.unwrap_or_else(|| "no description".to_string())
+            ))),
+        }
+    }
+}
+
+/// Drops the URL from transport errors: it contains the bot token.
+fn transport(err: reqwest::Error) -> AppError {
+    AppError::Fetch(format!("Telegram: {}", err.without_url()))
+}
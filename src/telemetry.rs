@@ -0,0 +1,66 @@
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use opentelemetry_sdk::Resource;
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+
+use crate::config::{Config, LogFormat};
+
+/// Installs the global tracing subscriber. When an OTLP endpoint is configured, spans are
+/// also exported over OTLP/HTTP. Keep the returned provider alive for the life of the
+/// process and shut it down on exit so the last batch gets flushed.
+pub fn init(config: &Config) -> Option<SdkTracerProvider> {
+    let provider = config.otlp_endpoint.as_deref().and_then(|endpoint| {
+        match build_provider(endpoint) {
+            Ok(provider) => Some(provider),
+            Err(err) => {
+                eprintln!("Failed to set up OTLP exporter for {}: {}", endpoint, err);
+                None
+            }
+        }
+    });
+    let otel_layer = provider
+        .as_ref()
+        .map(|provider| tracing_opentelemetry::layer().with_tracer(provider.tracer("rideviz-rs")));
+
+    let (text_layer, json_layer) = match config.log_format {
+        LogFormat::Text => (Some(tracing_subscriber::fmt::layer()), None),
+        LogFormat::Json => (None, Some(tracing_subscriber::fmt::layer().json())),
+    };
+
+    tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| "rideviz_rs=info,tower_http=debug".into()),
+        )
+        .with(text_layer)
+        .with(json_layer)
+        .with(otel_layer)
+        .init();
+
+    if let Some(endpoint) = &config.otlp_endpoint {
+        if provider.is_some() {
+            tracing::info!("Exporting traces to {}", endpoint);
+        }
+    }
+    provider
+}
+
+fn build_provider(endpoint: &str) -> Result<SdkTracerProvider, opentelemetry_otlp::ExporterBuildError> {
+    // Same convention as OTEL_EXPORTER_OTLP_ENDPOINT: the base URL of the collector.
+    let endpoint = endpoint.trim_end_matches('/');
+    let traces_url = if endpoint.ends_with("/v1/traces") {
+        endpoint.to_string()
+    } else {
+        format!("{}/v1/traces", endpoint)
+    };
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_http()
+        .with_endpoint(traces_url)
+        .build()?;
+
+    Ok(SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(Resource::builder().with_service_name("rideviz-rs").build())
+        .build())
+}
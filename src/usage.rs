@@ -0,0 +1,197 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::audit::ExportOutcome;
+
+/// Successful `/visualize` responses for one subject in some period.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct UsageCounts {
+    /// Static images rendered.
+    pub renders: u64,
+    /// Animated exports rendered.
+    pub exports: u64,
+    /// Responses served from the render cache.
+    pub cached: u64,
+    /// Image bytes sent.
+    pub bytes: u64,
+}
+
+impl UsageCounts {
+    fn add(&mut self, animated: bool, outcome: ExportOutcome, bytes: usize) {
+        match outcome {
+            ExportOutcome::Cached => self.cached += 1,
+            _ if animated => self.exports += 1,
+            _ => self.renders += 1,
+        }
+        self.bytes += bytes as u64;
+    }
+}
+
+/// One subject's usage as of now; periods that have rolled over read as zero.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct SubjectUsage {
+    /// The current UTC day.
+    pub today: UsageCounts,
+    /// The current UTC month.
+    pub this_month: UsageCounts,
+    /// Since the subject was first seen.
+    pub total: UsageCounts,
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+/// Render and export counts per subject (`key:<name>` or client IP), for fair-use review
+/// and so callers can see their own usage. Unlike export quotas this counts every
+/// successful render, whether or not a quota applies. Survives restarts when a data dir
+/// is configured (written by [`UsageStats::flush`]).
+pub struct UsageStats {
+    usage: Mutex<HashMap<String, Entry>>,
+    path: Option<PathBuf>,
+    /// Set when the counts changed since the last flush.
+    dirty: AtomicBool,
+    saving: Mutex<()>,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+struct Entry {
+    day: Option<NaiveDate>,
+    today: UsageCounts,
+    /// First day of the month being counted.
+    month: Option<NaiveDate>,
+    this_month: UsageCounts,
+    total: UsageCounts,
+    last_used_at: Option<DateTime<Utc>>,
+}
+
+impl Entry {
+    /// Resets counters whose period has rolled over.
+    fn roll(&mut self, today: NaiveDate) {
+        if self.day != Some(today) {
+            self.day = Some(today);
+            self.today = UsageCounts::default();
+        }
+        let month = first_of_month(today);
+        if self.month != Some(month) {
+            self.month = Some(month);
+            self.this_month = UsageCounts::default();
+        }
+    }
+
+    fn snapshot(&self, today: NaiveDate) -> SubjectUsage {
+        let mut entry = self.clone();
+        entry.roll(today);
+        SubjectUsage {
+            today: entry.today,
+            this_month: entry.this_month,
+            total: entry.total,
+            last_used_at: entry.last_used_at,
+        }
+    }
+}
+
+impl UsageStats {
+    pub fn new(data_dir: Option<&Path>) -> Self {
+        let path = data_dir.map(|dir| dir.join("usage").join("usage.json"));
+        let usage = path
+            .as_ref()
+            .and_then(|path| std::fs::read(path).ok())
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .unwrap_or_default();
+        Self {
+            usage: Mutex::new(usage),
+            path,
+            dirty: AtomicBool::new(false),
+            saving: Mutex::new(()),
+        }
+    }
+
+    /// Counts one successful `/visualize` response against `subject`.
+    pub fn record(&self, subject: &str, animated: bool, outcome: ExportOutcome, bytes: usize) {
+        let now = Utc::now();
+        let mut usage = self.lock();
+        let entry = usage.entry(subject.to_string()).or_default();
+        entry.roll(now.date_naive());
+        for counts in [&mut entry.today, &mut entry.this_month, &mut entry.total] {
+            counts.add(animated, outcome, bytes);
+        }
+        entry.last_used_at = Some(now);
+        self.dirty.store(true, Ordering::Relaxed);
+    }
+
+    /// Usage for `subject`, all zero if it hasn't rendered anything.
+    pub fn get(&self, subject: &str) -> SubjectUsage {
+        let today = Utc::now().date_naive();
+        self.lock()
+            .get(subject)
+            .cloned()
+            .unwrap_or_default()
+            .snapshot(today)
+    }
+
+    /// Every subject with its usage, most exports this month first.
+    pub fn all(&self) -> Vec<(String, SubjectUsage)> {
+        let today = Utc::now().date_naive();
+        let mut all: Vec<_> = self
+            .lock()
+            .iter()
+            .map(|(subject, entry)| (subject.clone(), entry.snapshot(today)))
+            .collect();
+        all.sort_by(|(a_subject, a), (b_subject, b)| {
+            b.this_month
+                .exports
+                .cmp(&a.this_month.exports)
+                .then(b.this_month.renders.cmp(&a.this_month.renders))
+                .then(a_subject.cmp(b_subject))
+        });
+        all
+    }
+
+    /// Forgets client IPs with nothing counted this month. API keys are kept for their
+    /// totals.
+    pub fn prune(&self) {
+        let this_month = first_of_month(Utc::now().date_naive());
+        self.lock()
+            .retain(|subject, entry| subject.starts_with("key:") || entry.month == Some(this_month));
+        self.dirty.store(true, Ordering::Relaxed);
+    }
+
+    /// Writes the counts to disk if they changed since the last flush. Blocking; called
+    /// periodically and on shutdown so requests never wait on the file.
+    pub fn flush(&self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let _saving = self.saving.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if !self.dirty.swap(false, Ordering::Relaxed) {
+            return;
+        }
+        let usage = self.lock().clone();
+        let result = serde_json::to_vec(&usage)
+            .map_err(std::io::Error::other)
+            .and_then(|json| {
+                if let Some(dir) = path.parent() {
+                    std::fs::create_dir_all(dir)?;
+                }
+                let tmp = path.with_extension("tmp");
+                std::fs::write(&tmp, json)?;
+                std::fs::rename(&tmp, path)
+            });
+        if let Err(err) = result {
+            self.dirty.store(true, Ordering::Relaxed);
+            tracing::warn!("Failed to save usage stats to {}: {}", path.display(), err);
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Entry>> {
+        self.usage.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+fn first_of_month(day: NaiveDate) -> NaiveDate {
+    day.with_day(1).expect("every month has a first day")
+}
@@ -0,0 +1,60 @@
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::time::Instant;
+
+use rideviz_core::pipeline::{parse, process};
+use rideviz_core::types::activity::{FileFormat, ProcessedActivity};
+use serde_json::{json, Value};
+
+use crate::error::AppError;
+use crate::routes::visualize::{DimensionLimits, RenderJob, VisualizeRequest};
+use crate::state::AppState;
+
+/// A short loop with elevation and timestamps, so every pipeline stage has data to work on.
+const ROUTE: &str = r#"<?xml version="1.0"?>
+<gpx version="1.1" creator="rideviz-rs warm-up"><trk><name>Warm-up</name><trkseg>
+<trkpt lat="47.0000" lon="8.0800"><ele>400</ele><time>2026-01-01T00:00:00Z</time></trkpt>
+<trkpt lat="47.0010" lon="8.0810"><ele>410</ele><time>2026-01-01T00:00:30Z</time></trkpt>
+<trkpt lat="47.0015" lon="8.0830"><ele>425</ele><time>2026-01-01T00:01:00Z</time></trkpt>
+<trkpt lat="47.0005" lon="8.0840"><ele>415</ele><time>2026-01-01T00:01:30Z</time></trkpt>
+<trkpt lat="47.0000" lon="8.0800"><ele>400</ele><time>2026-01-01T00:02:00Z</time></trkpt>
+</trkseg></trk></gpx>"#;
+
+/// Renders a minimum-size still (with a stats overlay, so the fonts load) and an
+/// eight-frame animation, the fewest an animation gets, on the pools real requests use.
+/// A failure is logged rather than fatal: the service still works, the first render is
+/// just slower.
+pub async fn run(state: &AppState) {
+    let started = Instant::now();
+    match warm_up(state).await {
+        Ok(()) => tracing::info!("Warm-up finished in {}ms", started.elapsed().as_millis()),
+        Err(err) => tracing::warn!("Warm-up failed: {}", err),
+    }
+}
+
+async fn warm_up(state: &AppState) -> Result<(), AppError> {
+    let parsed = parse::parse(ROUTE.as_bytes(), FileFormat::Gpx)?;
+    let processed = Arc::new(process::process(&parsed)?);
+
+    let still = job(json!({ "stats": ["distance", "elevation_gain"] }), &processed)?;
+    state
+        .render_pool()
+        .render_static(move || still.render(&AtomicBool::new(false)))
+        .await??;
+
+    let animation = job(json!({ "animation_frames": 8 }), &processed)?;
+    state
+        .render_pool()
+        .animate(move || animation.render(&AtomicBool::new(false)))
+        .await??;
+    Ok(())
+}
+
+fn job(mut body: Value, processed: &Arc<ProcessedActivity>) -> Result<RenderJob, AppError> {
+    body["file_id"] = json!("warm-up");
+    body["width"] = json!(320);
+    body["height"] = json!(320);
+    let req: VisualizeRequest =
+        serde_json::from_value(body).map_err(|e| AppError::BadRequest(e.to_string()))?;
+    RenderJob::new(&req, processed, &DimensionLimits::default())
+}
@@ -0,0 +1,52 @@
+use std::io;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+/// Files written to the work dir: multipart uploads spooled while they're parsed, and
+/// chunked uploads being assembled.
+const SCRATCH_EXTENSIONS: [&str; 2] = ["upload", "part"];
+
+/// Creates the work dir. On Unix it is only accessible to this user, since it holds
+/// other people's uploads.
+pub fn create(dir: &Path) -> io::Result<()> {
+    let mut builder = std::fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+    builder.create(dir)
+}
+
+/// Deletes scratch files that haven't been written to for `max_age`: leftovers of a
+/// crash or a killed process, which the normal cleanup never reaches. Going by age
+/// rather than by what this process knows about keeps it safe when several instances
+/// share the directory. Returns how many files were removed.
+pub fn sweep(dir: &Path, max_age: Duration) -> usize {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return 0,
+        Err(err) => {
+            tracing::warn!("Failed to scan work dir {}: {}", dir.display(), err);
+            return 0;
+        }
+    };
+    let Some(cutoff) = SystemTime::now().checked_sub(max_age) else {
+        return 0;
+    };
+
+    let mut removed = 0;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let is_scratch = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| SCRATCH_EXTENSIONS.contains(&ext));
+        let is_stale = entry
+            .metadata()
+            .and_then(|metadata| metadata.modified())
+            .is_ok_and(|modified| modified < cutoff);
+        if is_scratch && is_stale && std::fs::remove_file(&path).is_ok() {
+            removed += 1;
+        }
+    }
+    removed
+}